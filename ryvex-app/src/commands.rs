@@ -1,3 +1,5 @@
+use alloc::format;
+
 use crate::keymap::EditorCommand::{
 	self,
 	*,
@@ -8,8 +10,17 @@ use crate::{
 		EventResult,
 	},
 	define_keymaps,
+	editor::document::Mode,
+	keymap::KeyNode,
+};
+use ryvex_core::motion::{
+	AsKey,
+	MetaOperator,
+	MotionType,
+	NavigationMotion,
+	Range,
+	Scope,
 };
-use ryvex_core::motion::NavigationMotion;
 use ryvex_core::{
 	error_chain,
 	motion::{
@@ -17,6 +28,7 @@ use ryvex_core::{
 		MotionType::*,
 		NavigationMotion::*,
 	},
+	MarkTable,
 };
 
 pub fn to_insert_mode(cx: &mut Context) -> EventResult {
@@ -29,6 +41,11 @@ pub fn to_command_mode(cx: &mut Context) -> EventResult {
 	EventResult::Consumed(None)
 }
 
+pub fn to_selection_command_mode(cx: &mut Context) -> EventResult {
+	cx.editor.enter_selection_command_mode(false);
+	EventResult::Consumed(None)
+}
+
 pub fn quit_editor(cx: &mut Context) -> EventResult {
 	cx.editor.quit();
 	EventResult::Consumed(None)
@@ -39,6 +56,71 @@ pub fn write_active_document(cx: &mut Context) -> EventResult {
 	EventResult::Consumed(None)
 }
 
+pub fn undo(cx: &mut Context) -> EventResult {
+	cx.editor.undo();
+	EventResult::Consumed(None)
+}
+
+pub fn redo(cx: &mut Context) -> EventResult {
+	cx.editor.redo();
+	EventResult::Consumed(None)
+}
+
+pub fn undo_earlier(cx: &mut Context) -> EventResult {
+	cx.editor.earlier(1);
+	EventResult::Consumed(None)
+}
+
+pub fn undo_later(cx: &mut Context) -> EventResult {
+	cx.editor.later(1);
+	EventResult::Consumed(None)
+}
+
+pub fn select_next_match(cx: &mut Context) -> EventResult {
+	cx.editor.select_next_match();
+	EventResult::Consumed(None)
+}
+
+pub fn split_selections(cx: &mut Context) -> EventResult {
+	cx.editor.split_selections();
+	EventResult::Consumed(None)
+}
+
+pub fn collapse_selections(cx: &mut Context) -> EventResult {
+	cx.editor.collapse_selections();
+	EventResult::Consumed(None)
+}
+
+pub fn to_visual_mode(cx: &mut Context) -> EventResult {
+	cx.editor.toggle_visual_mode(Mode::Visual);
+	EventResult::Consumed(None)
+}
+
+pub fn to_visual_line_mode(cx: &mut Context) -> EventResult {
+	cx.editor.toggle_visual_mode(Mode::VisualLine);
+	EventResult::Consumed(None)
+}
+
+pub fn to_visual_block_mode(cx: &mut Context) -> EventResult {
+	cx.editor.toggle_visual_mode(Mode::VisualBlock);
+	EventResult::Consumed(None)
+}
+
+pub fn flip_selections(cx: &mut Context) -> EventResult {
+	cx.editor.flip_selections();
+	EventResult::Consumed(None)
+}
+
+pub fn block_insert(cx: &mut Context) -> EventResult {
+	cx.editor.block_insert(false);
+	EventResult::Consumed(None)
+}
+
+pub fn block_append(cx: &mut Context) -> EventResult {
+	cx.editor.block_insert(true);
+	EventResult::Consumed(None)
+}
+
 pub fn cmd(
 	f: fn(&mut Context) -> EventResult,
 	doc: &'static str,
@@ -65,27 +147,222 @@ pub fn nav(nav: NavigationMotion) -> EditorCommand {
 	Motion(NavigationOnly { nav, count: 1 })
 }
 
-pub fn delete(nav: NavigationMotion) -> EditorCommand {
+pub fn operator(
+	motion_type: MotionType,
+	nav: NavigationMotion,
+) -> EditorCommand {
 	Motion(OperatedNavigation {
-		motion_type: Delete,
+		motion_type,
 		nav,
 		count: 1,
 	})
 }
 
+pub fn operator_range(motion_type: MotionType, range: Range) -> EditorCommand {
+	Motion(OperatedRange {
+		motion_type,
+		range,
+		count: 1,
+	})
+}
+
+/// Binds every [`NavigationMotion`] to its key representation.
+pub fn bind_navigation(root: &mut KeyNode) {
+	for motion in NavigationMotion::ALL {
+		root.bind_str(&motion.as_key(), nav(motion));
+	}
+}
+
+/// The operators that take a motion in normal mode.
+fn operators() -> impl Iterator<Item = MotionType> {
+	[Delete, Yank, Change, ShiftRight, ShiftLeft, Filter]
+		.into_iter()
+		.chain(MetaOperator::ALL.map(Meta))
+}
+
+/// Binds `d`, `y`, `c`, `>`, `<`, `!` and the `g` operators like `gU`
+/// followed by any navigation, range or text object, as well as the doubled
+/// linewise forms like `dd` and `gqq`.
+pub fn bind_operators(root: &mut KeyNode) {
+	for op in operators() {
+		let prefix = op.as_key();
+
+		for motion in NavigationMotion::ALL {
+			let keys = format!("{prefix}{}", motion.as_key());
+			root.bind_str(&keys, operator(op, motion));
+		}
+
+		bind_ranges(root, &prefix, op);
+	}
+
+	for op in operators() {
+		let prefix = op.as_key();
+		let last = &prefix[prefix.len() - 1..];
+		root.bind_str(
+			&format!("{prefix}{last}"),
+			operator_range(op, Range::Line),
+		);
+	}
+}
+
+/// Binds `prefix` followed by every range and text object to `op`.
+fn bind_ranges(root: &mut KeyNode, prefix: &str, op: MotionType) {
+	for (key, range) in FINDS {
+		root.bind_char(&format!("{prefix}{key}"), move |ch| {
+			Some(operator_range(op, range(ch)))
+		});
+	}
+
+	for scope in Scope::ALL {
+		for (kind, range) in
+			[("i", Range::Inside(scope)), ("a", Range::Around(scope))]
+		{
+			let keys = format!("{prefix}{}", range.as_key());
+			root.bind_str(&keys, operator_range(op, range.clone()));
+
+			// `i)` works just like `i(`
+			if let Some((open, close)) = scope.delimiters() {
+				if open != close {
+					let keys = format!("{prefix}{kind}{close}");
+					root.bind_str(&keys, operator_range(op, range));
+				}
+			}
+		}
+	}
+
+	for range in [
+		Range::Percent(Scope::Parentheses),
+		Range::Percent(Scope::Brackets),
+		Range::Percent(Scope::Braces),
+		Range::Percent(Scope::AngleBrackets),
+		Range::SentenceEnd,
+		Range::SentenceStart,
+	] {
+		let keys = format!("{prefix}{}", range.as_key());
+		root.bind_str(&keys, operator_range(op, range));
+	}
+}
+
+/// Binds `m{a-zA-Z}` to set a mark, and `'` and `` ` `` followed by any mark
+/// to jump there, both on their own and after an operator.
+pub fn bind_marks(root: &mut KeyNode) {
+	root.bind_char("m", |mark| {
+		mark.is_ascii_alphabetic().then_some(SetMark(mark))
+	});
+
+	for (key, linewise) in [("'", true), ("`", false)] {
+		root.bind_char(key, move |mark| {
+			MarkTable::is_valid(mark).then_some(JumpToMark { mark, linewise })
+		});
+	}
+
+	for op in operators() {
+		for (key, range) in MARK_RANGES {
+			root.bind_char(&format!("{}{key}", op.as_key()), move |mark| {
+				MarkTable::is_valid(mark)
+					.then(|| operator_range(op, range(mark)))
+			});
+		}
+	}
+}
+
+/// A range to the character typed after its key.
+type CharRange = fn(char) -> Range;
+
+/// `f`, `t`, `F` and `T`, which take any character as their target.
+const FINDS: [(&str, CharRange); 4] = [
+	("f", Range::ForwardTo),
+	("t", Range::ForwardTill),
+	("F", Range::BackwardsTo),
+	("T", Range::BackwardsTill),
+];
+
+/// `'` and `` ` ``, which go to the line of a mark and to the mark itself.
+const MARK_RANGES: [(&str, CharRange); 2] =
+	[("'", Range::MarkLine), ("`", Range::Mark)];
+
+/// Binds the visual mode keys: navigations, ranges and mark jumps extend the
+/// selection, and operators act on it. `'` jumps to the exact mark too,
+/// since the selection has no use for its line alone.
+pub fn bind_visual(root: &mut KeyNode) {
+	bind_navigation(root);
+	bind_ranges(root, "", Visual);
+
+	for key in ["'", "`"] {
+		root.bind_char(key, |mark| {
+			MarkTable::is_valid(mark)
+				.then(|| operator_range(Visual, Range::Mark(mark)))
+		});
+	}
+
+	for op in operators() {
+		root.bind_str(&op.as_key(), Operator(op));
+	}
+
+	for (key, op) in [
+		("x", Delete),
+		("s", Change),
+		("~", Meta(MetaOperator::ToggleCase)),
+		("U", Meta(MetaOperator::Uppercase)),
+		("u", Meta(MetaOperator::Lowercase)),
+	] {
+		root.bind_str(key, Operator(op));
+	}
+}
+
 define_keymaps! {
 	normal {
 		"i" => cmd(to_insert_mode,"enter insert mode"),
 		":" => cmd(to_command_mode,"enter command mode"),
+		"v" => cmd(to_visual_mode, "enter visual mode"),
+		"V" => cmd(to_visual_line_mode, "enter linewise visual mode"),
+		"<C-V>" => cmd(to_visual_block_mode, "enter blockwise visual mode"),
 
 		"q" => cmd(quit_editor,"quit editor"),
-		"w" => cmd(write_active_document,"save active document"),
 
-		"h" => nav(CharBackward),
-		"j" => nav(LineForward),
-		"k" => nav(LineBackward),
-		"l" => nav(CharForward),
-		"dw" => delete(WordForward),
+		"u" => cmd(undo, "undo"),
+		"<C-R>" => cmd(redo, "redo"),
+		"g-" => cmd(undo_earlier, "go to older text state"),
+		"g+" => cmd(undo_later, "go to newer text state"),
+
+		"<C-N>" => cmd(select_next_match, "select next match"),
+		"gS" => cmd(split_selections, "split selections on lines"),
+		"<C-[>" => cmd(collapse_selections, "keep only the primary cursor"),
+
+		"x" => operator(Delete, CharForward),
+		"X" => operator(Delete, CharBackward),
+		"D" => operator(Delete, LineEnd),
+		"C" => operator(Change, LineEnd),
+		"s" => operator(Change, CharForward),
+		"S" => operator_range(Change, Range::Line),
+		"Y" => operator_range(Yank, Range::Line),
+
+		"p" => Put { before: false },
+		"P" => Put { before: true },
+
+		"<Left>" => nav(CharBackward),
+		"<Right>" => nav(CharForward),
+		"<Up>" => nav(LineBackward),
+		"<Down>" => nav(LineForward),
+		"<Home>" => nav(LineStart),
+		"<End>" => nav(LineEnd),
+	}
+	visual {
+		"<C-[>" => cmd(to_normal_mode, "normal mode"),
+		":" => cmd(to_selection_command_mode, "command on the selected lines"),
+		"v" => cmd(to_visual_mode, "charwise visual mode"),
+		"V" => cmd(to_visual_line_mode, "linewise visual mode"),
+		"<C-V>" => cmd(to_visual_block_mode, "blockwise visual mode"),
+		"o" => cmd(flip_selections, "go to the other end of the selection"),
+		"I" => cmd(block_insert, "insert before the block on every line"),
+		"A" => cmd(block_append, "append after the block on every line"),
+
+		"<Left>" => nav(CharBackward),
+		"<Right>" => nav(CharForward),
+		"<Up>" => nav(LineBackward),
+		"<Down>" => nav(LineForward),
+		"<Home>" => nav(LineStart),
+		"<End>" => nav(LineEnd),
 	}
 	insert {
 		"<C-[>"=> cmd(to_normal_mode,"normal mode")
@@ -95,3 +372,97 @@ define_keymaps! {
 		"<C-M>"=> cmd(submit_command, "submit command"),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::keymap::{
+		KeyEvent,
+		KeyParser,
+		ParseResult,
+	};
+
+	/// The normal keymap with everything the editor view binds in it.
+	fn normal() -> KeyMaps {
+		let mut km = KeyMaps::new();
+		bind_navigation(&mut km.normal);
+		bind_operators(&mut km.normal);
+		bind_marks(&mut km.normal);
+		km
+	}
+
+	/// The command `keys` complete, if every key but the last is a prefix.
+	fn resolve(root: &KeyNode, keys: &str) -> Option<EditorCommand> {
+		let mut parser = KeyParser::new(root);
+		let mut chars = keys.chars().peekable();
+
+		while let Some(ch) = chars.next() {
+			match (parser.feed(KeyEvent::from(ch)), chars.peek()) {
+				(ParseResult::Incomplete, Some(_)) => {}
+				(ParseResult::Command(cmd, ..), None) => return Some(cmd),
+				_ => return None,
+			}
+		}
+		None
+	}
+
+	#[test]
+	fn g_operators() {
+		let km = normal();
+
+		for op in MetaOperator::ALL {
+			let key = op.as_key();
+
+			let keys = format!("g{key}w");
+			assert!(
+				matches!(
+					resolve(&km.normal, &keys),
+					Some(Motion(OperatedNavigation {
+						motion_type: Meta(found),
+						nav: WordForward,
+						..
+					})) if found == op
+				),
+				"{keys}"
+			);
+
+			let keys = format!("g{key}{key}");
+			assert!(
+				matches!(
+					resolve(&km.normal, &keys),
+					Some(Motion(OperatedRange {
+						motion_type: Meta(found),
+						range: Range::Line,
+						..
+					})) if found == op
+				),
+				"{keys}"
+			);
+		}
+
+		// the other `g` commands share the prefix, and `q` still quits
+		for keys in ["g-", "g+", "gS", "gg"] {
+			assert!(resolve(&km.normal, keys).is_some(), "{keys}");
+		}
+		assert!(matches!(resolve(&km.normal, "q"), Some(Static { .. })));
+	}
+
+	#[test]
+	fn any_char_targets() {
+		let km = normal();
+		let range = |keys| match resolve(&km.normal, keys) {
+			Some(Motion(OperatedRange { range, .. })) => Some(range),
+			_ => None,
+		};
+
+		assert_eq!(range("dfä"), Some(Range::ForwardTo('ä')));
+		assert_eq!(range("ctß"), Some(Range::ForwardTill('ß')));
+		assert_eq!(range("yF😀"), Some(Range::BackwardsTo('😀')));
+		assert_eq!(range("gUT "), Some(Range::BackwardsTill(' ')));
+		assert_eq!(range("d'a"), Some(Range::MarkLine('a')));
+		assert_eq!(range("y`ä"), None);
+
+		assert!(matches!(resolve(&km.normal, "mQ"), Some(SetMark('Q'))));
+		assert!(resolve(&km.normal, "mä").is_none());
+	}
+}
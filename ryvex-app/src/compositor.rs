@@ -7,11 +7,15 @@ use core::any::Any;
 use crate::editor::editor::Editor;
 
 use ryvex_target::{
+	mouse::MouseEvent,
 	r#impl::TargetContext,
 	term::event::Event,
 };
 use ryvex_tui::buffer::Buffer;
-use ryvex_ui::graphics::Rect;
+use ryvex_ui::graphics::{
+	CursorKind,
+	Rect,
+};
 
 pub struct Compositor {
 	layers: Vec<Box<dyn Component>>,
@@ -45,12 +49,35 @@ impl Compositor {
 		self.area
 	}
 
+	/// Lays the layers out for a terminal of size `area`.
+	pub fn resize(&mut self, area: Rect) {
+		self.area = area;
+		for layer in &mut self.layers {
+			layer.required_size((area.width, area.height));
+		}
+	}
+
 	pub fn render(&mut self, area: Rect, frame: &mut Buffer, cx: &mut Context) {
 		for layer in &mut self.layers {
 			layer.render(area, frame, cx);
 		}
 	}
 
+	/// Cursor position and shape reported by the topmost layer that has one.
+	pub fn cursor(
+		&self,
+		area: Rect,
+		editor: &Editor,
+	) -> (Option<(u16, u16)>, CursorKind) {
+		for layer in self.layers.iter().rev() {
+			if let (Some(pos), kind) = layer.cursor(area, editor) {
+				return (Some(pos), kind);
+			}
+		}
+
+		(None, CursorKind::Block)
+	}
+
 	pub fn push(&mut self, mut layer: Box<dyn Component>) {
 		let size = self.size();
 		layer.required_size((size.width, size.height));
@@ -58,11 +85,21 @@ impl Compositor {
 	}
 
 	pub fn handle_event(&mut self, event: &Event, cx: &mut Context) -> bool {
+		if let Event::Mouse(mouse) = event {
+			return self.handle_mouse(event, mouse, cx);
+		}
+
 		let mut consumed = false;
 		let mut callbacks = Vec::new();
+		// idle work is not up to the topmost layer, every layer sees a tick
+		let broadcast = *event == Event::Tick;
 
 		for layer in self.layers.iter_mut().rev() {
 			match layer.handle_event(event, cx) {
+				EventResult::Consumed(callback) if broadcast => {
+					callbacks.extend(callback);
+					consumed = true;
+				}
 				EventResult::Consumed(Some(callback)) => {
 					callbacks.push(callback);
 					consumed = true;
@@ -85,6 +122,33 @@ impl Compositor {
 
 		consumed
 	}
+
+	/// Hands a mouse event to the topmost layer under the pointer.
+	fn handle_mouse(
+		&mut self,
+		event: &Event,
+		mouse: &MouseEvent,
+		cx: &mut Context,
+	) -> bool {
+		let area = self.area;
+		let Some(layer) = self.layers.iter_mut().rev().find(|layer| {
+			layer
+				.area(area, cx.editor)
+				.is_some_and(|rect| rect.contains(mouse.column, mouse.row))
+		}) else {
+			return false;
+		};
+
+		let (consumed, callback) = match layer.handle_event(event, cx) {
+			EventResult::Consumed(callback) => (true, callback),
+			EventResult::Ignored(callback) => (false, callback),
+		};
+		if let Some(callback) = callback {
+			callback(self, cx);
+		}
+
+		consumed
+	}
 }
 
 pub trait AnyComponent {
@@ -152,6 +216,15 @@ pub trait Component: Any + AnyComponent {
 	/// Render the component onto the provided surface.
 	fn render(&mut self, area: Rect, frame: &mut Buffer, cx: &mut Context);
 
+	/// Where the terminal cursor should be placed, if this component owns it.
+	fn cursor(
+		&self,
+		_area: Rect,
+		_editor: &Editor,
+	) -> (Option<(u16, u16)>, CursorKind) {
+		(None, CursorKind::Block)
+	}
+
 	fn type_name(&self) -> &'static str {
 		core::any::type_name::<Self>()
 	}
@@ -163,4 +236,9 @@ pub trait Component: Any + AnyComponent {
 	fn required_size(&mut self, _viewport: (u16, u16)) -> Option<(u16, u16)> {
 		None
 	}
+
+	/// The part of `viewport` this component takes mouse events for.
+	fn area(&self, _viewport: Rect, _editor: &Editor) -> Option<Rect> {
+		None
+	}
 }
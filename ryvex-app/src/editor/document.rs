@@ -4,17 +4,49 @@ use core::{
 		Display,
 	},
 	num::NonZeroUsize,
+	ops::Range,
 };
 
-use alloc::string::{
-	String,
-	ToString,
+use alloc::{
+	string::{
+		String,
+		ToString,
+	},
+	vec::Vec,
 };
 use ryvex_core::{
+	edit::Recorder,
+	grapheme,
+	history::History,
+	line_range::LineRange,
+	motion::{
+		self,
+		navigation,
+		operator::{
+			self,
+			RangeKind,
+			TextRange,
+		},
+		text_object,
+		Motion,
+		MotionType,
+		Options,
+		Scope,
+	},
 	piece_table::{
 		PieceTable,
 		RowCol,
 	},
+	regex::Regex,
+	register::{
+		self,
+		Register,
+	},
+	selection::{
+		Selection,
+		Selections,
+	},
+	Cursor,
 	MarkTable,
 	TextBuffer,
 };
@@ -50,10 +82,12 @@ pub struct Document {
 	pub id: DocumentId,
 	path:   Option<TargetPath>,
 
-	buffer: PieceTable,
-	cursor: RowCol,
-	mark:   Option<RowCol>,
-	marks:  MarkTable,
+	buffer:       PieceTable,
+	history:      History,
+	/// Cursor position before the first edit of the pending undo step.
+	change_start: Option<usize>,
+	selections:   Selections,
+	marks:        MarkTable,
 }
 
 impl Default for Document {
@@ -63,39 +97,32 @@ impl Default for Document {
 }
 
 impl Document {
-	pub fn scratch() -> Self {
+	fn with_content(path: Option<TargetPath>, content: String) -> Self {
+		let buffer = PieceTable::new(content);
+
 		Self {
-			id:     DocumentId::default(),
-			path:   None,
-			buffer: PieceTable::new(String::new()),
-			cursor: RowCol { row: 0, col: 0 },
-			mark:   None,
-			marks:  MarkTable::default(),
+			id: DocumentId::default(),
+			path,
+			history: History::new(&buffer),
+			buffer,
+			change_start: None,
+			selections: Selections::default(),
+			marks: MarkTable::default(),
 		}
 	}
 
+	pub fn scratch() -> Self {
+		Self::with_content(None, String::new())
+	}
+
 	pub fn open(path: TargetPath, fs: &TargetFileSystem) -> Result<Self> {
 		let content = fs.read_to_string(&path)?;
 
-		Ok(Self {
-			id:     DocumentId::default(),
-			path:   Some(path),
-			buffer: PieceTable::new(content),
-			cursor: RowCol { row: 0, col: 0 },
-			mark:   None,
-			marks:  MarkTable::default(),
-		})
+		Ok(Self::with_content(Some(path), content))
 	}
 
 	pub fn scratch_from_string(text: String) -> Self {
-		Self {
-			id:     DocumentId::default(),
-			path:   None,
-			buffer: PieceTable::new(text),
-			cursor: RowCol { row: 0, col: 0 },
-			mark:   None,
-			marks:  MarkTable::default(),
-		}
+		Self::with_content(None, text)
 	}
 
 	pub fn new(
@@ -124,17 +151,62 @@ impl Document {
 	}
 
 	pub fn current_row(&self) -> usize {
-		self.cursor.row
+		self.cursor().row
 	}
 	pub fn current_col(&self) -> usize {
-		self.cursor.col
+		self.cursor().col
 	}
+	/// The position drawn at screen column `col` of `row`. Past the end of
+	/// the line it is the last character, or with `past_end` the end of the
+	/// line itself, where insert mode types.
+	pub fn pos_at_display(
+		&self,
+		row: usize,
+		col: usize,
+		past_end: bool,
+	) -> usize {
+		let row = row.min(self.rows().saturating_sub(1));
+		let start = self.buffer.pos_from(RowCol { row, col: 0 });
+		let end = self.buffer.pos_from(RowCol {
+			row,
+			col: usize::MAX,
+		});
+		let pos = self.buffer.pos_from(RowCol { row, col });
+
+		if pos < end || past_end {
+			pos
+		} else {
+			grapheme::prev_boundary(&self.buffer, end).max(start)
+		}
+	}
+
+	/// Moves the primary cursor to `pos`. With `extend`, the selection keeps
+	/// its anchor and grows to `pos`, as dragging does.
+	pub fn move_primary(&mut self, pos: usize, extend: bool) {
+		let primary = self.selections.primary();
+		let moved = if extend {
+			Selection::new(primary.anchor, pos)
+		} else {
+			Selection::point(pos)
+		};
+
+		self.selections.set(self.selections.primary_index(), moved);
+		self.selections.normalize();
+	}
+
 	pub fn total_lines(&self) -> usize {
 		self.buffer.lines()
 	}
 
+	/// Number of lines with text, so a trailing line break doesn't start
+	/// another row and an empty buffer has none.
 	pub fn rows(&self) -> usize {
-		self.content().lines().count()
+		let len = self.buffer.len();
+		if len == 0 || self.buffer.char_at(len - 1) == Some('\n') {
+			self.buffer.lines() - 1
+		} else {
+			self.buffer.lines()
+		}
 	}
 
 	pub fn len(&self) -> usize {
@@ -146,17 +218,18 @@ impl Document {
 	}
 
 	pub fn insert_character(&mut self, ch: char) {
-		// 1) map (row,col) → byte offset
-		let pos = self.buffer.pos_from(self.cursor);
-		// 2) insert into piece-table
-		self.buffer.insert(pos, &ch.to_string());
-		// 3) advance cursor
-		if ch == '\n' {
-			self.cursor.row += 1;
-			self.cursor.col = 0;
-		} else {
-			self.cursor.col += 1;
-		}
+		self.insert_text(ch.encode_utf8(&mut [0; 4]));
+	}
+
+	/// Inserts `text` at every cursor and moves the cursors past it.
+	pub fn insert_text(&mut self, text: &str) {
+		self.begin_change();
+
+		self.for_each_selection(|buf, _, cursor| {
+			buf.insert(cursor.pos, text);
+			// the text may have landed before a cursor inside a multi-byte char
+			cursor.pos = buf.floor_char_boundary(cursor.pos) + text.len();
+		});
 	}
 
 	pub fn path(&self) -> Option<&TargetPath> {
@@ -173,31 +246,434 @@ impl Document {
 		&self.buffer
 	}
 
+	/// Position of the primary cursor.
 	pub fn cursor(&self) -> RowCol {
-		self.cursor
+		self.buffer.rowcol_at(self.selections.primary().head)
+	}
+
+	pub fn selections(&self) -> &Selections {
+		&self.selections
+	}
+
+	pub fn marks(&self) -> &MarkTable {
+		&self.marks
 	}
 
 	pub fn delete_at_cursor(&mut self) {
-		let mut pos = self.buffer.pos_from(self.cursor);
+		self.begin_change();
 
-		if pos == self.buffer.len() && pos > 0 {
-			let mut start = pos - 1;
-			while start > 0 && self.buffer.char_at(start).is_none() {
-				start -= 1;
+		self.for_each_selection(|buf, _, cursor| {
+			let mut pos = cursor.pos;
+			if pos == buf.len() {
+				pos = grapheme::prev_boundary(buf, pos);
 			}
-			pos = start;
-		}
 
-		if let Some(ch) = self.buffer.char_at(pos) {
-			let end = pos + ch.len_utf8();
-			self.buffer.delete(pos, end);
-			self.cursor = self.buffer.rowcol_at(pos);
-		}
+			// removes the whole cluster, e.g. a letter with its accents
+			let pos = grapheme::floor_boundary(buf, pos);
+			let end = grapheme::next_boundary(buf, pos);
+			if pos < end {
+				buf.delete(pos, end);
+				cursor.pos = pos;
+			}
+		});
 	}
 
 	pub fn buffer_mut(&mut self) -> &mut PieceTable {
 		&mut self.buffer
 	}
+
+	/// Applies `motion` at every cursor and moves each cursor to wherever
+	/// the motion left it. Returns the removed or yanked text, if any. Text
+	/// from several cursors is joined in document order, charwise pieces
+	/// separated by line breaks.
+	pub fn apply_motion(
+		&mut self,
+		motion: Motion,
+		options: &Options,
+	) -> Option<Register> {
+		self.begin_change();
+
+		let jump = matches!(
+			motion,
+			Motion::NavigationOnly { nav, .. } if nav.is_jump()
+		);
+		let from = self.selections.primary().head;
+		let marks = self.marks.clone();
+
+		let taken = self.for_each_selection(|buf, _, cursor| {
+			motion::apply(buf, cursor, motion.clone(), options, &marks)
+		});
+
+		if jump && self.selections.primary().head != from {
+			self.marks.set('\'', from);
+		}
+
+		join_registers(taken)
+	}
+
+	/// Moves the head of every selection by `motion`, keeping its anchor, as
+	/// motions do in visual mode. Ranges like text objects extend the
+	/// selection over the text they cover.
+	pub fn extend_selections(&mut self, motion: Motion) {
+		let from = self.selections.primary().head;
+
+		for idx in 0..self.selections.len() {
+			let sel = self.selections.get(idx);
+			let extended = match &motion {
+				Motion::NavigationOnly { nav, count } => {
+					let head = navigation::navigate(
+						&self.buffer,
+						sel.head,
+						*nav,
+						*count,
+					);
+					Selection::new(sel.anchor, head)
+				}
+				_ => match operator::resolve(
+					&self.buffer,
+					sel.head,
+					&motion,
+					&self.marks,
+				) {
+					Some(range) => {
+						sel.extend(&self.buffer, range.start, range.end)
+					}
+					None => sel,
+				},
+			};
+			self.selections.set(idx, extended);
+		}
+		self.selections.normalize();
+
+		let jump = matches!(
+			motion,
+			Motion::NavigationOnly { nav, .. } if nav.is_jump()
+		);
+		if jump && self.selections.primary().head != from {
+			self.marks.set('\'', from);
+		}
+	}
+
+	/// Applies `op` to the selections of visual `mode` and returns the text
+	/// it removed or yanked, joined like [`Document::apply_motion`] does.
+	/// Every selection collapses to wherever the operator leaves its cursor.
+	pub fn apply_visual(
+		&mut self,
+		op: MotionType,
+		mode: Mode,
+		options: &Options,
+	) -> Option<Register> {
+		self.set_visual_marks();
+		if mode == Mode::VisualBlock {
+			self.selections.to_block(&self.buffer);
+			self.selections.set_primary(0);
+		}
+		self.begin_change();
+
+		let taken = self.for_each_selection(|buf, sel, cursor| {
+			let range = if mode == Mode::VisualLine {
+				let first = buf.rowcol_at(sel.from()).row;
+				let last = buf.rowcol_at(sel.to()).row;
+				TextRange::linewise(buf, first, last)
+			} else {
+				TextRange::charwise(sel.from(), sel.end(buf))
+			};
+
+			cursor.pos = range.start;
+			operator::execute(buf, cursor, op, range, options)
+				.map(|text| Register::new(text, range.kind))
+		});
+
+		join_registers(taken)
+	}
+
+	/// The text the selections of visual `mode` cover, in document order.
+	/// A range runs on over the line break of every line the selection
+	/// continues past. A block covers the columns its operators act on.
+	pub fn visual_ranges(&self, mode: Mode) -> Vec<Range<usize>> {
+		let buf = &self.buffer;
+
+		match mode {
+			Mode::VisualLine => self
+				.selections
+				.iter()
+				.map(|sel| {
+					let first = buf.rowcol_at(sel.from()).row;
+					let last = buf.rowcol_at(sel.to()).row;
+					let range = TextRange::linewise(buf, first, last);
+					range.start..range.end
+				})
+				.collect(),
+			Mode::VisualBlock => {
+				let mut block = self.selections.clone();
+				block.to_block(buf);
+				block.iter().map(|sel| sel.from()..sel.end(buf)).collect()
+			}
+			_ => self
+				.selections
+				.iter()
+				.map(|sel| sel.from()..sel.end(buf))
+				.collect(),
+		}
+	}
+
+	/// The lines an operated `motion` covers from the primary cursor, or
+	/// `None` if it fails.
+	pub fn motion_lines(&self, motion: &Motion) -> Option<LineRange> {
+		let pos = self.selections.primary().head;
+		operator::resolve(&self.buffer, pos, motion, &self.marks)
+			.map(|range| LineRange::covering(&self.buffer, range))
+	}
+
+	/// Replaces the lines of `range` with `text` as an undo step of its own,
+	/// leaving a single cursor on the first of them.
+	pub fn replace_lines(&mut self, range: LineRange, text: &str) {
+		self.collapse_selections();
+		self.commit();
+		// undoing goes back to the first line, like Vim
+		let start = self.buffer.pos_from(RowCol {
+			row: range.first,
+			col: 0,
+		});
+		self.change_start = Some(start);
+
+		self.for_each_selection(|buf, _, cursor| {
+			range.replace(buf, cursor, text);
+		});
+		self.commit();
+	}
+
+	/// Leaves visual mode: remembers the selected area in the `<` and `>`
+	/// marks and shrinks every selection to its head.
+	pub fn leave_visual(&mut self) {
+		self.set_visual_marks();
+		self.selections.reduce_to_heads();
+	}
+
+	fn set_visual_marks(&mut self) {
+		let first = self.selections.iter().map(|sel| sel.from()).min();
+		let last = self.selections.iter().map(|sel| sel.to()).max();
+
+		if let (Some(first), Some(last)) = (first, last) {
+			self.marks.set('<', first);
+			self.marks.set('>', last);
+		}
+	}
+
+	/// Swaps the ends of every selection, moving the cursor to the other
+	/// end, or the opposite corner of a block.
+	pub fn flip_selections(&mut self) {
+		self.selections.flip();
+	}
+
+	/// Puts a cursor on every line of the visual block, in its left column
+	/// for `I` or right after its right column for `A`, so insert mode types
+	/// on all lines at once. The top line becomes primary.
+	pub fn block_insert(&mut self, append: bool) {
+		self.set_visual_marks();
+		self.selections.to_block(&self.buffer);
+
+		for idx in 0..self.selections.len() {
+			let sel = self.selections.get(idx);
+			let pos = if append {
+				sel.end(&self.buffer)
+			} else {
+				sel.from()
+			};
+			self.selections.set(idx, Selection::point(pos));
+		}
+		self.selections.set_primary(0);
+		self.selections.normalize();
+	}
+
+	/// Puts `register` `count` times after or before every cursor.
+	pub fn put(&mut self, register: &Register, before: bool, count: u32) {
+		self.begin_change();
+
+		self.for_each_selection(|buf, _, cursor| {
+			register::put(buf, cursor, register, before, count);
+		});
+	}
+
+	/// Runs `f` at the head of every selection, in document order, passing
+	/// the selection along. Each selection collapses to wherever `f` leaves
+	/// its cursor, and the others follow the edits `f` makes, as do the
+	/// marks.
+	fn for_each_selection<R>(
+		&mut self,
+		mut f: impl FnMut(&mut Recorder<PieceTable>, Selection, &mut Cursor) -> R,
+	) -> Vec<R> {
+		let mut results = Vec::with_capacity(self.selections.len());
+
+		for idx in 0..self.selections.len() {
+			let sel = self.selections.get(idx);
+			let mut cursor = Cursor { pos: sel.head };
+
+			let mut buf = Recorder::new(&mut self.buffer);
+			results.push(f(&mut buf, sel, &mut cursor));
+			for edit in buf.into_edits() {
+				self.selections.map_except(idx, &edit);
+				self.marks.map(&edit);
+				self.marks.set('.', edit.start);
+			}
+
+			cursor.clamp(&self.buffer);
+			self.selections.set(idx, Selection::point(cursor.pos));
+		}
+
+		self.selections.clamp(&self.buffer);
+		self.selections.normalize();
+		results
+	}
+
+	/// Sets mark `ch` to the primary cursor. Returns `false` if `ch` is not
+	/// a mark.
+	pub fn set_mark(&mut self, ch: char) -> bool {
+		self.marks.set(ch, self.selections.primary().head)
+	}
+
+	pub fn remove_mark(&mut self, ch: char) {
+		self.marks.remove(ch);
+	}
+
+	/// Moves to mark `ch`, or to the first non-blank of its line if
+	/// `linewise`, and remembers where the jump started in `'`. Returns
+	/// `false` if the mark is not set.
+	pub fn jump_to_mark(&mut self, ch: char, linewise: bool) -> bool {
+		let Some(target) = self.marks.jump_target(&self.buffer, ch, linewise)
+		else {
+			return false;
+		};
+
+		self.marks.set('\'', self.selections.primary().head);
+		self.selections = Selections::single(target);
+		true
+	}
+
+	/// Selects the next occurrence of the primary selection, or the word
+	/// under the cursor if nothing is selected yet, and makes it primary.
+	/// Returns `false` if there is nothing more to select.
+	pub fn select_next_match(&mut self) -> bool {
+		let primary = self.selections.primary();
+
+		if primary.is_point() {
+			let Some(word) = text_object::resolve(
+				&self.buffer,
+				primary.head,
+				Scope::Word,
+				false,
+				1,
+			) else {
+				return false;
+			};
+
+			let last = grapheme::prev_boundary(&self.buffer, word.end);
+			let idx = self.selections.primary_index();
+			self.selections.set(idx, Selection::new(word.start, last));
+			self.selections.normalize();
+			return true;
+		}
+
+		let text = self.buffer.slice(primary.from(), primary.end(&self.buffer));
+		let regex = Regex::literal(&text);
+
+		// skip over occurrences that are selected already
+		let mut pos = primary.to();
+		for _ in 0..self.selections.len() {
+			let Some(m) = regex.next_match(&self.buffer, pos) else {
+				return false;
+			};
+
+			let taken = self
+				.selections
+				.iter()
+				.any(|sel| sel.from() <= m.start && m.start <= sel.to());
+			if !taken && m.start < m.end {
+				let last = grapheme::prev_boundary(&self.buffer, m.end);
+				self.selections.push(Selection::new(m.start, last));
+				return true;
+			}
+			pos = m.start;
+		}
+		false
+	}
+
+	/// Splits every selection into one per line it spans.
+	pub fn split_selections(&mut self) {
+		self.selections.split_lines(&self.buffer);
+	}
+
+	/// Goes back to a single cursor at the head of the primary selection.
+	/// Returns `false` if there was only one cursor to begin with.
+	pub fn collapse_selections(&mut self) -> bool {
+		let before = self.selections.clone();
+		self.selections.collapse();
+		before != self.selections
+	}
+
+	fn begin_change(&mut self) {
+		if self.change_start.is_none() {
+			self.change_start = Some(self.selections.primary().head);
+		}
+	}
+
+	/// Closes the pending undo step. Everything edited since the last commit
+	/// is undone at once.
+	pub fn commit(&mut self) {
+		let pos = self.selections.primary().head;
+		let start = self.change_start.take().unwrap_or(pos);
+
+		self.history.commit(&self.buffer, start);
+	}
+
+	pub fn undo(&mut self) -> bool {
+		self.commit();
+		let pos = self.history.undo(&mut self.buffer);
+		self.move_to(pos)
+	}
+
+	pub fn redo(&mut self) -> bool {
+		self.commit();
+		let pos = self.history.redo(&mut self.buffer);
+		self.move_to(pos)
+	}
+
+	pub fn earlier(&mut self, count: usize) -> bool {
+		self.commit();
+		let pos = self.history.earlier(&mut self.buffer, count);
+		self.move_to(pos)
+	}
+
+	pub fn later(&mut self, count: usize) -> bool {
+		self.commit();
+		let pos = self.history.later(&mut self.buffer, count);
+		self.move_to(pos)
+	}
+
+	fn move_to(&mut self, pos: Option<usize>) -> bool {
+		match pos {
+			Some(pos) => {
+				self.selections = Selections::single(pos);
+				self.marks.clamp(&self.buffer);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+/// Joins the registers taken at several cursors in document order. Charwise
+/// pieces are separated by line breaks.
+fn join_registers(taken: Vec<Option<Register>>) -> Option<Register> {
+	let mut taken = taken.into_iter().flatten();
+	let mut joined = taken.next()?;
+	for reg in taken {
+		if joined.kind == RangeKind::Charwise {
+			joined.text.push('\n');
+		}
+		joined.text.push_str(&reg.text);
+	}
+	Some(joined)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -206,6 +682,14 @@ pub enum Mode {
 	Visual = 1,
 	Insert = 2,
 	Command = 3,
+	VisualLine = 4,
+	VisualBlock = 5,
+}
+
+impl Mode {
+	pub fn is_visual(&self) -> bool {
+		matches!(self, Mode::Visual | Mode::VisualLine | Mode::VisualBlock)
+	}
 }
 
 impl Display for Mode {
@@ -213,8 +697,65 @@ impl Display for Mode {
 		match self {
 			Mode::Normal => f.write_str("NORMAL"),
 			Mode::Visual => f.write_str("VISUAL"),
+			Mode::VisualLine => f.write_str("VISUAL LINE"),
+			Mode::VisualBlock => f.write_str("VISUAL BLOCK"),
 			Mode::Insert => f.write_str("INSERT"),
 			Mode::Command => f.write_str("COMMAND"),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rows_ignore_trailing_break() {
+		for (text, rows) in [("", 0), ("ä", 1), ("a\n", 1), ("a\n\nb", 3)] {
+			let doc = Document::scratch_from_string(text.into());
+			assert_eq!(doc.rows(), rows, "{text:?}");
+		}
+	}
+
+	#[test]
+	fn marks_on_deleted_lines() {
+		let mut doc = Document::scratch_from_string("one\ntwo\nthree".into());
+		doc.move_primary(5, false);
+		doc.set_mark('a');
+		doc.move_primary(10, false);
+		doc.set_mark('B');
+
+		// a mark on a deleted line moves to the start of the line after it
+		doc.replace_lines(LineRange { first: 1, last: 1 }, "");
+		assert_eq!(doc.content(), "one\nthree");
+		assert_eq!(doc.marks().get('a'), Some(4));
+		assert_eq!(doc.marks().get('B'), Some(6));
+		assert!(doc.jump_to_mark('a', true));
+		assert_eq!(doc.cursor(), RowCol { row: 1, col: 0 });
+
+		// without a line after it, the mark ends up on the line above
+		doc.replace_lines(LineRange { first: 1, last: 1 }, "");
+		assert_eq!(doc.content(), "one");
+		assert_eq!(doc.marks().get('a'), Some(3));
+		assert!(doc.jump_to_mark('B', true));
+		assert_eq!(doc.cursor(), RowCol { row: 0, col: 0 });
+	}
+
+	#[test]
+	fn replace_lines_without_output() {
+		let mut doc = Document::scratch_from_string("a\nb\nlast".into());
+
+		// the line break before the last line goes along with it
+		doc.replace_lines(LineRange { first: 2, last: 2 }, "");
+		assert_eq!(doc.content(), "a\nb");
+		assert_eq!(doc.cursor(), RowCol { row: 1, col: 0 });
+
+		doc.replace_lines(LineRange { first: 0, last: 1 }, "");
+		assert_eq!(doc.content(), "");
+
+		assert!(doc.undo());
+		assert_eq!(doc.content(), "a\nb");
+		assert!(doc.undo());
+		assert_eq!(doc.content(), "a\nb\nlast");
+	}
+}
@@ -1,17 +1,31 @@
 use alloc::{
+	boxed::Box,
 	collections::BTreeMap,
 	format,
 	string::{
 		String,
 		ToString,
 	},
-	vec::Vec,
 };
 use core::num::NonZeroUsize;
 use ryvex_core::{
 	error_chain,
 	info,
+	line_range::{
+		self,
+		LineRange,
+	},
+	motion::{
+		Motion,
+		MotionType,
+		Options,
+	},
 	piece_table::PieceTable,
+	register::{
+		self,
+		Register,
+		Registers,
+	},
 	warn,
 };
 
@@ -19,10 +33,18 @@ use ryvex_target::{
 	r#impl::{
 		TargetContext,
 		TargetFileSystem,
+		TargetPath,
 	},
 	std::{
+		clipboard::{
+			ClipboardKind,
+			ClipboardProvider,
+		},
+		env::Environment,
 		process::{
+			Command,
 			Exitstatus,
+			Output,
 			Shell,
 			ShellError,
 		},
@@ -41,6 +63,15 @@ use crate::{
 	error::Result,
 };
 
+/// Where an uppercase mark was set. The position itself is tracked by the
+/// document's [`MarkTable`](ryvex_core::MarkTable); the path lets the mark
+/// find its file again once that document is gone.
+#[derive(Debug, Clone)]
+pub struct GlobalMark {
+	pub document: DocumentId,
+	pub path:     Option<TargetPath>,
+}
+
 #[derive(Debug)]
 pub struct Editor {
 	pub documents:        BTreeMap<DocumentId, Document>,
@@ -49,7 +80,18 @@ pub struct Editor {
 
 	pub mode: Mode,
 
+	pub registers: Registers,
+	pub options:   Options,
+	/// Where `+` and `*` are copied to and pasted from, if anywhere.
+	clipboard:     Option<Box<dyn ClipboardProvider>>,
+
+	global_marks: [Option<GlobalMark>; 26],
+	/// Set while inserting on every line of a visual block.
+	block_insert: bool,
+
 	command_buffer: String,
+	/// What the last `:!` command wrote, shown until it is acknowledged.
+	command_output: Option<String>,
 
 	should_close: bool,
 }
@@ -67,11 +109,22 @@ impl Editor {
 			active_document:  None,
 			next_document_id: DocumentId::default(),
 			mode:             Mode::Normal,
+			registers:        Registers::default(),
+			options:          Options::default(),
+			clipboard:        None,
+			global_marks:     Default::default(),
+			block_insert:     false,
 			command_buffer:   String::new(),
+			command_output:   None,
 			should_close:     false,
 		}
 	}
 
+	pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+		info!("Using the {} clipboard", clipboard.name());
+		self.clipboard = Some(clipboard);
+	}
+
 	pub fn new_document(&mut self, mut document: Document) -> DocumentId {
 		let id = self.next_document_id;
 		self.next_document_id = DocumentId(unsafe {
@@ -120,6 +173,26 @@ impl Editor {
 		}
 	}
 
+	/// Inserts pasted `text` as an undo step of its own. The command line
+	/// takes its first line only.
+	pub fn paste(&mut self, text: &str) {
+		match self.mode {
+			Mode::Command => {
+				let line = text.lines().next().unwrap_or_default();
+				line.chars().for_each(|ch| self.push_command_char(ch));
+			}
+			Mode::Normal | Mode::Insert => {
+				if let Some(doc) = self.get_active_document_mut() {
+					doc.commit();
+					doc.insert_text(text);
+					doc.commit();
+				}
+			}
+			// there is nothing sensible to do with the selection
+			_ => {}
+		}
+	}
+
 	pub fn delete_at_cursor(&mut self) {
 		if self.mode == Mode::Command {
 			self.pop_command_char();
@@ -133,11 +206,75 @@ impl Editor {
 		self.mode = Mode::Command;
 	}
 
+	/// Enters command mode on the lines of the visual selection, the way
+	/// Vim types `:'<,'>` for it, followed by `!` to `filter` them.
+	pub fn enter_selection_command_mode(&mut self, filter: bool) {
+		self.enter_normal_mode();
+		self.enter_command_mode();
+		self.command_buffer.push_str("'<,'>");
+		if filter {
+			self.command_buffer.push('!');
+		}
+	}
+
+	/// Enters command mode to ask for the command that filters the lines an
+	/// operated `motion` covers, as `!` does.
+	fn enter_filter_command_mode(&mut self, motion: &Motion) {
+		let Some(doc) = self.get_active_document() else {
+			return;
+		};
+		let Some(range) = doc.motion_lines(motion) else {
+			return;
+		};
+
+		let row = doc.current_row();
+		let prefix = match (range.first == row, range.len()) {
+			(true, 1) => ".".to_string(),
+			(true, len) => format!(".,.+{}", len - 1),
+			(false, _) => format!("{},{}", range.first + 1, range.last + 1),
+		};
+		self.enter_command_mode();
+		self.command_buffer = format!("{prefix}!");
+	}
+
 	pub fn exit_command_mode(&mut self) {
 		self.command_buffer.clear();
 		self.mode = Mode::Normal;
 	}
 
+	pub fn command_output(&self) -> Option<&str> {
+		self.command_output.as_deref()
+	}
+
+	pub fn clear_command_output(&mut self) {
+		self.command_output = None;
+	}
+
+	/// Keeps what a command wrote to stdout and stderr to show it, followed
+	/// by how it exited unless that was successful. A successful command
+	/// that wrote nothing shows nothing.
+	fn show_output(&mut self, output: &Output) {
+		let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+		text.push_str(&String::from_utf8_lossy(&output.stderr));
+		let mut text = String::from(text.trim_end());
+
+		if output.status.failure() {
+			if !text.is_empty() {
+				text.push('\n');
+			}
+			match output.status {
+				Exitstatus::Code(code) => {
+					text.push_str(&format!("shell returned {code}"))
+				}
+				Exitstatus::Signal(signal) => {
+					text.push_str(&format!("shell killed by signal {signal}"))
+				}
+			}
+		}
+
+		self.command_output = (!text.is_empty()).then_some(text);
+	}
+
 	pub fn push_command_char(&mut self, ch: char) {
 		self.command_buffer.push(ch);
 	}
@@ -152,25 +289,51 @@ impl Editor {
 	) -> Result<Exitstatus> {
 		let input: String = self.command_buffer.trim().to_string();
 
+		let (range, rest) = match self.get_active_document() {
+			Some(doc) => line_range::parse(
+				&input,
+				doc.buffer(),
+				doc.current_row(),
+				doc.marks(),
+			)?,
+			None => (None, input.as_str()),
+		};
+		if let Some(range) = range {
+			let command = rest.strip_prefix('!').map(str::trim);
+			let Some(command) = command.filter(|c| !c.is_empty()) else {
+				return Err(StdError::Shell(ShellError::CommandNotFound(
+					input,
+				))
+				.into());
+			};
+			return self.filter(target, range, command);
+		}
+
 		if let Some(command) = input.strip_prefix('!') {
-			let parts: Vec<&str> = command.split_whitespace().collect();
+			let command = command.trim();
+			if command.is_empty() {
+				return Err(StdError::Shell(ShellError::CommandNotFound(
+					input,
+				))
+				.into());
+			}
 
-			if !parts.is_empty() {
-				let status = target.shell.status(parts[0], &parts[1..])?;
+			// unlike a filter, a failing command still shows what it wrote
+			let command = shell_command(&target.env, command);
+			let output = target.shell.run(&command)?;
+			self.show_output(&output);
 
-				if status.failure() {
-					return Err(StdError::Shell(ShellError::ExecutionFailed(
-						input,
-					))
-					.into());
-				}
+			return Ok(output.status);
+		}
 
-				return Ok(status);
+		if let Some(option) = input.strip_prefix("set ") {
+			if !self.set_option(option.trim()) {
+				return Err(StdError::Shell(ShellError::CommandNotFound(
+					input,
+				))
+				.into());
 			}
-
-			return Err(
-				StdError::Shell(ShellError::CommandNotFound(input)).into()
-			);
+			return Ok(Exitstatus::SUCCESS);
 		}
 
 		match input.as_str() {
@@ -183,13 +346,145 @@ impl Editor {
 			}
 		}
 
-		Ok(Exitstatus::Success)
+		Ok(Exitstatus::SUCCESS)
+	}
+
+	/// Pipes the lines of `range` through `command` and replaces them with
+	/// what it writes to stdout. If it fails, the lines stay as they are.
+	fn filter(
+		&mut self,
+		target: &TargetContext,
+		range: LineRange,
+		command: &str,
+	) -> Result<Exitstatus> {
+		let Some(doc) = self.get_active_document_mut() else {
+			return Ok(Exitstatus::SUCCESS);
+		};
+
+		let input = range.text(doc.buffer());
+		let command =
+			shell_command(&target.env, command).input(input.as_bytes());
+		let output = target.shell.run(&command)?.exit_ok(&command)?;
+		let text =
+			core::str::from_utf8(&output.stdout).map_err(StdError::from)?;
+
+		doc.replace_lines(range, text);
+		match range.len() {
+			1 => info!("1 line filtered"),
+			len => info!("{len} lines filtered"),
+		}
+		Ok(output.status)
+	}
+
+	/// Applies a `:set` assignment like `textwidth=72`. Returns `false` for
+	/// unknown options and invalid values.
+	fn set_option(&mut self, option: &str) -> bool {
+		let Some((name, value)) = option.split_once('=') else {
+			return false;
+		};
+		let Ok(value) = value.parse::<usize>() else {
+			return false;
+		};
+
+		match name {
+			// like Vim, a width of 0 formats to the default width
+			"textwidth" | "tw" => {
+				self.options.text_width = match value {
+					0 => Options::default().text_width,
+					n => n,
+				};
+			}
+			"shiftwidth" | "sw" if value > 0 => {
+				self.options.shift_width = value
+			}
+			_ => return false,
+		}
+		true
 	}
 
 	pub fn enter_normal_mode(&mut self) {
+		let mode = self.mode;
+		// like Vim, a block insert ends with a single cursor again
+		let block_insert = core::mem::take(&mut self.block_insert);
+		if let Some(doc) = self.get_active_document_mut() {
+			if mode == Mode::Insert {
+				doc.set_mark('^');
+				if block_insert {
+					doc.collapse_selections();
+				}
+			} else if mode.is_visual() {
+				doc.leave_visual();
+			}
+		}
 		self.mode = Mode::Normal;
 	}
 
+	/// Enters visual `mode`, or goes back to normal mode if it is active
+	/// already. Switching between the visual modes keeps the selection.
+	pub fn toggle_visual_mode(&mut self, mode: Mode) {
+		if self.mode == mode {
+			self.enter_normal_mode();
+		} else {
+			self.mode = mode;
+		}
+	}
+
+	/// Places a single cursor at screen column `col` of document `row`, as a
+	/// mouse click does. A click ends visual mode.
+	pub fn click(&mut self, row: usize, col: usize) {
+		if self.mode == Mode::Command {
+			return;
+		}
+		if self.mode.is_visual() {
+			self.enter_normal_mode();
+		}
+
+		let past_end = self.mode == Mode::Insert;
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.collapse_selections();
+			let pos = doc.pos_at_display(row, col, past_end);
+			doc.move_primary(pos, false);
+		}
+	}
+
+	/// Extends the selection to screen column `col` of document `row`, as
+	/// dragging the mouse does, starting charwise visual mode unless a
+	/// visual mode is active already.
+	pub fn drag(&mut self, row: usize, col: usize) {
+		match self.mode {
+			Mode::Command => return,
+			Mode::Insert => {
+				self.enter_normal_mode();
+				self.mode = Mode::Visual;
+			}
+			Mode::Normal => self.mode = Mode::Visual,
+			_ => {}
+		}
+
+		if let Some(doc) = self.get_active_document_mut() {
+			let pos = doc.pos_at_display(row, col, false);
+			doc.move_primary(pos, true);
+		}
+	}
+
+	/// Moves the cursor onto the nearest of the document rows `first..=last`
+	/// if it is outside them, keeping its screen column, so it stays in view
+	/// when the view scrolls. Visual mode extends the selection along.
+	pub fn keep_cursor_within(&mut self, first: usize, last: usize) {
+		let extend = self.mode.is_visual();
+		let past_end = self.mode == Mode::Insert;
+
+		if let Some(doc) = self.get_active_document_mut() {
+			let row = doc.current_row();
+			let target = row.clamp(first, last.max(first));
+			if target != row {
+				let pos =
+					doc.pos_at_display(target, doc.current_col(), past_end);
+				doc.move_primary(pos, extend);
+			}
+		}
+	}
+
 	pub fn enter_insert_mode(&mut self) {
 		self.mode = Mode::Insert;
 	}
@@ -216,8 +511,337 @@ impl Editor {
 		EventResult::Consumed(None)
 	}
 
+	/// Applies `motion` to the active document and stores what an operator
+	/// yanked or removed in `register`, or the default registers if `None`.
+	/// A successful change leaves the editor in insert mode. In visual mode
+	/// the motion extends the selections instead.
+	pub fn apply_motion(&mut self, motion: Motion, register: Option<char>) {
+		if self.mode.is_visual() {
+			if let Some(doc) = self.get_active_document_mut() {
+				doc.extend_selections(motion);
+			}
+			return;
+		}
+
+		let motion_type = motion.motion_type();
+		if motion_type == Some(MotionType::Filter) {
+			self.enter_filter_command_mode(&motion);
+			return;
+		}
+
+		let options = self.options;
+		let text = self
+			.get_active_document_mut()
+			.and_then(|doc| doc.apply_motion(motion, &options));
+
+		self.store_operated(motion_type, register, text);
+	}
+
+	/// Applies `op` to the visual selection and goes back to normal mode, or
+	/// insert mode for a change. Changing a block inserts on all its lines.
+	pub fn apply_visual(&mut self, op: MotionType, register: Option<char>) {
+		if op == MotionType::Filter {
+			self.enter_selection_command_mode(true);
+			return;
+		}
+
+		let mode = self.mode;
+		let options = self.options;
+		let block = mode == Mode::VisualBlock;
+		let text = self.get_active_document_mut().and_then(|doc| {
+			let text = doc.apply_visual(op, mode, &options);
+			if block && op != MotionType::Change {
+				doc.collapse_selections();
+			}
+			text
+		});
+
+		self.mode = Mode::Normal;
+		self.block_insert = block && op == MotionType::Change;
+		self.store_operated(Some(op), register, text);
+	}
+
+	/// Starts inserting on every line of the visual block, before it for `I`
+	/// or after it for `A`.
+	pub fn block_insert(&mut self, append: bool) {
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.block_insert(append);
+		}
+		self.block_insert = true;
+		self.enter_insert_mode();
+	}
+
+	pub fn flip_selections(&mut self) {
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.flip_selections();
+		}
+	}
+
+	/// Stores the text an operator yanked or removed in `register`. A change
+	/// leaves the editor in insert mode.
+	fn store_operated(
+		&mut self,
+		motion_type: Option<MotionType>,
+		register: Option<char>,
+		text: Option<Register>,
+	) {
+		let Some(text) = text else {
+			return;
+		};
+
+		match motion_type {
+			Some(MotionType::Yank) => self.registers.yank(register, text),
+			Some(MotionType::Delete) => self.registers.delete(register, text),
+			Some(MotionType::Change) => {
+				self.registers.delete(register, text);
+				self.enter_insert_mode();
+			}
+			_ => return,
+		}
+
+		if let Some(name) = register {
+			self.copy_to_system(name);
+		}
+	}
+
+	/// Copies `+` or `*` to the system clipboard they stand for.
+	fn copy_to_system(&mut self, name: char) {
+		let (Some(kind), Some(clipboard)) =
+			(clipboard_kind(name), self.clipboard.as_mut())
+		else {
+			return;
+		};
+		let Some(register) = self.registers.get(name) else {
+			return;
+		};
+
+		if let Err(e) = clipboard.set(kind, &register.text) {
+			error_chain!(&e, "failed copying to the clipboard");
+		}
+	}
+
+	/// Refreshes `+` or `*` from the system clipboard they stand for. If
+	/// that fails, the register keeps what it had.
+	fn paste_from_system(&mut self, name: char) {
+		let (Some(kind), Some(clipboard)) =
+			(clipboard_kind(name), self.clipboard.as_mut())
+		else {
+			return;
+		};
+
+		let text = match clipboard.get(kind) {
+			Ok(text) => text,
+			Err(e) => {
+				error_chain!(&e, "failed pasting from the clipboard");
+				return;
+			}
+		};
+		// unchanged text keeps the kind it was yanked with
+		let unchanged = self
+			.registers
+			.get(name)
+			.is_some_and(|register| register.text == text);
+		if text.is_empty() || unchanged {
+			return;
+		}
+
+		let register = if text.ends_with('\n') {
+			Register::linewise(text)
+		} else {
+			Register::charwise(text)
+		};
+		self.registers.set_system(name, register);
+	}
+
+	/// Puts the contents of `register`, `"` by default, into the active
+	/// document.
+	pub fn put(&mut self, register: Option<char>, before: bool, count: u32) {
+		let name = register.unwrap_or(register::UNNAMED);
+		self.paste_from_system(name);
+		let Some(contents) = self.registers.get(name).cloned() else {
+			warn!("Nothing in register {name}");
+			return;
+		};
+
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.put(&contents, before, count);
+		}
+	}
+
+	/// Sets mark `ch` at the cursor of the active document. An uppercase
+	/// mark moves there from whichever document held it before.
+	pub fn set_mark(&mut self, ch: char) {
+		let Some(doc) = self.get_active_document_mut() else {
+			return;
+		};
+		if !doc.set_mark(ch) {
+			warn!("Invalid mark {ch}");
+			return;
+		}
+
+		if !ch.is_ascii_uppercase() {
+			return;
+		}
+
+		let mark = GlobalMark {
+			document: doc.id,
+			path:     doc.path().cloned(),
+		};
+		let slot = &mut self.global_marks[(ch as u8 - b'A') as usize];
+		if let Some(old) = slot.replace(mark.clone()) {
+			if old.document != mark.document {
+				if let Some(doc) = self.documents.get_mut(&old.document) {
+					doc.remove_mark(ch);
+				}
+			}
+		}
+	}
+
+	/// Jumps to mark `ch`. Uppercase marks switch to the document holding
+	/// them, opening their file again if no document has it.
+	pub fn jump_to_mark(
+		&mut self,
+		ch: char,
+		linewise: bool,
+		fs: &TargetFileSystem,
+	) {
+		if ch.is_ascii_uppercase() {
+			let Some(id) = self.global_mark_document(ch, fs) else {
+				warn!("Mark not set");
+				return;
+			};
+			self.active_document = Some(id);
+		}
+
+		if !self
+			.get_active_document_mut()
+			.is_some_and(|d| d.jump_to_mark(ch, linewise))
+		{
+			warn!("Mark not set");
+		}
+	}
+
+	/// The document holding uppercase mark `ch`, by id or else by path.
+	fn global_mark_document(
+		&mut self,
+		ch: char,
+		fs: &TargetFileSystem,
+	) -> Option<DocumentId> {
+		let mark = self.global_marks[(ch as u8 - b'A') as usize].clone()?;
+		if self.documents.contains_key(&mark.document) {
+			return Some(mark.document);
+		}
+
+		let path = mark.path.clone()?;
+		let open = self
+			.documents
+			.values()
+			.find(|doc| doc.path().is_some_and(|p| p.as_str() == path.as_str()))
+			.map(|doc| doc.id);
+		let id = match open {
+			Some(id) => id,
+			None => match Document::open(path, fs) {
+				Ok(doc) => self.new_document(doc),
+				Err(e) => {
+					error_chain!(&e, "failed to open file of mark {ch}");
+					return None;
+				}
+			},
+		};
+
+		// the position went with the old document, so the mark starts out
+		// at the cursor
+		if let Some(doc) = self.documents.get_mut(&id) {
+			if doc.marks().get(ch).is_none() {
+				doc.set_mark(ch);
+			}
+		}
+		self.global_marks[(ch as u8 - b'A') as usize] = Some(GlobalMark {
+			document: id,
+			..mark
+		});
+		Some(id)
+	}
+
+	pub fn select_next_match(&mut self) {
+		if !self
+			.get_active_document_mut()
+			.is_some_and(|d| d.select_next_match())
+		{
+			info!("No more matches");
+		}
+	}
+
+	pub fn split_selections(&mut self) {
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.split_selections();
+		}
+	}
+
+	pub fn collapse_selections(&mut self) {
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.collapse_selections();
+		}
+	}
+
+	/// Closes the pending undo step of the active document.
+	pub fn commit_history(&mut self) {
+		if let Some(doc) = self.get_active_document_mut() {
+			doc.commit();
+		}
+	}
+
+	pub fn undo(&mut self) {
+		if !self.get_active_document_mut().is_some_and(|d| d.undo()) {
+			info!("Already at oldest change");
+		}
+	}
+
+	pub fn redo(&mut self) {
+		if !self.get_active_document_mut().is_some_and(|d| d.redo()) {
+			info!("Already at newest change");
+		}
+	}
+
+	pub fn earlier(&mut self, count: usize) {
+		if !self
+			.get_active_document_mut()
+			.is_some_and(|d| d.earlier(count))
+		{
+			info!("Already at oldest change");
+		}
+	}
+
+	pub fn later(&mut self, count: usize) {
+		if !self
+			.get_active_document_mut()
+			.is_some_and(|d| d.later(count))
+		{
+			info!("Already at newest change");
+		}
+	}
+
 	pub fn buffer_mut(&mut self) -> &mut PieceTable {
 		let doc = self.get_active_document_mut().unwrap();
 		doc.buffer_mut()
 	}
 }
+
+/// The system clipboard register `name` stands for.
+fn clipboard_kind(name: char) -> Option<ClipboardKind> {
+	match name {
+		register::CLIPBOARD => Some(ClipboardKind::Clipboard),
+		register::SELECTION => Some(ClipboardKind::Selection),
+		_ => None,
+	}
+}
+
+/// Runs `line` through the user's shell, so pipes and quotes work as typed.
+fn shell_command(env: &impl Environment, line: &str) -> Command {
+	if cfg!(windows) {
+		return Command::new("cmd").args(&["/C", line]);
+	}
+
+	let shell = env.var("SHELL").filter(|shell| !shell.is_empty());
+	Command::new(shell.as_deref().unwrap_or("sh")).args(&["-c", line])
+}
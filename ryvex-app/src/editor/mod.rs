@@ -1,2 +1,3 @@
 pub mod document;
+#[allow(clippy::module_inception)]
 pub mod editor;
@@ -7,6 +7,7 @@ use core::fmt::{
 	Display,
 };
 
+use ryvex_core::line_range::LineRangeError;
 use ryvex_target::std::{
 	error::Error,
 	StdError,
@@ -15,6 +16,7 @@ use ryvex_target::std::{
 #[derive(Clone, PartialEq)]
 pub enum RyvexError {
 	StdError(StdError),
+	LineRangeError(LineRangeError),
 	LoggerError(String),
 	ArgParseError(String),
 }
@@ -23,6 +25,7 @@ impl Error for RyvexError {
 	fn source(&self) -> Option<&(dyn Error + 'static)> {
 		match self {
 			RyvexError::StdError(err) => Some(err),
+			RyvexError::LineRangeError(err) => Some(err),
 			RyvexError::LoggerError(_) => None,
 			RyvexError::ArgParseError(_) => None,
 		}
@@ -33,6 +36,7 @@ impl Display for RyvexError {
 	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> fmt::Result {
 		let s = match self {
 			RyvexError::StdError(_) => "std error",
+			RyvexError::LineRangeError(_) => "invalid line range",
 			RyvexError::LoggerError(msg) => {
 				&format!("failed initializing logger '{}'", msg)
 			}
@@ -51,4 +55,10 @@ impl From<StdError> for RyvexError {
 	}
 }
 
+impl From<LineRangeError> for RyvexError {
+	fn from(value: LineRangeError) -> Self {
+		Self::LineRangeError(value)
+	}
+}
+
 pub type Result<T> = core::result::Result<T, RyvexError>;
@@ -1,13 +1,17 @@
-use ryvex_core::motion::Motion;
-use ryvex_target::key::AsciiKeyCode;
+use ryvex_core::{
+	motion::{
+		Motion,
+		MotionType,
+	},
+	register,
+};
+pub use ryvex_target::key::KeyEvent;
 
 use crate::compositor::{
 	Context,
 	EventResult,
 };
 
-pub type KeyEvent = AsciiKeyCode;
-
 #[derive(Clone)]
 pub enum EditorCommand {
 	Typable {
@@ -20,16 +24,35 @@ pub enum EditorCommand {
 		doc: &'static str,
 	},
 	Macro(Vec<KeyEvent>),
+	/// Puts the selected register after (`p`) or before (`P`) the cursor.
+	Put {
+		before: bool,
+	},
+	/// Applies an operator to the visual selection.
+	Operator(MotionType),
+	/// Sets a mark at the cursor (`m`).
+	SetMark(char),
+	/// Jumps to a mark, to its line (`'`) or its exact position (`` ` ``).
+	JumpToMark {
+		mark:     char,
+		linewise: bool,
+	},
 }
 
+/// Builds a command from the character typed after a key sequence, like
+/// the target of `f` or the name of a mark, or refuses it with `None`.
+pub type CharCommand = Box<dyn Fn(char) -> Option<EditorCommand>>;
+
 #[derive(Default)]
 pub struct KeyNode {
-	pub command: Option<EditorCommand>,
-	pub next:    Vec<(AsciiKeyCode, Box<KeyNode>)>,
+	pub command:  Option<EditorCommand>,
+	/// Takes any character that none of the `next` keys match.
+	pub any_char: Option<CharCommand>,
+	pub next:     Vec<(KeyEvent, Box<KeyNode>)>,
 }
 
 impl KeyNode {
-	fn child_mut(&mut self, k: AsciiKeyCode) -> &mut KeyNode {
+	fn child_mut(&mut self, k: KeyEvent) -> &mut KeyNode {
 		if let Some(idx) = self.next.iter().position(|(key, _)| *key == k) {
 			return self.next[idx].1.as_mut();
 		}
@@ -38,14 +61,14 @@ impl KeyNode {
 		self.next.last_mut().unwrap().1.as_mut()
 	}
 
-	fn child(&self, k: AsciiKeyCode) -> Option<&KeyNode> {
+	fn child(&self, k: KeyEvent) -> Option<&KeyNode> {
 		self.next
 			.iter()
 			.find(|(key, _)| *key == k)
 			.map(|(_, node)| &**node)
 	}
 
-	pub fn bind(&mut self, seq: &[AsciiKeyCode], cmd: EditorCommand) {
+	pub fn bind(&mut self, seq: &[KeyEvent], cmd: EditorCommand) {
 		let mut node = self;
 		for &k in seq {
 			node = node.child_mut(k);
@@ -55,68 +78,152 @@ impl KeyNode {
 	}
 
 	pub fn bind_str(&mut self, seq: &str, cmd: EditorCommand) {
-		let keys: Vec<AsciiKeyCode> =
-			seq.bytes().map(AsciiKeyCode::from).collect();
+		let keys: Vec<KeyEvent> = seq.chars().map(KeyEvent::from).collect();
 		self.bind(&keys, cmd);
 	}
+
+	/// Binds `seq` followed by any one character to the command `f` builds
+	/// from that character.
+	pub fn bind_char(
+		&mut self,
+		seq: &str,
+		f: impl Fn(char) -> Option<EditorCommand> + 'static,
+	) {
+		let mut node = self;
+		for k in seq.chars().map(KeyEvent::from) {
+			node = node.child_mut(k);
+		}
+
+		node.any_char = Some(Box::new(f));
+	}
 }
 
 pub struct KeyParser<'a> {
-	keymap: &'a KeyNode,
-	cursor: &'a KeyNode,
-	count:  Option<u32>,
+	keymap:    &'a KeyNode,
+	cursor:    &'a KeyNode,
+	count:     Option<u32>,
+	/// Whether leading digits are a count in the current keymap, rather
+	/// than text.
+	counts:    bool,
+	/// Whether `"x` selects a register in the current keymap.
+	registers: bool,
+	register:  Option<char>,
+	/// Set after `"` until the register name arrives.
+	naming:    bool,
 }
 
 impl<'a> KeyParser<'a> {
 	pub fn new(root: &'a KeyNode) -> Self {
 		Self {
-			keymap: root,
-			cursor: root,
-			count:  None,
+			keymap:    root,
+			cursor:    root,
+			count:     None,
+			counts:    true,
+			registers: false,
+			register:  None,
+			naming:    false,
 		}
 	}
 
+	/// Switches to `root`, dropping any pending sequence if it differs from
+	/// the current keymap.
 	pub fn set_keymap(&mut self, root: &'a KeyNode) {
-		self.keymap = root;
-		self.reset();
+		if !core::ptr::eq(self.keymap, root) {
+			self.keymap = root;
+			self.reset();
+		}
 	}
 
-	pub fn feed(&mut self, key: AsciiKeyCode) -> ParseResult<'a> {
-		if core::ptr::eq(self.cursor, self.keymap) && key.is_digit() {
-			let d = key.to_char().to_digit(10).unwrap();
-			self.count = Some(self.count.unwrap_or(0) * 10 + d);
+	pub fn accept_counts(&mut self, accept: bool) {
+		self.counts = accept;
+	}
+
+	pub fn accept_registers(&mut self, accept: bool) {
+		self.registers = accept;
+	}
+
+	pub fn feed(&mut self, key: KeyEvent) -> ParseResult {
+		let at_root = core::ptr::eq(self.cursor, self.keymap);
+		let ch = key.as_char();
+
+		if self.naming {
+			self.naming = false;
+			match ch {
+				Some(name) if register::is_valid_name(name) => {
+					self.register = Some(name);
+					return ParseResult::Incomplete;
+				}
+				_ => {
+					self.reset();
+					return ParseResult::Error;
+				}
+			}
+		}
 
+		if at_root && self.registers && ch == Some('"') {
+			self.naming = true;
 			return ParseResult::Incomplete;
 		}
 
+		if let Some(d) = ch.and_then(|ch| ch.to_digit(10)) {
+			if at_root && self.counts && (d != 0 || self.count.is_some()) {
+				self.count = Some(self.count.unwrap_or(0) * 10 + d);
+
+				return ParseResult::Incomplete;
+			}
+		}
+
 		match self.cursor.child(key) {
 			Some(node) => {
 				self.cursor = node;
 
 				match &self.cursor.command {
 					None => ParseResult::Incomplete,
-					Some(cmd) => {
-						let repeat = self.count.take();
-						self.reset();
-						ParseResult::Command(cmd, repeat)
-					}
+					Some(cmd) => self.complete(cmd.clone()),
 				}
 			}
 			None => {
-				self.reset();
-				ParseResult::Error
+				let any_char = self.cursor.any_char.as_ref();
+				match any_char.zip(ch).and_then(|(f, ch)| f(ch)) {
+					Some(cmd) => self.complete(cmd),
+					None => {
+						self.reset();
+						ParseResult::Error
+					}
+				}
 			}
 		}
 	}
 
+	fn complete(&mut self, cmd: EditorCommand) -> ParseResult {
+		let repeat = self.count.take();
+		let register = self.register.take();
+		self.reset();
+		ParseResult::Command(cmd, repeat, register)
+	}
+
+	/// Drops a pending key sequence once keys stopped coming, like Vim's
+	/// `timeoutlen`. Returns whether there was one.
+	pub fn timeout(&mut self) -> bool {
+		let pending = !core::ptr::eq(self.cursor, self.keymap) ||
+			self.count.is_some() ||
+			self.register.is_some() ||
+			self.naming;
+		self.reset();
+		pending
+	}
+
 	fn reset(&mut self) {
 		self.cursor = self.keymap;
 		self.count = None;
+		self.register = None;
+		self.naming = false;
 	}
 }
 
-pub enum ParseResult<'a> {
+pub enum ParseResult {
 	Incomplete,
-	Command(&'a EditorCommand, Option<u32>),
+	/// A complete command with its count and selected register, if any.
+	Command(EditorCommand, Option<u32>, Option<char>),
 	Error,
 }
@@ -23,15 +23,12 @@ macro_rules! define_keymaps {
                     {
                         let root = &mut km.$mode;
                         $(
-                            let normalized: String = ::ryvex_target::key::AsciiKeyCode::parse_human_str($seq)
+                            let keys = ::ryvex_target::key::KeyEvent::parse_human_str($seq)
                                 .unwrap_or_else(|e| {
                                     panic!("Error parsing key‐sequence literal `{}`: {}", $seq, e)
-                                })
-                                .into_iter()
-                                .map(|kc| kc.to_char())
-                                .collect();
+                                });
 
-                            root.bind_str(&normalized, $cmd);
+                            root.bind(&keys, $cmd);
                         )*
                     }
                 )*
@@ -2,13 +2,16 @@
 #![cfg_attr(not(feature = "std"), feature(core_intrinsics))]
 extern crate alloc;
 
-use core::sync::atomic::Ordering;
 #[cfg(not(feature = "std"))]
 use core::{
 	error::Error,
 	panic::PanicInfo,
 	ptr,
 };
+use core::{
+	sync::atomic::Ordering,
+	time::Duration,
+};
 use ryvex_app::terminal_guard::TERMINAL_GUARD;
 
 use alloc::boxed::Box;
@@ -40,6 +43,10 @@ use ryvex_target::{
 	term::event::SyncEventStream,
 };
 
+/// How long the editor waits for input before it does idle work, and before
+/// a pending key sequence is dropped, like Vim's `timeoutlen`.
+const TICK: Duration = Duration::from_millis(1000);
+
 fn main() -> ! {
 	let exit_code: i32 = app_main().unwrap_or(1);
 
@@ -55,7 +62,7 @@ fn app_main() -> Result<i32> {
 		return Ok(0);
 	}
 
-	let guard = Box::new(TerminalGuard::spawn()?);
+	let guard = Box::new(TerminalGuard::spawn(&cx)?);
 	TERMINAL_GUARD
 		.store(&*guard as *const _ as *mut TerminalGuard, Ordering::SeqCst);
 
@@ -63,9 +70,9 @@ fn app_main() -> Result<i32> {
 	setup_panic_handler();
 
 	setup_logging(&cx.fs, args.verbosity);
-	let mut app = Application::build(cx, args)?;
+	let mut app = Application::build(cx, args, guard.capabilities().clone())?;
 
-	let mut event_stream = SyncEventStream::new()?;
+	let mut event_stream = SyncEventStream::new()?.ticking(TICK);
 	let exit_code = app.run_until_stopped(&mut event_stream)?;
 
 	LOGGER.flush();
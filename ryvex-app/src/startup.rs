@@ -1,15 +1,22 @@
+use ryvex_core::error;
 use ryvex_target::{
+	std::clipboard,
 	target::TargetContext,
 	target::{
 		self,
 		term::Handle,
+		TargetOutWriter,
+	},
+	term::{
+		capabilities::Capabilities,
+		event::Event,
 	},
-	term::event::Event,
 };
 use ryvex_tui::{
 	backend::term::TerminalBackend,
 	terminal::Terminal,
 };
+use ryvex_ui::graphics::Rect;
 
 use alloc::boxed::Box;
 
@@ -35,8 +42,18 @@ pub struct Application {
 }
 
 impl Application {
-	pub fn build(cx: TargetContext, args: Args) -> Result<Self> {
+	pub fn build(
+		cx: TargetContext,
+		args: Args,
+		capabilities: Capabilities,
+	) -> Result<Self> {
 		let mut editor = Editor::new();
+		editor.set_clipboard(clipboard::detect(
+			&cx.env,
+			&cx.fs,
+			cx.shell.clone(),
+			TargetOutWriter::default(),
+		));
 		let document = Document::new(args.file, &cx.fs)?;
 		let _id = editor.new_document(document);
 
@@ -50,7 +67,10 @@ impl Application {
 		compositor.push(Box::new(ui::LoggingLine::new()));
 		compositor.push(Box::new(ui::CommandLine::new()));
 
-		let terminal = Terminal::new(TerminalBackend::new(handle))?;
+		let mut terminal =
+			Terminal::new(TerminalBackend::new(handle, capabilities))?;
+		// frames only draw what changed, starting from a blank screen
+		terminal.clear()?;
 
 		Ok(Application {
 			editor,
@@ -93,20 +113,18 @@ impl Application {
 	}
 
 	fn render(&mut self) {
-		self.terminal.clear().expect("Failed to clear terminal");
-
 		let mut cx = crate::compositor::Context {
 			editor:    &mut self.editor,
 			target_cx: &mut self.target_cx,
 		};
 
-		let area = self.terminal.size().expect("Failed to get terminal size");
+		let area = self.compositor.size();
 		let surface = self.terminal.current_buffer_mut();
 
 		self.compositor.render(area, surface, &mut cx);
-		let _ = self
-			.terminal
-			.draw(None, ryvex_ui::graphics::CursorKind::Block);
+
+		let (cursor, kind) = self.compositor.cursor(area, &self.editor);
+		let _ = self.terminal.draw(cursor, kind);
 	}
 
 	fn handle_terminal_event(&mut self, event: Event) {
@@ -116,8 +134,27 @@ impl Application {
 		};
 
 		let should_redraw = match event {
-			Event::Resize(_, _) => {
-				todo!("I dont know how to handle resize event yet :/")
+			Event::Resize(width, height) => {
+				let area = Rect {
+					x: 0,
+					y: 0,
+					width,
+					height,
+				};
+				self.compositor.resize(area);
+				// the next resize or key press draws again
+				match self.terminal.resize(area) {
+					Ok(()) => true,
+					Err(e) => {
+						error!("Failed to resize the terminal: {e}");
+						false
+					}
+				}
+			}
+			// leave through the normal exit, which puts the terminal back
+			Event::Terminate => {
+				self.editor.quit();
+				false
 			}
 			e => self.compositor.handle_event(&e, &mut cx),
 		};
@@ -2,44 +2,127 @@ use core::{
 	marker::PhantomData,
 	ptr,
 	sync::atomic::{
+		AtomicBool,
 		AtomicPtr,
 		Ordering,
 	},
 };
 
 use ryvex_target::{
+	execute,
+	queue,
+	r#impl::TargetOutWriter,
 	std::Result,
-	target::term::{
-		ConsoleSettings,
-		Handle,
+	target::{
+		term::{
+			ConsoleSettings,
+			Handle,
+		},
+		TargetContext,
+	},
+	term::{
+		capabilities::Capabilities,
+		command::{
+			cursor::{
+				SetCursorStyle,
+				Show,
+			},
+			terminal::{
+				DisableBracketedPaste,
+				DisableMouseCapture,
+				EnableBracketedPaste,
+				EnableMouseCapture,
+				EnterAlternateScreen,
+				LeaveAlternateScreen,
+				PopKeyboardEnhancement,
+				PushKeyboardEnhancement,
+			},
+		},
+		console::Console,
 	},
-	term::console::Console,
 };
 
 pub static TERMINAL_GUARD: AtomicPtr<TerminalGuard> =
 	AtomicPtr::new(ptr::null_mut());
 
+/// Owns the terminal while the editor runs: raw mode, the alternate screen,
+/// mouse capture, bracketed paste and the keyboard protocol, plus the cursor
+/// shape and visibility the editor changes as it draws. [`restore`] hands
+/// all of it back, whether the editor quits, panics or is terminated.
+///
+/// [`restore`]: TerminalGuard::restore
 pub struct TerminalGuard<'a> {
 	handle:       Handle,
 	orig_console: ConsoleSettings,
+	capabilities: Capabilities,
+	/// Cleared by the first restore, so the panic hook and drop do not
+	/// restore twice.
+	active:       AtomicBool,
 
 	_phantom: PhantomData<&'a ()>,
 }
 
 impl<'a> TerminalGuard<'a> {
-	pub fn spawn() -> Result<Self> {
+	pub fn spawn(cx: &TargetContext) -> Result<Self> {
 		let (mut console, handle) = ConsoleSettings::init()?;
 		let orig_console = console.raw(&handle)?;
+		// the replies to the queries only come back in raw mode
+		let capabilities = Capabilities::detect(&cx.env, &cx.fs);
+
+		let mut out = TargetOutWriter::default();
+		execute!(
+			out,
+			EnterAlternateScreen,
+			EnableMouseCapture,
+			EnableBracketedPaste
+		)?;
+		if capabilities.keyboard_enhancement {
+			execute!(out, PushKeyboardEnhancement)?;
+		}
 
 		Ok(TerminalGuard {
 			handle,
 			orig_console,
+			capabilities,
+			active: AtomicBool::new(true),
 			_phantom: core::marker::PhantomData,
 		})
 	}
 
+	/// What the terminal turned out to support.
+	pub fn capabilities(&self) -> &Capabilities {
+		&self.capabilities
+	}
+
 	pub fn restore(&self) -> Result<()> {
-		ConsoleSettings::restore(&self.handle, self.orig_console)
+		if !self.active.swap(false, Ordering::SeqCst) {
+			return Ok(());
+		}
+
+		// the console settings go back even if the terminal stopped listening
+		let left = self.leave();
+		ConsoleSettings::restore(&self.handle, self.orig_console)?;
+		left
+	}
+
+	/// Switches off what [`spawn`](Self::spawn) switched on, and the cursor
+	/// changes the editor made while drawing.
+	fn leave(&self) -> Result<()> {
+		let mut out = TargetOutWriter::default();
+		if self.capabilities.keyboard_enhancement {
+			queue!(out, PopKeyboardEnhancement)?;
+		}
+		if self.capabilities.cursor_style {
+			queue!(out, SetCursorStyle::DefaultUserShape)?;
+		}
+		execute!(
+			out,
+			DisableBracketedPaste,
+			DisableMouseCapture,
+			Show,
+			LeaveAlternateScreen
+		)?;
+		Ok(())
 	}
 }
 
@@ -53,3 +136,45 @@ impl<'a> Drop for TerminalGuard<'a> {
 
 unsafe impl<'a> Sync for TerminalGuard<'a> {}
 unsafe impl<'a> Send for TerminalGuard<'a> {}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::{
+		fs::OpenOptions,
+		os::fd::{
+			AsRawFd,
+			OwnedFd,
+		},
+	};
+
+	use super::*;
+
+	#[test]
+	fn restore_runs_once() {
+		let pty = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open("/dev/ptmx")
+			.unwrap();
+		let fd = pty.as_raw_fd();
+		let handle = Handle::from(OwnedFd::from(pty));
+		let mut raw = ConsoleSettings::from_fd(fd).unwrap();
+		let orig_console = raw.raw(&handle).unwrap();
+
+		let guard = TerminalGuard {
+			handle,
+			orig_console,
+			capabilities: Capabilities::default(),
+			active: AtomicBool::new(true),
+			_phantom: PhantomData,
+		};
+		guard.restore().unwrap();
+		assert_eq!(ConsoleSettings::from_fd(fd).unwrap(), orig_console);
+
+		// once restored, the guard leaves the terminal alone
+		ConsoleSettings::restore(&guard.handle, raw).unwrap();
+		guard.restore().unwrap();
+		assert_eq!(ConsoleSettings::from_fd(fd).unwrap(), raw);
+		assert!(!guard.active.load(Ordering::SeqCst));
+	}
+}
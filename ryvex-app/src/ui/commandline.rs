@@ -1,7 +1,10 @@
 use alloc::format;
 use ryvex_target::term::event::Event;
 use ryvex_tui::buffer::Buffer;
-use ryvex_ui::graphics::Rect;
+use ryvex_ui::graphics::{
+	CursorKind,
+	Rect,
+};
 
 use crate::{
 	compositor::{
@@ -9,7 +12,10 @@ use crate::{
 		Context,
 		EventResult,
 	},
-	editor::document::Mode,
+	editor::{
+		document::Mode,
+		editor::Editor,
+	},
 };
 
 pub struct CommandLine;
@@ -46,6 +52,24 @@ impl Component for CommandLine {
 		EventResult::Ignored(None)
 	}
 
+	fn cursor(
+		&self,
+		area: Rect,
+		editor: &Editor,
+	) -> (Option<(u16, u16)>, CursorKind) {
+		if editor.mode != Mode::Command {
+			return (None, CursorKind::Bar);
+		}
+
+		let y = area.y + area.height.saturating_sub(1);
+		let x = 1 + editor.command_buffer().chars().count() as u16;
+
+		(
+			Some((x.min(area.width.saturating_sub(1)), y)),
+			CursorKind::Bar,
+		)
+	}
+
 	fn should_update(&self) -> bool {
 		true
 	}
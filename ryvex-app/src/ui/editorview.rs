@@ -1,17 +1,42 @@
+use core::ops::Range;
+
 use ryvex_core::{
-	motion::{
-		self,
-		Motion,
-	},
+	grapheme::graphemes,
+	motion::Motion,
+	piece_table::RowCol,
 	warn,
+	TextBuffer,
 };
 use ryvex_target::{
-	key::AsciiKeyCode,
+	key::{
+		KeyCode,
+		KeyEvent,
+		Modifiers,
+	},
+	mouse::{
+		MouseButton,
+		MouseEvent,
+		MouseEventKind,
+	},
 	term::event::Event,
 };
 
+use ryvex_ui::{
+	graphics::{
+		CursorKind,
+		Rect,
+	},
+	style::{
+		Modifier,
+		Style,
+	},
+};
+
 use crate::{
-	commands::KeyMaps,
+	commands::{
+		self,
+		KeyMaps,
+	},
 	compositor::{
 		Component,
 		Context,
@@ -32,10 +57,17 @@ use crate::{
 };
 
 pub struct EditorView {
-	parser: KeyParser<'static>,
-	km:     &'static KeyMaps,
+	parser:   KeyParser<'static>,
+	km:       &'static KeyMaps,
+	/// First document row visible in the view.
+	offset:   usize,
+	/// The area of the last render, to map mouse events to the text.
+	viewport: Rect,
 }
 
+/// How many rows one notch of the mouse wheel scrolls.
+const SCROLL_ROWS: usize = 3;
+
 impl Default for EditorView {
 	fn default() -> Self {
 		Self::new()
@@ -45,15 +77,99 @@ impl Default for EditorView {
 impl EditorView {
 	pub fn new() -> Self {
 		let km: &'static mut KeyMaps = Box::leak(Box::new(KeyMaps::new()));
+		commands::bind_navigation(&mut km.normal);
+		commands::bind_operators(&mut km.normal);
+		commands::bind_marks(&mut km.normal);
+		commands::bind_visual(&mut km.visual);
+
 		let parser = KeyParser::new(&km.normal);
-		Self { parser, km }
+		Self {
+			parser,
+			km,
+			offset: 0,
+			viewport: Rect::default(),
+		}
+	}
+
+	fn text_rows(area: Rect) -> usize {
+		area.height.saturating_sub(2) as usize
+	}
+
+	/// Scrolls the view so the cursor row stays visible.
+	fn scroll_to_cursor(&mut self, doc: &Document, area: Rect) {
+		let rows = Self::text_rows(area).max(1);
+		let row = doc.current_row();
+
+		if row < self.offset {
+			self.offset = row;
+		} else if row >= self.offset + rows {
+			self.offset = row + 1 - rows;
+		}
+	}
+
+	fn text_area(viewport: Rect) -> Rect {
+		Rect {
+			height: Self::text_rows(viewport) as u16,
+			..viewport
+		}
+	}
+
+	/// Clicking places the cursor, dragging selects and the wheel scrolls.
+	fn handle_mouse(
+		&mut self,
+		mouse: &MouseEvent,
+		cx: &mut Context,
+	) -> EventResult {
+		let row =
+			self.offset + mouse.row.saturating_sub(self.viewport.y) as usize;
+		let col = mouse.column.saturating_sub(self.viewport.x) as usize;
+
+		match mouse.kind {
+			MouseEventKind::Down(MouseButton::Left) => {
+				cx.editor.click(row, col)
+			}
+			MouseEventKind::Drag(MouseButton::Left) => cx.editor.drag(row, col),
+			MouseEventKind::ScrollUp => self.scroll(cx.editor, true),
+			MouseEventKind::ScrollDown => self.scroll(cx.editor, false),
+			_ => return EventResult::Ignored(None),
+		}
+
+		// the mouse interrupts whatever was being typed
+		self.parser.timeout();
+		self.switch_keymap(cx.editor.mode);
+		EventResult::Consumed(None)
+	}
+
+	/// Scrolls the view by [`SCROLL_ROWS`] and takes the cursor along where
+	/// it would leave the view.
+	fn scroll(&mut self, editor: &mut Editor, up: bool) {
+		let Some(doc) = editor.get_active_document() else {
+			return;
+		};
+
+		let last = doc.rows().saturating_sub(1);
+		self.offset = if up {
+			self.offset.saturating_sub(SCROLL_ROWS)
+		} else {
+			(self.offset + SCROLL_ROWS).min(last)
+		};
+
+		let rows = Self::text_rows(self.viewport).max(1);
+		editor.keep_cursor_within(self.offset, self.offset + rows - 1);
 	}
 
 	fn switch_keymap(&mut self, mode: Mode) {
+		// typed digits are text in insert and command mode
+		let operating = mode == Mode::Normal || mode.is_visual();
+		self.parser.accept_counts(operating);
+		self.parser.accept_registers(operating);
+
 		match mode {
 			Mode::Normal => self.parser.set_keymap(&self.km.normal),
 			Mode::Insert => self.parser.set_keymap(&self.km.insert),
-			Mode::Visual => self.parser.set_keymap(&self.km.normal),
+			Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
+				self.parser.set_keymap(&self.km.visual)
+			}
 			Mode::Command => self.parser.set_keymap(&self.km.command),
 		}
 	}
@@ -61,25 +177,71 @@ impl EditorView {
 	pub fn render_view(
 		&self,
 		frame: &mut ryvex_tui::buffer::Buffer,
-		_editor: &Editor,
+		editor: &Editor,
 		doc: &Document,
 		area: ryvex_ui::graphics::Rect,
 	) {
-		let max_rows = area.height.saturating_sub(2);
-		for (row_idx, line) in doc.content().lines().enumerate() {
-			if row_idx as u16 >= max_rows {
-				break;
-			}
+		let max_rows = Self::text_rows(area);
+		let content = doc.content();
+		let visible = content.lines().skip(self.offset).take(max_rows);
+
+		let selected = if editor.mode.is_visual() {
+			doc.visual_ranges(editor.mode)
+		} else {
+			Vec::new()
+		};
+		let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+		for (row_idx, line) in visible.enumerate() {
 			let y = area.y + row_idx as u16;
-			let slice = &line[..line.len().min(area.width as usize)];
-			frame.set_string(area.x, y, slice);
+			let clusters = graphemes(line).take(area.width as usize);
+			frame.set_symbols(area.x, y, clusters);
+
+			let start = doc.buffer().pos_from(RowCol {
+				row: self.offset + row_idx,
+				col: 0,
+			});
+			for range in &selected {
+				let Some(cells) = selected_cells(line, start, range) else {
+					continue;
+				};
+				let (left, right) = (
+					cells.start.min(area.width as usize) as u16,
+					cells.end.min(area.width as usize) as u16,
+				);
+				let cells = Rect {
+					x: area.x + left,
+					y,
+					width: right - left,
+					height: 1,
+				};
+				frame.set_style(cells, highlight);
+			}
 		}
 	}
 
+	/// Runs `cmd` and, unless it left the editor in insert mode, closes the
+	/// undo step so every command is undone on its own and a whole insert
+	/// session at once.
 	fn execute(
 		&mut self,
 		cmd: &EditorCommand,
 		repeat: Option<u32>,
+		register: Option<char>,
+		cx: &mut Context,
+	) -> EventResult {
+		let res = self.run_command(cmd, repeat, register, cx);
+		if cx.editor.mode != Mode::Insert {
+			cx.editor.commit_history();
+		}
+		res
+	}
+
+	fn run_command(
+		&mut self,
+		cmd: &EditorCommand,
+		repeat: Option<u32>,
+		register: Option<char>,
 		cx: &mut Context,
 	) -> EventResult {
 		match cmd {
@@ -91,7 +253,27 @@ impl EditorView {
 				} else {
 					motion.clone()
 				};
-				motion::apply(cx.editor.buffer_mut(), effective);
+				cx.editor.apply_motion(effective, register);
+				EventResult::Consumed(None)
+			}
+
+			EditorCommand::Put { before } => {
+				cx.editor.put(register, *before, repeat.unwrap_or(1));
+				EventResult::Consumed(None)
+			}
+
+			EditorCommand::Operator(op) => {
+				cx.editor.apply_visual(*op, register);
+				EventResult::Consumed(None)
+			}
+
+			EditorCommand::SetMark(mark) => {
+				cx.editor.set_mark(*mark);
+				EventResult::Consumed(None)
+			}
+
+			EditorCommand::JumpToMark { mark, linewise } => {
+				cx.editor.jump_to_mark(*mark, *linewise, &cx.target_cx.fs);
 				EventResult::Consumed(None)
 			}
 
@@ -112,21 +294,24 @@ impl EditorView {
 		}
 	}
 
-	fn insert_default(&self, key: AsciiKeyCode, cx: &mut Context) {
-		match key {
-			AsciiKeyCode::Backspace | AsciiKeyCode::Del => {
+	fn insert_default(&self, key: KeyEvent, cx: &mut Context) {
+		match key.code {
+			KeyCode::Backspace | KeyCode::Delete => {
+				cx.editor.delete_at_cursor()
+			}
+			KeyCode::Char('h') if key.modifiers == Modifiers::CTRL => {
 				cx.editor.delete_at_cursor()
 			}
-			AsciiKeyCode::CarriageReturn | AsciiKeyCode::LineFeed => {
+			KeyCode::Enter => cx.editor.insert_character('\n'),
+			KeyCode::Char('j') if key.modifiers == Modifiers::CTRL => {
 				cx.editor.insert_character('\n')
 			}
-			k if k.is_control_character() => {}
-			k if k.is_seperator() => {
-				if k == AsciiKeyCode::Space {
-					cx.editor.insert_character(' ');
+			KeyCode::Tab => cx.editor.insert_character('\t'),
+			_ => {
+				if let Some(ch) = key.as_char() {
+					cx.editor.insert_character(ch);
 				}
 			}
-			printable => cx.editor.insert_character(printable.to_char()),
 		}
 	}
 }
@@ -139,9 +324,36 @@ impl Component for EditorView {
 		cx: &mut Context,
 	) {
 		let doc = cx.editor.get_active_document().expect("");
+		self.viewport = area;
+		self.scroll_to_cursor(doc, area);
 		self.render_view(frame, cx.editor, doc, area);
 	}
 
+	fn cursor(
+		&self,
+		area: Rect,
+		editor: &Editor,
+	) -> (Option<(u16, u16)>, CursorKind) {
+		let kind = match editor.mode {
+			Mode::Insert => CursorKind::Bar,
+			_ => CursorKind::Block,
+		};
+
+		let Some(doc) = editor.get_active_document() else {
+			return (None, kind);
+		};
+
+		let row = doc.current_row().saturating_sub(self.offset);
+		if row >= Self::text_rows(area) {
+			return (None, kind);
+		}
+
+		let x = area.x.saturating_add(doc.current_col() as u16);
+		let y = area.y.saturating_add(row as u16);
+
+		(Some((x, y)), kind)
+	}
+
 	fn handle_event(
 		&mut self,
 		event: &ryvex_target::term::event::Event,
@@ -159,8 +371,8 @@ impl Component for EditorView {
 						ParseResult::Incomplete => {
 							return EventResult::Consumed(None);
 						}
-						ParseResult::Command(cmd, repeat) => {
-							let res = self.execute(cmd, repeat, cx);
+						ParseResult::Command(cmd, repeat, register) => {
+							let res = self.execute(&cmd, repeat, register, cx);
 							self.switch_keymap(cx.editor.mode);
 							return res;
 						}
@@ -179,8 +391,8 @@ impl Component for EditorView {
 					ParseResult::Incomplete => {
 						return EventResult::Consumed(None)
 					}
-					ParseResult::Command(cmd, repeat) => {
-						let res = self.execute(cmd, repeat, cx);
+					ParseResult::Command(cmd, repeat, register) => {
+						let res = self.execute(&cmd, repeat, register, cx);
 						self.switch_keymap(cx.editor.mode);
 						return res;
 					}
@@ -190,7 +402,20 @@ impl Component for EditorView {
 					}
 				}
 			}
-			Event::Resize(_, _) => { /* TODO */ }
+			Event::Mouse(mouse) => return self.handle_mouse(mouse, cx),
+			Event::Paste(text) => {
+				self.parser.timeout();
+				cx.editor.paste(text);
+			}
+			// the application resizes the compositor and the terminal itself
+			Event::Resize(..) | Event::Terminate => {
+				return EventResult::Ignored(None)
+			}
+			Event::Tick => {
+				if !self.parser.timeout() {
+					return EventResult::Ignored(None);
+				}
+			}
 		}
 
 		EventResult::Consumed(None)
@@ -199,15 +424,9 @@ impl Component for EditorView {
 	fn should_update(&self) -> bool {
 		true
 	}
-}
 
-fn multiply_motion_count(motion: &mut Motion, r: u32) {
-	match motion {
-		Motion::NavigationOnly { count, .. } |
-		Motion::OperatedNavigation { count, .. } => {
-			*count = count.saturating_mul(r);
-		}
-		_ => {}
+	fn area(&self, viewport: Rect, _editor: &Editor) -> Option<Rect> {
+		Some(Self::text_area(viewport))
 	}
 }
 
@@ -241,3 +460,25 @@ fn scaled_motion(m: &Motion, mult: u32) -> Motion {
 		},
 	}
 }
+
+/// The screen columns of `line`, which starts at `start`, that `range`
+/// covers. The column past the end of the line stands for its line break.
+fn selected_cells(
+	line: &str,
+	start: usize,
+	range: &Range<usize>,
+) -> Option<Range<usize>> {
+	let end = start + line.len();
+	if range.end <= start || range.start > end {
+		return None;
+	}
+
+	let cells = |pos: usize| graphemes(&line[..pos - start]).count();
+	let first = cells(range.start.max(start));
+	let last = if range.end > end {
+		cells(end) + 1
+	} else {
+		cells(range.end)
+	};
+	(first < last).then_some(first..last)
+}
@@ -1,13 +1,22 @@
-use crate::compositor::{
-	Component,
-	Context,
-	EventResult,
+use crate::{
+	compositor::{
+		Component,
+		Context,
+		EventResult,
+	},
+	editor::editor::Editor,
+};
+use alloc::{
+	format,
+	string::String,
 };
-use alloc::format;
 use ryvex_core::logging::record::RecordSnapshot;
 use ryvex_core::logging::LOGGER;
 use ryvex_target::{
-	key::AsciiKeyCode,
+	key::{
+		KeyCode,
+		KeyEvent,
+	},
 	term::event::Event,
 };
 use ryvex_tui::buffer::Buffer;
@@ -24,9 +33,9 @@ impl LoggingKeyMap {
 	pub fn new() -> Self {
 		Self
 	}
-	pub fn feed(&self, key: AsciiKeyCode) -> Option<LoggingCommand> {
-		match key {
-			AsciiKeyCode::CarriageReturn => Some(LoggingCommand::Acknowledge),
+	pub fn feed(&self, key: KeyEvent) -> Option<LoggingCommand> {
+		match key.code {
+			KeyCode::Enter => Some(LoggingCommand::Acknowledge),
 			_ => None,
 		}
 	}
@@ -101,6 +110,21 @@ impl LoggingLine {
 	}
 }
 
+/// The bottom third of `area`, where an error is shown.
+fn error_area(area: Rect) -> Rect {
+	let height = core::cmp::max(1, area.height / 3);
+	area.clip_top(area.height.saturating_sub(height))
+}
+
+/// The rows at the bottom of `area` that fit `output` and the prompt below
+/// it.
+fn output_area(area: Rect, output: &str) -> Rect {
+	let height = (output.lines().count() + 1).min(area.height as usize);
+	area.clip_top(area.height - height as u16)
+}
+
+const OUTPUT_PROMPT: &str = "Press ENTER to continue";
+
 struct LineView<'a> {
 	snap:  &'a RecordSnapshot,
 	width: u16,
@@ -139,7 +163,9 @@ impl<'a> core::fmt::Display for ErrorFrameView<'a> {
 
 		if let Some(chain) = &self.snap.chain {
 			for (i, frame) in chain.iter().enumerate() {
-				lines.push(format!("{:>2}: {frame}", i));
+				// frames like a command's stderr span several lines
+				let frame = format!("{:>2}: {frame}", i);
+				lines.extend(frame.lines().map(String::from));
 			}
 
 			if self.snap.truncated {
@@ -176,17 +202,10 @@ impl Component for LoggingLine {
 		}
 	}
 
-	fn render(&mut self, area: Rect, frame: &mut Buffer, _cx: &mut Context) {
+	fn render(&mut self, area: Rect, frame: &mut Buffer, cx: &mut Context) {
 		self.poll_logger();
 		if let Some(err) = &self.active_error {
-			let h = core::cmp::max(1, area.height / 3);
-			let y0 = area.y + area.height - h;
-			let error_rect = Rect {
-				x:      area.x,
-				y:      y0,
-				width:  area.width,
-				height: h,
-			};
+			let error_rect = error_area(area);
 
 			let view = ErrorFrameView {
 				snap:   err,
@@ -194,13 +213,21 @@ impl Component for LoggingLine {
 				height: error_rect.height,
 			};
 
-			let mut y = error_rect.y;
-			for (i, line) in view.to_string().split('\n').enumerate() {
-				if (i as u16) >= error_rect.height {
-					break;
-				}
+			let text = view.to_string();
+			let lines = text.split('\n').take(error_rect.height as usize);
+			for (y, line) in (error_rect.y..).zip(lines) {
 				frame.set_string(error_rect.x, y, line);
-				y += 1;
+			}
+		} else if let Some(output) = cx.editor.command_output() {
+			let output_rect = output_area(area, output);
+			let width = output_rect.width as usize;
+			// the rest is cut off, the prompt always shows
+			let shown = output_rect.height.saturating_sub(1) as usize;
+			let lines = output.lines().take(shown).chain([OUTPUT_PROMPT]);
+
+			for (y, line) in (output_rect.y..).zip(lines) {
+				let line: String = line.chars().take(width).collect();
+				frame.set_string(output_rect.x, y, format!("{line:width$}"));
 			}
 		} else if let Some(line_snap) = &self.last_info_warn {
 			let view = LineView {
@@ -220,13 +247,30 @@ impl Component for LoggingLine {
 		self.dirty = false;
 	}
 
-	fn handle_event(
-		&mut self,
-		event: &Event,
-		_cx: &mut Context,
-	) -> EventResult {
+	fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+		if *event == Event::Tick {
+			// show what was logged in the background
+			self.poll_logger();
+			return if self.dirty {
+				EventResult::Consumed(None)
+			} else {
+				EventResult::Ignored(None)
+			};
+		}
+
 		if self.active_error.is_none() {
-			return EventResult::Ignored(None);
+			return match event {
+				Event::Key(kc) if cx.editor.command_output().is_some() => {
+					if let Some(LoggingCommand::Acknowledge) =
+						self.keymap.feed(*kc)
+					{
+						cx.editor.clear_command_output();
+					}
+					// the output stays until it is acknowledged
+					EventResult::Consumed(None)
+				}
+				_ => EventResult::Ignored(None),
+			};
 		}
 
 		match event {
@@ -244,6 +288,17 @@ impl Component for LoggingLine {
 		}
 	}
 
+	/// The error and output frames cover the text below them, so they take
+	/// the clicks.
+	fn area(&self, viewport: Rect, editor: &Editor) -> Option<Rect> {
+		if self.active_error.is_some() {
+			return Some(error_area(viewport));
+		}
+		editor
+			.command_output()
+			.map(|output| output_area(viewport, output))
+	}
+
 	fn should_update(&self) -> bool {
 		self.dirty
 	}
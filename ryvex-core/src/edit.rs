@@ -0,0 +1,116 @@
+use alloc::{
+	string::String,
+	vec::Vec,
+};
+
+use crate::{
+	piece_table::RowCol,
+	TextBuffer,
+};
+
+/// A change to a buffer: `removed` bytes at `start` were replaced by
+/// `inserted` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+	pub start:    usize,
+	pub removed:  usize,
+	pub inserted: usize,
+}
+
+impl Edit {
+	/// Where `pos` ends up after the edit. Positions inside removed text move
+	/// to where it was, positions at an insertion point move past the
+	/// inserted text.
+	pub fn map(&self, pos: usize) -> usize {
+		if pos < self.start {
+			pos
+		} else if pos >= self.start + self.removed {
+			pos - self.removed + self.inserted
+		} else {
+			self.start
+		}
+	}
+}
+
+/// Wraps a buffer and records every edit made through it, so positions kept
+/// outside the buffer can follow along.
+pub struct Recorder<'a, B: TextBuffer> {
+	buf:   &'a mut B,
+	edits: Vec<Edit>,
+}
+
+impl<'a, B: TextBuffer> Recorder<'a, B> {
+	pub fn new(buf: &'a mut B) -> Self {
+		Self {
+			buf,
+			edits: Vec::new(),
+		}
+	}
+
+	pub fn into_edits(self) -> Vec<Edit> {
+		self.edits
+	}
+}
+
+impl<B: TextBuffer> TextBuffer for Recorder<'_, B> {
+	fn len(&self) -> usize {
+		self.buf.len()
+	}
+
+	fn is_empty(&self) -> bool {
+		self.buf.is_empty()
+	}
+
+	fn char_at(&self, idx: usize) -> Option<char> {
+		self.buf.char_at(idx)
+	}
+
+	fn slice(&self, start: usize, end: usize) -> String {
+		self.buf.slice(start, end)
+	}
+
+	fn insert(&mut self, idx: usize, text: &str) {
+		let start = self.buf.floor_char_boundary(idx);
+		self.buf.insert(start, text);
+		self.edits.push(Edit {
+			start,
+			removed: 0,
+			inserted: text.len(),
+		});
+	}
+
+	fn delete(&mut self, start: usize, end: usize) {
+		let start = self.buf.floor_char_boundary(start);
+		let end = self.buf.ceil_char_boundary(end);
+		self.buf.delete(start, end);
+		self.edits.push(Edit {
+			start,
+			removed: end - start,
+			inserted: 0,
+		});
+	}
+
+	fn find(&self, pattern: &str, from: usize) -> Option<usize> {
+		self.buf.find(pattern, from)
+	}
+
+	fn chunk_at(&self, idx: usize) -> &str {
+		self.buf.chunk_at(idx)
+	}
+
+	fn rowcol_at(&self, idx: usize) -> RowCol {
+		self.buf.rowcol_at(idx)
+	}
+
+	fn pos_from(&self, rc: RowCol) -> usize {
+		self.buf.pos_from(rc)
+	}
+
+	fn lines(&self) -> usize {
+		self.buf.lines()
+	}
+
+	fn line_len(&self, row: usize) -> usize {
+		self.buf.line_len(row)
+	}
+}
@@ -0,0 +1,204 @@
+//! Grapheme cluster boundaries.
+//!
+//! A cluster is what the user perceives as a single character, e.g. `e`
+//! followed by a combining acute accent, an emoji joined by zero width
+//! joiners or a pair of regional indicators forming a flag. The rules are a
+//! practical subset of Unicode's extended grapheme clusters that need no
+//! tables beyond the ranges below.
+
+use crate::TextBuffer;
+
+const ZWJ: char = '\u{200D}';
+
+/// Whether `ch` attaches to the character before it.
+pub fn is_extend(ch: char) -> bool {
+	matches!(
+		ch as u32,
+		0x0300..=0x036F |
+			0x0483..=0x0489 |
+			0x0591..=0x05BD |
+			0x0610..=0x061A |
+			0x064B..=0x065F |
+			0x0670 |
+			0x0900..=0x0903 |
+			0x093A..=0x094F |
+			0x1AB0..=0x1AFF |
+			0x1DC0..=0x1DFF |
+			0x200C..=0x200D |
+			0x20D0..=0x20FF |
+			0x3099..=0x309A |
+			0xFE00..=0xFE0F |
+			0xFE20..=0xFE2F |
+			0x1F3FB..=0x1F3FF |
+			0xE0020..=0xE007F |
+			0xE0100..=0xE01EF
+	)
+}
+
+fn is_regional_indicator(ch: char) -> bool {
+	matches!(ch as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Whether `next` belongs to the same cluster as `prev`. `regional` is the
+/// number of regional indicators the cluster ends with.
+fn continues(prev: char, next: char, regional: usize) -> bool {
+	(prev == '\r' && next == '\n') ||
+		is_extend(next) ||
+		prev == ZWJ ||
+		(is_regional_indicator(next) && regional % 2 == 1)
+}
+
+/// Iterator over the grapheme clusters of a string.
+#[derive(Debug, Clone)]
+pub struct Graphemes<'a> {
+	rest: &'a str,
+}
+
+pub fn graphemes(s: &str) -> Graphemes<'_> {
+	Graphemes { rest: s }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<&'a str> {
+		let mut chars = self.rest.char_indices();
+		let (_, mut prev) = chars.next()?;
+		let mut regional = is_regional_indicator(prev) as usize;
+		let mut end = self.rest.len();
+
+		for (i, ch) in chars {
+			if !continues(prev, ch, regional) {
+				end = i;
+				break;
+			}
+			regional = if is_regional_indicator(ch) {
+				regional + 1
+			} else {
+				0
+			};
+			prev = ch;
+		}
+
+		let (cluster, rest) = self.rest.split_at(end);
+		self.rest = rest;
+		Some(cluster)
+	}
+}
+
+/// Byte offset of the character starting before `pos`.
+pub fn prev_char_boundary<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	buf.floor_char_boundary(pos.saturating_sub(1))
+}
+
+/// Byte offset of the cluster following the one at `pos`, or the buffer
+/// length if there is none.
+pub fn next_boundary<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	let pos = buf.ceil_char_boundary(pos);
+	let Some(mut prev) = buf.char_at(pos) else {
+		return buf.len();
+	};
+
+	let mut regional = is_regional_indicator(prev) as usize;
+	let mut p = pos + prev.len_utf8();
+
+	while let Some(ch) = buf.char_at(p) {
+		if !continues(prev, ch, regional) {
+			break;
+		}
+		regional = if is_regional_indicator(ch) {
+			regional + 1
+		} else {
+			0
+		};
+		prev = ch;
+		p += ch.len_utf8();
+	}
+	p
+}
+
+/// Byte offset of the cluster preceding `pos`.
+pub fn prev_boundary<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	if pos == 0 {
+		return 0;
+	}
+
+	// walk back to a character that certainly starts a cluster, then forward
+	// again so regional indicators pair up from the start of their run
+	let mut start = prev_char_boundary(buf, pos);
+	while start > 0 {
+		let before = prev_char_boundary(buf, start);
+		let (Some(prev), Some(ch)) = (buf.char_at(before), buf.char_at(start))
+		else {
+			break;
+		};
+
+		let joined = (prev == '\r' && ch == '\n') ||
+			is_extend(ch) ||
+			prev == ZWJ ||
+			(is_regional_indicator(prev) && is_regional_indicator(ch));
+		if !joined {
+			break;
+		}
+		start = before;
+	}
+
+	let mut boundary = start;
+	loop {
+		let next = next_boundary(buf, boundary);
+		if next >= pos {
+			return boundary;
+		}
+		boundary = next;
+	}
+}
+
+/// Rounds `pos` down to the start of the cluster containing it.
+pub fn floor_boundary<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	if pos >= buf.len() {
+		return buf.len();
+	}
+	prev_boundary(buf, next_boundary(buf, pos))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+	use alloc::vec::Vec;
+
+	#[test]
+	fn clusters() {
+		let text =
+			"e\u{301}a\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}\r\n👩\u{200D}💻";
+		let clusters: Vec<&str> = graphemes(text).collect();
+
+		assert_eq!(clusters, [
+			"e\u{301}",
+			"a",
+			"\u{1F1E9}\u{1F1EA}",
+			"\u{1F1EB}\u{1F1F7}",
+			"\r\n",
+			"👩\u{200D}💻"
+		]);
+
+		let buf = PieceTable::new(text.into());
+		let mut forward = Vec::new();
+		let mut p = 0;
+		while p < buf.len() {
+			forward.push(p);
+			p = next_boundary(&buf, p);
+		}
+
+		let mut backward = Vec::new();
+		let mut p = buf.len();
+		while p > 0 {
+			p = prev_boundary(&buf, p);
+			backward.push(p);
+		}
+		backward.reverse();
+
+		assert_eq!(forward, backward);
+		assert_eq!(floor_boundary(&buf, 1), 0);
+	}
+}
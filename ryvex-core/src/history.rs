@@ -0,0 +1,226 @@
+use alloc::vec::Vec;
+
+use crate::{
+	motion::navigation::clamp_to_line,
+	piece_table::{
+		PieceTable,
+		Snapshot,
+	},
+};
+
+#[derive(Debug)]
+struct State {
+	parent:   Option<usize>,
+	/// Child that redo moves to, the one most recently left by undo.
+	redo:     Option<usize>,
+	snapshot: Snapshot,
+	/// Where the change leading to this state started.
+	cursor:   usize,
+}
+
+/// Undo tree over the snapshots of a [`PieceTable`].
+///
+/// States are stored in creation order, so besides walking up and down the
+/// tree with [`History::undo`] and [`History::redo`], [`History::earlier`]
+/// and [`History::later`] move through time across branches like Vim's `g-`
+/// and `g+`.
+///
+/// Every method that moves through history returns the cursor position to
+/// restore, or `None` if there is nothing to move to.
+#[derive(Debug)]
+pub struct History {
+	states:  Vec<State>,
+	current: usize,
+}
+
+impl History {
+	pub fn new(buf: &PieceTable) -> Self {
+		Self {
+			states:  vec![State {
+				parent:   None,
+				redo:     None,
+				snapshot: buf.snapshot(),
+				cursor:   0,
+			}],
+			current: 0,
+		}
+	}
+
+	/// Records the buffer as a new state if it changed since the current one.
+	/// `cursor` is where the change started and is restored on undo.
+	pub fn commit(&mut self, buf: &PieceTable, cursor: usize) -> bool {
+		if buf.version() == self.states[self.current].snapshot.version() {
+			return false;
+		}
+
+		let idx = self.states.len();
+		self.states.push(State {
+			parent: Some(self.current),
+			redo: None,
+			snapshot: buf.snapshot(),
+			cursor,
+		});
+		self.states[self.current].redo = Some(idx);
+		self.current = idx;
+
+		true
+	}
+
+	pub fn undo(&mut self, buf: &mut PieceTable) -> Option<usize> {
+		let state = &self.states[self.current];
+		let parent = state.parent?;
+		let cursor = state.cursor;
+
+		self.states[parent].redo = Some(self.current);
+		self.current = parent;
+		buf.restore(&self.states[parent].snapshot);
+
+		Some(clamp_to_line(buf, cursor))
+	}
+
+	pub fn redo(&mut self, buf: &mut PieceTable) -> Option<usize> {
+		let child = self.states[self.current].redo?;
+
+		self.current = child;
+		buf.restore(&self.states[child].snapshot);
+
+		Some(clamp_to_line(buf, self.states[child].cursor))
+	}
+
+	/// Goes back `count` states in time (`g-`).
+	pub fn earlier(
+		&mut self,
+		buf: &mut PieceTable,
+		count: usize,
+	) -> Option<usize> {
+		let target = self.current.saturating_sub(count.max(1));
+		self.jump(buf, target)
+	}
+
+	/// Goes forward `count` states in time (`g+`).
+	pub fn later(
+		&mut self,
+		buf: &mut PieceTable,
+		count: usize,
+	) -> Option<usize> {
+		let target = (self.current + count.max(1)).min(self.states.len() - 1);
+		self.jump(buf, target)
+	}
+
+	fn jump(&mut self, buf: &mut PieceTable, target: usize) -> Option<usize> {
+		if target == self.current {
+			return None;
+		}
+
+		// the newest change involved marks where the buffer changed
+		let cursor = self.states[self.current.max(target)].cursor;
+
+		// let redo follow the branch the target is on
+		let mut child = target;
+		while let Some(parent) = self.states[child].parent {
+			self.states[parent].redo = Some(child);
+			child = parent;
+		}
+
+		self.current = target;
+		buf.restore(&self.states[target].snapshot);
+
+		Some(clamp_to_line(buf, cursor))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::TextBuffer;
+
+	fn text(buf: &PieceTable) -> String {
+		buf.slice(0, buf.len())
+	}
+
+	#[test]
+	fn undo_redo_and_branches() {
+		let mut buf = PieceTable::new("abc".into());
+		let mut history = History::new(&buf);
+
+		buf.insert(3, "d");
+		assert!(history.commit(&buf, 3));
+		assert!(!history.commit(&buf, 3));
+
+		buf.delete(0, 1);
+		history.commit(&buf, 0);
+		assert_eq!(text(&buf), "bcd");
+
+		assert_eq!(history.undo(&mut buf), Some(0));
+		assert_eq!(text(&buf), "abcd");
+
+		// a new change after undo starts a branch
+		buf.insert(0, "x");
+		history.commit(&buf, 0);
+		assert_eq!(text(&buf), "xabcd");
+
+		assert_eq!(history.undo(&mut buf), Some(0));
+		assert_eq!(history.undo(&mut buf), Some(2));
+		assert_eq!(text(&buf), "abc");
+		assert_eq!(history.undo(&mut buf), None);
+
+		history.redo(&mut buf);
+		history.redo(&mut buf);
+		assert_eq!(text(&buf), "xabcd");
+
+		history.earlier(&mut buf, 1);
+		assert_eq!(text(&buf), "bcd");
+		history.earlier(&mut buf, 1);
+		assert_eq!(text(&buf), "abcd");
+		history.later(&mut buf, 2);
+		assert_eq!(text(&buf), "xabcd");
+		assert_eq!(history.later(&mut buf, 1), None);
+	}
+
+	#[test]
+	fn jumps_switch_branches() {
+		let mut buf = PieceTable::new("a".into());
+		let mut history = History::new(&buf);
+
+		// three branches off the first state: "ab", "ac" and "ad"
+		for ch in ["b", "c", "d"] {
+			buf.insert(1, ch);
+			history.commit(&buf, 1);
+			history.undo(&mut buf);
+		}
+		history.redo(&mut buf);
+		assert_eq!(text(&buf), "ad");
+
+		// `g-` goes to the branch made before, and redo then follows it
+		assert_eq!(history.earlier(&mut buf, 1), Some(1));
+		assert_eq!(text(&buf), "ac");
+		history.undo(&mut buf);
+		assert_eq!(text(&buf), "a");
+		history.redo(&mut buf);
+		assert_eq!(text(&buf), "ac");
+
+		history.earlier(&mut buf, 1);
+		assert_eq!(text(&buf), "ab");
+		// the cursor is kept within the text that is left
+		assert_eq!(history.earlier(&mut buf, 5), Some(0));
+		assert_eq!(text(&buf), "a");
+		assert_eq!(history.earlier(&mut buf, 1), None);
+
+		assert_eq!(history.later(&mut buf, 10), Some(1));
+		assert_eq!(text(&buf), "ad");
+
+		// a change on an older branch starts one more, after all the others
+		history.earlier(&mut buf, 2);
+		buf.insert(2, "x");
+		history.commit(&buf, 2);
+		history.undo(&mut buf);
+		history.undo(&mut buf);
+		history.redo(&mut buf);
+		history.redo(&mut buf);
+		assert_eq!(text(&buf), "abx");
+		history.earlier(&mut buf, 1);
+		assert_eq!(text(&buf), "ad");
+		assert_eq!(history.later(&mut buf, 1), Some(2));
+		assert_eq!(history.later(&mut buf, 1), None);
+	}
+}
@@ -3,9 +3,19 @@
 use piece_table::RowCol;
 pub extern crate alloc;
 
+pub mod edit;
+pub mod grapheme;
+pub mod history;
+pub mod line_range;
 pub mod logging;
+pub mod mark;
 pub mod motion;
 pub mod piece_table;
+pub mod regex;
+pub mod register;
+pub mod selection;
+
+pub use mark::MarkTable;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Cursor {
@@ -18,38 +28,60 @@ impl Cursor {
 	}
 }
 
+/// A text buffer indexed by byte offsets.
+///
+/// Offsets passed to and returned from a buffer are byte offsets into its
+/// UTF-8 content. Only [`RowCol`] counts differently: its `col` is the number
+/// of grapheme clusters from the start of the line, so it matches what the
+/// user sees and survives edits to multi-byte characters earlier on the
+/// line.
+/// [`TextBuffer::rowcol_at`] and [`TextBuffer::pos_from`] convert between
+/// the two.
 pub trait TextBuffer {
+	/// Length in bytes.
 	fn len(&self) -> usize;
 	fn is_empty(&self) -> bool;
+	/// The char starting at byte `idx`, or `None` if `idx` is out of bounds or
+	/// inside a multi-byte char.
 	fn char_at(&self, idx: usize) -> Option<char>;
 	fn slice(&self, start: usize, end: usize) -> String;
 
+	/// Inserts `text` at `idx`, rounded down to a char boundary.
 	fn insert(&mut self, idx: usize, text: &str);
+	/// Deletes `start..end`, widened to the chars both ends fall into.
 	fn delete(&mut self, start: usize, end: usize);
+	/// Offset of the first occurrence of `pattern` at or after `from`.
 	fn find(&self, pattern: &str, from: usize) -> Option<usize>;
+	/// The contiguous run of text starting at `idx`, which must be a char
+	/// boundary. Runs end wherever the underlying storage does, so reading
+	/// the buffer piece by piece needs no copies. Empty at the end.
+	fn chunk_at(&self, idx: usize) -> &str;
 
 	fn rowcol_at(&self, idx: usize) -> RowCol;
+	/// Byte offset of `rc`. Columns past the end of the line are clamped to
+	/// its line break.
 	fn pos_from(&self, rc: RowCol) -> usize;
 	fn lines(&self) -> usize;
+	/// Length of `row` in grapheme clusters, including its line break.
 	fn line_len(&self, row: usize) -> usize;
-}
 
-#[derive(Default, Clone, Debug)]
-pub struct MarkTable {
-	slots: [Option<RowCol>; 26],
-}
+	fn is_char_boundary(&self, idx: usize) -> bool {
+		idx == self.len() || self.char_at(idx).is_some()
+	}
 
-impl MarkTable {
-	pub fn set(&mut self, ch: char, pos: RowCol) {
-		if ch.is_ascii_lowercase() {
-			self.slots[(ch as u8 - b'a') as usize] = Some(pos);
+	fn floor_char_boundary(&self, idx: usize) -> usize {
+		let mut idx = idx.min(self.len());
+		while !self.is_char_boundary(idx) {
+			idx -= 1;
 		}
+		idx
 	}
-	pub fn get(&self, ch: char) -> Option<RowCol> {
-		if ch.is_ascii_lowercase() {
-			self.slots[(ch as u8 - b'a') as usize]
-		} else {
-			None
+
+	fn ceil_char_boundary(&self, idx: usize) -> usize {
+		let mut idx = idx.min(self.len());
+		while !self.is_char_boundary(idx) {
+			idx += 1;
 		}
+		idx
 	}
 }
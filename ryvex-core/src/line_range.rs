@@ -0,0 +1,303 @@
+//! Line ranges in front of command line commands, like the `%` of `:%!sort`
+//! or the `'<,'>` of `:'<,'>!jq .`.
+//!
+//! An address is a line number, `.` for the cursor line, `$` for the last
+//! line or `'x` for the line of mark `x`, followed by any number of `+N` and
+//! `-N` offsets, where a bare `+` or `-` counts one. An offset without an
+//! address in front of it counts from the cursor line. Two addresses
+//! separated by `,` span the lines from one to the other, and `%` spans every
+//! line.
+
+use alloc::string::String;
+use core::fmt::{
+	self,
+	Display,
+};
+
+use ryvex_target::std::error::Error;
+
+use crate::{
+	motion::{
+		navigation::{
+			first_non_blank,
+			last_line,
+			prev_char,
+		},
+		operator::TextRange,
+	},
+	Cursor,
+	MarkTable,
+	TextBuffer,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineRangeError {
+	/// The mark is not set.
+	UnsetMark(char),
+	/// An address points before the first or past the last line.
+	OutOfBounds,
+}
+
+impl Error for LineRangeError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		None
+	}
+}
+
+impl Display for LineRangeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LineRangeError::UnsetMark(mark) => {
+				write!(f, "mark '{mark}' not set")
+			}
+			LineRangeError::OutOfBounds => f.write_str("line out of range"),
+		}
+	}
+}
+
+pub type Result<T> = core::result::Result<T, LineRangeError>;
+
+/// The rows `first..=last`, zero based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+	pub first: usize,
+	pub last:  usize,
+}
+
+impl LineRange {
+	/// The rows `range` touches. Its end is exclusive, so a linewise range
+	/// ending at the start of a row leaves that row out.
+	pub fn covering<B: TextBuffer>(buf: &B, range: TextRange) -> Self {
+		let end = if range.end > range.start {
+			prev_char(buf, range.end)
+		} else {
+			range.end
+		};
+
+		Self {
+			first: buf.rowcol_at(range.start).row,
+			last:  buf.rowcol_at(end).row,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.last - self.first + 1
+	}
+
+	/// A range always holds at least one line.
+	pub fn is_empty(&self) -> bool {
+		false
+	}
+
+	/// The text of the lines, each ending in a line break.
+	pub fn text<B: TextBuffer>(&self, buf: &B) -> String {
+		let range = TextRange::linewise(buf, self.first, self.last);
+		let mut text = buf.slice(range.start, range.end);
+		if !text.is_empty() && !text.ends_with('\n') {
+			text.push('\n');
+		}
+		text
+	}
+
+	/// Replaces the lines with `text` and puts the cursor on the first of
+	/// them. The last line of the buffer keeps going without a line break if
+	/// it had none, and if `text` is empty the lines go away without leaving
+	/// an empty one behind.
+	pub fn replace<B: TextBuffer>(
+		&self,
+		buf: &mut B,
+		cursor: &mut Cursor,
+		text: &str,
+	) {
+		let range = TextRange::linewise(buf, self.first, self.last);
+		let terminated = range.end < buf.len() ||
+			buf.char_at(prev_char(buf, range.end)) == Some('\n');
+
+		// like deleting them, which takes the line break before them along
+		let mut start = range.start;
+		if text.is_empty() && !terminated && start > 0 {
+			start = prev_char(buf, start);
+		}
+
+		let mut text = String::from(text);
+		if !terminated {
+			if text.ends_with('\n') {
+				text.pop();
+			}
+		} else if !text.is_empty() && !text.ends_with('\n') {
+			text.push('\n');
+		}
+
+		if start < range.end {
+			buf.delete(start, range.end);
+		}
+		buf.insert(start, &text);
+
+		cursor.pos = first_non_blank(buf, self.first.min(last_line(buf)));
+	}
+}
+
+/// Parses the range at the start of `input`, relative to the cursor on
+/// `row`. Returns the range, if there is one, and the rest of `input`.
+pub fn parse<'a, B: TextBuffer>(
+	input: &'a str,
+	buf: &B,
+	row: usize,
+	marks: &MarkTable,
+) -> Result<(Option<LineRange>, &'a str)> {
+	let last = last_line(buf);
+
+	if let Some(rest) = input.strip_prefix('%') {
+		let range = LineRange { first: 0, last };
+		return Ok((Some(range), rest));
+	}
+
+	let (first, rest) = address(input, buf, row, marks)?;
+	let Some(first) = first else {
+		return Ok((None, input));
+	};
+
+	let (second, rest) = match rest.strip_prefix(',') {
+		Some(rest) => {
+			let (second, rest) = address(rest, buf, row, marks)?;
+			// `1,` ends at the cursor line like Vim's
+			(second.unwrap_or(row as i64), rest)
+		}
+		None => (first, rest),
+	};
+
+	let in_bounds = |line: i64| {
+		usize::try_from(line)
+			.ok()
+			.filter(|&line| line <= last)
+			.ok_or(LineRangeError::OutOfBounds)
+	};
+	let (first, second) = (in_bounds(first)?, in_bounds(second)?);
+
+	// a backwards range is turned around instead of asking, unlike Vim
+	let range = LineRange {
+		first: first.min(second),
+		last:  first.max(second),
+	};
+	Ok((Some(range), rest))
+}
+
+/// Parses one address, as a zero based row that may still be out of bounds.
+fn address<'a, B: TextBuffer>(
+	input: &'a str,
+	buf: &B,
+	row: usize,
+	marks: &MarkTable,
+) -> Result<(Option<i64>, &'a str)> {
+	let mut chars = input.chars();
+	let (base, mut rest) = match chars.next() {
+		Some('.') => (Some(row as i64), chars.as_str()),
+		Some('$') => (Some(last_line(buf) as i64), chars.as_str()),
+		Some('\'') => {
+			let mark = chars.next().unwrap_or('\'');
+			let pos = marks.get(mark).ok_or(LineRangeError::UnsetMark(mark))?;
+			let line = buf.rowcol_at(pos.min(buf.len())).row;
+			(Some(line as i64), chars.as_str())
+		}
+		Some(ch) if ch.is_ascii_digit() => {
+			let (number, rest) = number(input);
+			// line numbers count from one, `0` is taken as the first line
+			(Some(number.max(1) - 1), rest)
+		}
+		_ => (None, input),
+	};
+
+	let mut line = base;
+	loop {
+		let sign = match rest.chars().next() {
+			Some('+') => 1,
+			Some('-') => -1,
+			_ => break,
+		};
+		let (offset, after) = match number(&rest[1..]) {
+			(_, after) if after.len() == rest.len() - 1 => (1, after),
+			found => found,
+		};
+
+		line = Some(line.unwrap_or(row as i64) + sign * offset);
+		rest = after;
+	}
+
+	Ok((line, rest))
+}
+
+/// The decimal number at the start of `input`, or 0 if there is none, and
+/// what follows it.
+fn number(input: &str) -> (i64, &str) {
+	let len = input
+		.find(|ch: char| !ch.is_ascii_digit())
+		.unwrap_or(input.len());
+	let number = input[..len].bytes().fold(0i64, |n, digit| {
+		n.saturating_mul(10).saturating_add((digit - b'0') as i64)
+	});
+
+	(number, &input[len..])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	fn range(input: &str) -> Result<(Option<LineRange>, &str)> {
+		let buf = PieceTable::new("a\nb\nc\nd\ne\n".into());
+		let mut marks = MarkTable::default();
+		marks.set('<', 2);
+		marks.set('>', 6);
+
+		parse(input, &buf, 1, &marks)
+	}
+
+	#[test]
+	fn addresses() {
+		let lines = |first, last| Some(LineRange { first, last });
+
+		assert_eq!(range("%!sort"), Ok((lines(0, 4), "!sort")));
+		assert_eq!(range("!ls"), Ok((None, "!ls")));
+		assert_eq!(range(".!cmd"), Ok((lines(1, 1), "!cmd")));
+		assert_eq!(range(".,.+2!"), Ok((lines(1, 3), "!")));
+		assert_eq!(range("'<,'>!"), Ok((lines(1, 3), "!")));
+		assert_eq!(range("2,$"), Ok((lines(1, 4), "")));
+		assert_eq!(range("$-1,1"), Ok((lines(0, 3), "")));
+		assert_eq!(range("+,++"), Ok((lines(2, 3), "")));
+		assert_eq!(range("set tw=72"), Ok((None, "set tw=72")));
+
+		assert_eq!(range("7!"), Err(LineRangeError::OutOfBounds));
+		assert_eq!(range("-2!"), Err(LineRangeError::OutOfBounds));
+		assert_eq!(range("'a!"), Err(LineRangeError::UnsetMark('a')));
+	}
+
+	#[test]
+	fn replace_lines() {
+		let mut buf = PieceTable::new("b\na\n  c\nlast".into());
+		let mut cursor = Cursor { pos: 0 };
+
+		let sorted = LineRange { first: 0, last: 1 };
+		assert_eq!(sorted.text(&buf), "b\na\n");
+		sorted.replace(&mut buf, &mut cursor, "a\nb");
+		assert_eq!(buf.slice(0, buf.len()), "a\nb\n  c\nlast");
+		assert_eq!(cursor.pos, 0);
+
+		// the last line stays without a line break
+		let tail = LineRange { first: 2, last: 3 };
+		assert_eq!(tail.text(&buf), "  c\nlast\n");
+		tail.replace(&mut buf, &mut cursor, "  C\nLAST\n");
+		assert_eq!(buf.slice(0, buf.len()), "a\nb\n  C\nLAST");
+		assert_eq!(cursor.pos, 6);
+
+		LineRange { first: 0, last: 1 }.replace(&mut buf, &mut cursor, "");
+		assert_eq!(buf.slice(0, buf.len()), "  C\nLAST");
+
+		// no output removes the last lines along with the break before them
+		LineRange { first: 1, last: 1 }.replace(&mut buf, &mut cursor, "");
+		assert_eq!(buf.slice(0, buf.len()), "  C");
+		assert_eq!(cursor.pos, 2);
+		LineRange { first: 0, last: 0 }.replace(&mut buf, &mut cursor, "");
+		assert_eq!(buf.slice(0, buf.len()), "");
+	}
+}
@@ -0,0 +1,147 @@
+use crate::{
+	edit::Edit,
+	motion::navigation::first_non_blank,
+	TextBuffer,
+};
+
+/// Special marks, set by the editor rather than with `m`:
+///
+/// - `'` (or `` ` ``) where the cursor was before the latest jump
+/// - `.` where the last change was made
+/// - `^` where insert mode was left
+/// - `<` and `>` the bounds of the last visual selection
+const SPECIAL: [char; 5] = ['\'', '.', '^', '<', '>'];
+
+/// The marks of a buffer.
+///
+/// Marks are byte offsets that follow the edits passed to
+/// [`MarkTable::map`], so a mark stays on its text when lines above it are
+/// inserted or deleted. Lowercase marks are local to the buffer. Uppercase
+/// marks are file-global: the editor keeps track of which buffer holds each
+/// one, while its position is tracked here like any other mark.
+#[derive(Default, Clone, Debug)]
+pub struct MarkTable {
+	local:   [Option<usize>; 26],
+	global:  [Option<usize>; 26],
+	special: [Option<usize>; SPECIAL.len()],
+}
+
+impl MarkTable {
+	pub fn is_valid(ch: char) -> bool {
+		ch.is_ascii_alphabetic() || ch == '`' || SPECIAL.contains(&ch)
+	}
+
+	fn slot(&mut self, ch: char) -> Option<&mut Option<usize>> {
+		match ch {
+			'a'..='z' => Some(&mut self.local[(ch as u8 - b'a') as usize]),
+			'A'..='Z' => Some(&mut self.global[(ch as u8 - b'A') as usize]),
+			'`' => Some(&mut self.special[0]),
+			ch => {
+				let idx = SPECIAL.iter().position(|&s| s == ch)?;
+				Some(&mut self.special[idx])
+			}
+		}
+	}
+
+	/// Sets mark `ch` to `pos`. Returns `false` if `ch` is not a mark.
+	pub fn set(&mut self, ch: char, pos: usize) -> bool {
+		match self.slot(ch) {
+			Some(slot) => {
+				*slot = Some(pos);
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn get(&self, ch: char) -> Option<usize> {
+		match ch {
+			'a'..='z' => self.local[(ch as u8 - b'a') as usize],
+			'A'..='Z' => self.global[(ch as u8 - b'A') as usize],
+			'`' => self.special[0],
+			ch => {
+				let idx = SPECIAL.iter().position(|&s| s == ch)?;
+				self.special[idx]
+			}
+		}
+	}
+
+	pub fn remove(&mut self, ch: char) {
+		if let Some(slot) = self.slot(ch) {
+			*slot = None;
+		}
+	}
+
+	fn all_mut(&mut self) -> impl Iterator<Item = &mut usize> {
+		self.local
+			.iter_mut()
+			.chain(&mut self.global)
+			.chain(&mut self.special)
+			.flatten()
+	}
+
+	/// Moves every mark along with `edit`.
+	pub fn map(&mut self, edit: &Edit) {
+		for pos in self.all_mut() {
+			*pos = edit.map(*pos);
+		}
+	}
+
+	/// Pulls marks past the end of `buf` back inside, e.g. after undo
+	/// restored a shorter text.
+	pub fn clamp<B: TextBuffer>(&mut self, buf: &B) {
+		for pos in self.all_mut() {
+			*pos = buf.floor_char_boundary(*pos);
+		}
+	}
+
+	/// Where jumping to mark `ch` goes: the mark itself, or the first
+	/// non-blank of its line for `linewise` jumps like `'a`.
+	pub fn jump_target<B: TextBuffer>(
+		&self,
+		buf: &B,
+		ch: char,
+		linewise: bool,
+	) -> Option<usize> {
+		let pos = buf.floor_char_boundary(self.get(ch)?);
+		if linewise {
+			Some(first_non_blank(buf, buf.rowcol_at(pos).row))
+		} else {
+			Some(pos)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		edit::Recorder,
+		piece_table::PieceTable,
+	};
+
+	#[test]
+	fn marks_follow_edits() {
+		let mut buf = PieceTable::new("one\ntwo\nthree".into());
+		let mut marks = MarkTable::default();
+
+		marks.set('a', 4);
+		marks.set('B', 8);
+		marks.set('`', 0);
+		assert!(!marks.set('!', 0));
+		assert_eq!(marks.get('\''), Some(0));
+
+		let mut rec = Recorder::new(&mut buf);
+		rec.insert(0, "zero\n");
+		rec.delete(9, 13);
+		for edit in rec.into_edits() {
+			marks.map(&edit);
+		}
+
+		assert_eq!(buf.slice(0, buf.len()), "zero\none\nthree");
+		assert_eq!(marks.get('a'), Some(9));
+		assert_eq!(marks.get('B'), Some(9));
+		assert_eq!(marks.get('\''), Some(5));
+		assert_eq!(marks.jump_target(&buf, 'B', true), Some(9));
+	}
+}
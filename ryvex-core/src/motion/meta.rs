@@ -0,0 +1,285 @@
+//! The `g` operators that rewrite text in place: case changes, rot13 and
+//! `gq` reflow.
+
+use alloc::{
+	string::String,
+	vec::Vec,
+};
+
+use crate::{
+	Cursor,
+	TextBuffer,
+};
+
+use super::{
+	navigation::{
+		first_non_blank,
+		line_end,
+		line_start,
+	},
+	operator::TextRange,
+	MetaOperator,
+	Options,
+};
+
+/// Comment leaders kept at the start of every line `gq` produces, longest
+/// first so `///` is not taken for `//`.
+const COMMENT_LEADERS: [&str; 7] = ["//!", "///", "//", "#", "--", ";", ">"];
+
+pub(super) const TAB_STOP: usize = 8;
+
+/// Applies `op` to `range` and leaves the cursor where Vim does: at the start
+/// of the range, or on the last formatted line for `gq`.
+pub fn apply<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	op: MetaOperator,
+	range: TextRange,
+	options: &Options,
+) {
+	match op {
+		MetaOperator::Format => format(buf, cursor, range, options.text_width),
+		MetaOperator::Uppercase => map_chars(buf, cursor, range, |ch, out| {
+			out.extend(ch.to_uppercase())
+		}),
+		MetaOperator::Lowercase => map_chars(buf, cursor, range, |ch, out| {
+			out.extend(ch.to_lowercase())
+		}),
+		MetaOperator::ToggleCase => map_chars(buf, cursor, range, |ch, out| {
+			if ch.is_uppercase() {
+				out.extend(ch.to_lowercase());
+			} else {
+				out.extend(ch.to_uppercase());
+			}
+		}),
+		MetaOperator::Rot13 => {
+			map_chars(buf, cursor, range, |ch, out| out.push(rot13(ch)))
+		}
+	}
+}
+
+fn rot13(ch: char) -> char {
+	let base = match ch {
+		'a'..='z' => b'a',
+		'A'..='Z' => b'A',
+		_ => return ch,
+	};
+	char::from((ch as u8 - base + 13) % 26 + base)
+}
+
+/// Replaces `start..end` with `text` unless nothing would change, so no-op
+/// operators leave the buffer version, and with it the undo history, alone.
+fn replace<B: TextBuffer>(buf: &mut B, start: usize, end: usize, text: &str) {
+	if buf.slice(start, end) == text {
+		return;
+	}
+
+	if start < end {
+		buf.delete(start, end);
+	}
+	buf.insert(start, text);
+}
+
+fn map_chars<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	range: TextRange,
+	f: impl Fn(char, &mut String),
+) {
+	let old = buf.slice(range.start, range.end);
+	let mut new = String::with_capacity(old.len());
+	for ch in old.chars() {
+		f(ch, &mut new);
+	}
+
+	replace(buf, range.start, range.end, &new);
+
+	let first_row = buf.rowcol_at(range.start).row;
+	// `gUU` and friends leave the cursor on its line
+	cursor.pos = if range.is_linewise() && cursor.pos >= range.start {
+		cursor.pos.min(line_end(buf, first_row))
+	} else {
+		range.start
+	};
+	cursor.pos = buf.floor_char_boundary(cursor.pos);
+}
+
+/// Display width of `s`, with tabs advancing to the next tab stop.
+pub(super) fn width(s: &str) -> usize {
+	s.chars().fold(0, |w, ch| match ch {
+		'\t' => (w / TAB_STOP + 1) * TAB_STOP,
+		_ => w + 1,
+	})
+}
+
+/// A line split into the prefix `gq` keeps and the text it reflows.
+struct Line<'a> {
+	prefix: &'a str,
+	leader: &'a str,
+	text:   &'a str,
+}
+
+impl<'a> Line<'a> {
+	fn parse(line: &'a str) -> Self {
+		let indent = line.len() - line.trim_start().len();
+		let rest = &line[indent..];
+
+		let leader = COMMENT_LEADERS
+			.into_iter()
+			.find(|leader| rest.starts_with(leader))
+			.unwrap_or("");
+		let text = rest[leader.len()..].trim_start();
+
+		// blank lines drop the whitespace trailing their leader
+		let prefix = if text.is_empty() {
+			&line[..indent + leader.len()]
+		} else {
+			&line[..line.len() - text.len()]
+		};
+
+		Self {
+			prefix,
+			leader,
+			text,
+		}
+	}
+
+	fn is_blank(&self) -> bool {
+		self.text.is_empty()
+	}
+}
+
+/// Reflows the lines touched by `range` so no line is wider than
+/// `text_width`, unless a single word already is. Blank lines and changes of
+/// comment leader separate paragraphs. The first line of a paragraph keeps
+/// its own indentation and leader, and the following lines take those of the
+/// second line, which allows hanging indents.
+fn format<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	range: TextRange,
+	text_width: usize,
+) {
+	let first_row = buf.rowcol_at(range.start).row;
+	let last_row = buf
+		.rowcol_at(range.end.saturating_sub(1).max(range.start))
+		.row;
+	let start = line_start(buf, first_row);
+	let end = line_end(buf, last_row);
+
+	let old = buf.slice(start, end);
+	let lines: Vec<Line> = old.split('\n').map(Line::parse).collect();
+
+	let mut out: Vec<String> = Vec::new();
+	let mut i = 0;
+	while i < lines.len() {
+		if lines[i].is_blank() {
+			out.push(String::from(lines[i].prefix));
+			i += 1;
+			continue;
+		}
+
+		let leader = lines[i].leader;
+		let len = lines[i..]
+			.iter()
+			.take_while(|l| !l.is_blank() && l.leader == leader)
+			.count();
+		let paragraph = &lines[i..i + len];
+
+		let first = paragraph[0].prefix;
+		let rest = paragraph.get(1).map_or(first, |l| l.prefix);
+		let words = paragraph.iter().flat_map(|l| l.text.split_whitespace());
+		fill(words, first, rest, text_width, &mut out);
+
+		i += len;
+	}
+
+	let new = out.join("\n");
+	replace(buf, start, end, &new);
+
+	let last = first_row + out.len().saturating_sub(1);
+	cursor.pos = first_non_blank(buf, last);
+}
+
+/// Greedily packs `words` into lines of at most `text_width` columns.
+fn fill<'a>(
+	words: impl Iterator<Item = &'a str>,
+	first: &str,
+	rest: &str,
+	text_width: usize,
+	out: &mut Vec<String>,
+) {
+	let mut line = String::from(first);
+	let mut empty = true;
+
+	for word in words {
+		if !empty && width(&line) + 1 + width(word) > text_width {
+			out.push(core::mem::replace(&mut line, String::from(rest)));
+			empty = true;
+		}
+
+		if !empty {
+			line.push(' ');
+		}
+		line.push_str(word);
+		empty = false;
+	}
+	out.push(line);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	fn run(text: &str, op: MetaOperator, range: TextRange) -> String {
+		let mut buf = PieceTable::new(text.into());
+		let mut cursor = Cursor { pos: 0 };
+		let options = Options {
+			text_width: 20,
+			..Options::default()
+		};
+
+		apply(&mut buf, &mut cursor, op, range, &options);
+		buf.slice(0, buf.len())
+	}
+
+	#[test]
+	fn case_and_rot13() {
+		let word = TextRange::charwise(0, 5);
+
+		assert_eq!(
+			run("hello world", MetaOperator::Uppercase, word),
+			"HELLO world"
+		);
+		assert_eq!(run("HeLLo", MetaOperator::ToggleCase, word), "hEllO");
+		assert_eq!(run("Hello", MetaOperator::Rot13, word), "Uryyb");
+		assert_eq!(
+			run("straße", MetaOperator::Uppercase, TextRange::charwise(0, 7)),
+			"STRASSE"
+		);
+	}
+
+	#[test]
+	fn reflow_keeps_indent_and_leaders() {
+		let text = "  // one two three four five six\n  // seven\n\n# a b\n";
+		let buf = PieceTable::new(text.into());
+		let all = TextRange::linewise(&buf, 0, 3);
+
+		assert_eq!(
+			run(text, MetaOperator::Format, all),
+			"  // one two three\n  // four five six\n  // seven\n\n# a b\n"
+		);
+
+		let hanging = "- first item that wraps\n  around\n";
+		let buf = PieceTable::new(hanging.into());
+		assert_eq!(
+			run(
+				hanging,
+				MetaOperator::Format,
+				TextRange::linewise(&buf, 0, 1)
+			),
+			"- first item that\n  wraps around\n"
+		);
+	}
+}
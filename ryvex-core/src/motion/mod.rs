@@ -1,6 +1,17 @@
 use alloc::borrow::Cow;
 
-use crate::piece_table::PieceTable;
+use crate::{
+	register::Register,
+	Cursor,
+	MarkTable,
+	TextBuffer,
+};
+
+pub mod meta;
+pub mod navigation;
+pub mod operator;
+pub mod shift;
+pub mod text_object;
 
 pub trait AsKey {
 	fn as_key(&self) -> Cow<'_, str>;
@@ -35,6 +46,11 @@ pub enum MotionType {
 	Delete,
 	Yank,
 	Change,
+	ShiftRight,
+	ShiftLeft,
+	/// Pipes the lines through a shell command, which the command line asks
+	/// for.
+	Filter,
 	Meta(MetaOperator),
 }
 
@@ -45,7 +61,10 @@ impl AsKey for MotionType {
 			MotionType::Delete => Cow::Borrowed("d"),
 			MotionType::Yank => Cow::Borrowed("y"),
 			MotionType::Change => Cow::Borrowed("c"),
-			MotionType::Meta(op) => Cow::Owned(format!("q{}", op.as_key())),
+			MotionType::ShiftRight => Cow::Borrowed(">"),
+			MotionType::ShiftLeft => Cow::Borrowed("<"),
+			MotionType::Filter => Cow::Borrowed("!"),
+			MotionType::Meta(op) => Cow::Owned(format!("g{}", op.as_key())),
 		}
 	}
 }
@@ -59,6 +78,16 @@ pub enum MetaOperator {
 	ToggleCase,
 }
 
+impl MetaOperator {
+	pub const ALL: [MetaOperator; 5] = [
+		MetaOperator::Uppercase,
+		MetaOperator::Lowercase,
+		MetaOperator::Format,
+		MetaOperator::Rot13,
+		MetaOperator::ToggleCase,
+	];
+}
+
 impl AsKey for MetaOperator {
 	fn as_key(&self) -> Cow<'_, str> {
 		let s = match self {
@@ -86,6 +115,11 @@ pub enum NavigationMotion {
 	EndOfWordForward,
 	EndOfWordBackward,
 
+	BigWordForward,
+	BigWordBackward,
+	BigEndOfWordForward,
+	BigEndOfWordBackward,
+
 	LineEnd,
 	LineStart,
 
@@ -96,6 +130,41 @@ pub enum NavigationMotion {
 	Top,
 }
 
+impl NavigationMotion {
+	pub const ALL: [NavigationMotion; 18] = [
+		NavigationMotion::CharForward,
+		NavigationMotion::CharBackward,
+		NavigationMotion::LineForward,
+		NavigationMotion::LineBackward,
+		NavigationMotion::WordForward,
+		NavigationMotion::WordBackward,
+		NavigationMotion::EndOfWordForward,
+		NavigationMotion::EndOfWordBackward,
+		NavigationMotion::BigWordForward,
+		NavigationMotion::BigWordBackward,
+		NavigationMotion::BigEndOfWordForward,
+		NavigationMotion::BigEndOfWordBackward,
+		NavigationMotion::LineEnd,
+		NavigationMotion::LineStart,
+		NavigationMotion::EmptyLineAbove,
+		NavigationMotion::EmptyLineBelow,
+		NavigationMotion::Bottom,
+		NavigationMotion::Top,
+	];
+
+	/// Whether the motion counts as a jump, which remembers where it started
+	/// in the `'` mark.
+	pub fn is_jump(&self) -> bool {
+		matches!(
+			self,
+			NavigationMotion::Top |
+				NavigationMotion::Bottom |
+				NavigationMotion::EmptyLineAbove |
+				NavigationMotion::EmptyLineBelow
+		)
+	}
+}
+
 impl AsKey for NavigationMotion {
 	fn as_key(&self) -> Cow<'_, str> {
 		let s = match self {
@@ -109,6 +178,11 @@ impl AsKey for NavigationMotion {
 			NavigationMotion::EndOfWordForward => "e",
 			NavigationMotion::EndOfWordBackward => "ge",
 
+			NavigationMotion::BigWordForward => "W",
+			NavigationMotion::BigWordBackward => "B",
+			NavigationMotion::BigEndOfWordForward => "E",
+			NavigationMotion::BigEndOfWordBackward => "gE",
+
 			NavigationMotion::LineEnd => "$",
 			NavigationMotion::LineStart => "0",
 
@@ -136,7 +210,10 @@ pub enum Range {
 	Line,
 	ForwardSearch(String),
 	BackwardSearch(String),
+	/// `` `x ``: charwise up to the mark.
 	Mark(char),
+	/// `'x`: every line between the cursor and the mark.
+	MarkLine(char),
 
 	Percent(Scope),
 	GoToLine(GoToLineNumber),
@@ -157,7 +234,8 @@ impl AsKey for Range {
 			Range::Line => Cow::Borrowed("l"),
 			Range::ForwardSearch(s) => Cow::Owned(format!("/{}", s)),
 			Range::BackwardSearch(s) => Cow::Owned(format!("?{}", s)),
-			Range::Mark(c) => Cow::Owned(format!("m{}", c)),
+			Range::Mark(c) => Cow::Owned(format!("`{}", c)),
+			Range::MarkLine(c) => Cow::Owned(format!("'{}", c)),
 			Range::Percent(scope) => Cow::Owned(format!("%{}", scope.as_key())),
 			Range::GoToLine(num) => num.as_key(),
 			Range::SentenceEnd => Cow::Borrowed(")"),
@@ -214,6 +292,84 @@ impl AsKey for Scope {
 	}
 }
 
-pub fn apply(_buffer: &mut PieceTable, _motion: Motion) -> Option<String> {
-	None
+impl Scope {
+	pub const ALL: [Scope; 9] = [
+		Scope::Parentheses,
+		Scope::Brackets,
+		Scope::Braces,
+		Scope::AngleBrackets,
+		Scope::SingleQuote,
+		Scope::DoubleQuote,
+		Scope::Backtick,
+		Scope::Word,
+		Scope::Paragraph,
+	];
+
+	/// Opening and closing delimiter of the scope. Quotes open and close with
+	/// the same character; words and paragraphs have no delimiters.
+	pub fn delimiters(&self) -> Option<(char, char)> {
+		match self {
+			Scope::Parentheses => Some(('(', ')')),
+			Scope::Brackets => Some(('[', ']')),
+			Scope::Braces => Some(('{', '}')),
+			Scope::AngleBrackets => Some(('<', '>')),
+			Scope::SingleQuote => Some(('\'', '\'')),
+			Scope::DoubleQuote => Some(('"', '"')),
+			Scope::Backtick => Some(('`', '`')),
+			Scope::Word | Scope::Paragraph => None,
+		}
+	}
+}
+
+impl Motion {
+	pub fn motion_type(&self) -> Option<MotionType> {
+		match self {
+			Motion::NavigationOnly { .. } => None,
+			Motion::OperatedNavigation { motion_type, .. } |
+			Motion::OperatedRange { motion_type, .. } => Some(*motion_type),
+		}
+	}
+}
+
+/// Settings that change how operators behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+	/// Maximum line width `gq` formats to.
+	pub text_width:  usize,
+	/// Columns `>` and `<` shift lines by.
+	pub shift_width: usize,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			text_width:  79,
+			shift_width: 8,
+		}
+	}
+}
+
+/// Applies `motion` to `buffer`, moving `cursor` accordingly.
+///
+/// Returns the text removed or yanked by an operator, if any, along with
+/// whether it spans whole lines.
+pub fn apply<B: TextBuffer>(
+	buffer: &mut B,
+	cursor: &mut Cursor,
+	motion: Motion,
+	options: &Options,
+	marks: &MarkTable,
+) -> Option<Register> {
+	match motion {
+		Motion::NavigationOnly { nav, count } => {
+			cursor.pos = navigation::navigate(buffer, cursor.pos, nav, count);
+			None
+		}
+		Motion::OperatedNavigation { motion_type, .. } |
+		Motion::OperatedRange { motion_type, .. } => {
+			let range = operator::resolve(buffer, cursor.pos, &motion, marks)?;
+			operator::execute(buffer, cursor, motion_type, range, options)
+				.map(|text| Register::new(text, range.kind))
+		}
+	}
 }
@@ -0,0 +1,529 @@
+use crate::{
+	grapheme,
+	piece_table::RowCol,
+	TextBuffer,
+};
+
+use super::NavigationMotion;
+
+/// Character classes used to find word boundaries.
+///
+/// A `word` is a run of [`CharClass::Word`] or a run of
+/// [`CharClass::Punctuation`], a `WORD` is any run of non-blank characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharClass {
+	Blank,
+	Punctuation,
+	Word,
+}
+
+pub(crate) fn char_class(ch: char, big: bool) -> CharClass {
+	if ch.is_whitespace() {
+		CharClass::Blank
+	} else if big || ch.is_alphanumeric() || ch == '_' {
+		CharClass::Word
+	} else {
+		CharClass::Punctuation
+	}
+}
+
+/// Computes the position the cursor ends up at after moving `count` times
+/// by `nav`, starting at byte offset `pos`.
+///
+/// The result is always a valid normal mode cursor position, meaning it never
+/// rests on a line break unless the line is empty.
+pub fn navigate<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	nav: NavigationMotion,
+	count: u32,
+) -> usize {
+	let count = count.max(1) as usize;
+	let pos = pos.min(buf.len());
+	let row = buf.rowcol_at(pos).row;
+
+	match nav {
+		NavigationMotion::CharForward => {
+			let last = last_char(buf, row);
+			(0..count).fold(pos, |p, _| next_char(buf, p).min(last))
+		}
+		NavigationMotion::CharBackward => {
+			let first = line_start(buf, row);
+			(0..count).fold(pos, |p, _| prev_char(buf, p).max(first))
+		}
+		NavigationMotion::LineForward => {
+			let target = (row + count).min(last_line(buf));
+			same_column(buf, pos, target)
+		}
+		NavigationMotion::LineBackward => {
+			let target = row.saturating_sub(count);
+			same_column(buf, pos, target)
+		}
+
+		NavigationMotion::WordForward => {
+			repeat(pos, count, |p| word_forward(buf, p, false))
+		}
+		NavigationMotion::BigWordForward => {
+			repeat(pos, count, |p| word_forward(buf, p, true))
+		}
+		NavigationMotion::WordBackward => {
+			repeat(pos, count, |p| word_backward(buf, p, false))
+		}
+		NavigationMotion::BigWordBackward => {
+			repeat(pos, count, |p| word_backward(buf, p, true))
+		}
+		NavigationMotion::EndOfWordForward => {
+			repeat(pos, count, |p| end_of_word_forward(buf, p, false))
+		}
+		NavigationMotion::BigEndOfWordForward => {
+			repeat(pos, count, |p| end_of_word_forward(buf, p, true))
+		}
+		NavigationMotion::EndOfWordBackward => {
+			repeat(pos, count, |p| end_of_word_backward(buf, p, false))
+		}
+		NavigationMotion::BigEndOfWordBackward => {
+			repeat(pos, count, |p| end_of_word_backward(buf, p, true))
+		}
+
+		NavigationMotion::LineStart => line_start(buf, row),
+		NavigationMotion::LineEnd => {
+			let target = (row + count - 1).min(last_line(buf));
+			last_char(buf, target)
+		}
+
+		NavigationMotion::EmptyLineAbove => {
+			let target =
+				(0..count).fold(row, |r, _| paragraph_backward(buf, r));
+			line_start(buf, target)
+		}
+		NavigationMotion::EmptyLineBelow => {
+			let target = (0..count).fold(row, |r, _| paragraph_forward(buf, r));
+			if target > last_line(buf) {
+				buffer_end(buf)
+			} else {
+				line_start(buf, target)
+			}
+		}
+
+		NavigationMotion::Top => {
+			let target = (count - 1).min(last_line(buf));
+			first_non_blank(buf, target)
+		}
+		NavigationMotion::Bottom => {
+			let target = if count > 1 {
+				(count - 1).min(last_line(buf))
+			} else {
+				last_line(buf)
+			};
+			first_non_blank(buf, target)
+		}
+	}
+}
+
+fn repeat(pos: usize, count: usize, f: impl Fn(usize) -> usize) -> usize {
+	(0..count).fold(pos, |p, _| f(p))
+}
+
+/// Byte offset of the character following the one at `pos`. Characters are
+/// grapheme clusters, so the cursor never lands inside one.
+pub(crate) fn next_char<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	grapheme::next_boundary(buf, pos)
+}
+
+/// Byte offset of the character preceding `pos`.
+pub(crate) fn prev_char<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	grapheme::prev_boundary(buf, pos)
+}
+
+pub(crate) fn line_start<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	buf.pos_from(RowCol { row, col: 0 })
+}
+
+/// Byte offset of the line break terminating `row`, or the buffer length
+/// for the last line.
+pub(crate) fn line_end<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	if row + 1 < buf.lines() {
+		line_start(buf, row + 1) - 1
+	} else {
+		buf.len()
+	}
+}
+
+pub(crate) fn is_empty_line<B: TextBuffer>(buf: &B, row: usize) -> bool {
+	line_start(buf, row) == line_end(buf, row)
+}
+
+/// Position of the last character on `row`, or the line start if the line is
+/// empty.
+pub(crate) fn last_char<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	let start = line_start(buf, row);
+	let end = line_end(buf, row);
+
+	if end > start {
+		prev_char(buf, end)
+	} else {
+		start
+	}
+}
+
+/// Index of the last line a cursor can be placed on. A trailing line break
+/// does not start a new line.
+pub(crate) fn last_line<B: TextBuffer>(buf: &B) -> usize {
+	let lines = buf.lines();
+	if lines > 1 && line_start(buf, lines - 1) == buf.len() {
+		lines - 2
+	} else {
+		lines.saturating_sub(1)
+	}
+}
+
+pub(crate) fn buffer_end<B: TextBuffer>(buf: &B) -> usize {
+	last_char(buf, last_line(buf))
+}
+
+pub(crate) fn first_non_blank<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	let end = line_end(buf, row);
+	let mut p = line_start(buf, row);
+
+	while p < end {
+		match buf.char_at(p) {
+			Some(ch) if ch.is_whitespace() => p = next_char(buf, p),
+			_ => return p,
+		}
+	}
+
+	last_char(buf, row)
+}
+
+fn same_column<B: TextBuffer>(buf: &B, pos: usize, row: usize) -> usize {
+	let col = buf.rowcol_at(pos).col;
+	let target = buf.pos_from(RowCol { row, col });
+
+	target.min(last_char(buf, row))
+}
+
+pub(crate) fn class_at<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> CharClass {
+	buf.char_at(pos)
+		.map(|ch| char_class(ch, big))
+		.unwrap_or(CharClass::Blank)
+}
+
+/// An empty line is treated as a word of its own by word motions.
+pub(crate) fn is_empty_line_at<B: TextBuffer>(buf: &B, pos: usize) -> bool {
+	buf.char_at(pos) == Some('\n') &&
+		(pos == 0 || buf.char_at(prev_char(buf, pos)) == Some('\n'))
+}
+
+pub(crate) fn word_forward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> usize {
+	if pos >= buf.len() {
+		return pos;
+	}
+
+	match next_word_start(buf, pos, big) {
+		p if p >= buf.len() => buffer_end(buf),
+		p => p,
+	}
+}
+
+/// Start of the word following `pos`, or the buffer length if there is none.
+pub(crate) fn next_word_start<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> usize {
+	let len = buf.len();
+	let class = class_at(buf, pos, big);
+	let mut p = pos;
+
+	if class != CharClass::Blank {
+		while p < len && class_at(buf, p, big) == class {
+			p = next_char(buf, p);
+		}
+	}
+
+	while p < len && class_at(buf, p, big) == CharClass::Blank {
+		if p != pos && is_empty_line_at(buf, p) {
+			return p;
+		}
+		p = next_char(buf, p);
+	}
+
+	p.min(len)
+}
+
+pub(crate) fn word_backward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> usize {
+	if pos == 0 {
+		return 0;
+	}
+
+	let mut p = prev_char(buf, pos);
+	while p > 0 && class_at(buf, p, big) == CharClass::Blank {
+		if is_empty_line_at(buf, p) {
+			return p;
+		}
+		p = prev_char(buf, p);
+	}
+
+	let class = class_at(buf, p, big);
+	if class == CharClass::Blank {
+		return p;
+	}
+
+	while p > 0 {
+		let q = prev_char(buf, p);
+		if class_at(buf, q, big) != class {
+			break;
+		}
+		p = q;
+	}
+	p
+}
+
+pub(crate) fn end_of_word_forward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> usize {
+	let len = buf.len();
+	let mut p = next_char(buf, pos);
+
+	while p < len && class_at(buf, p, big) == CharClass::Blank {
+		p = next_char(buf, p);
+	}
+
+	if p >= len {
+		return buffer_end(buf).max(pos.min(len));
+	}
+
+	let class = class_at(buf, p, big);
+	loop {
+		let n = next_char(buf, p);
+		if n >= len || class_at(buf, n, big) != class {
+			break;
+		}
+		p = n;
+	}
+	p
+}
+
+pub(crate) fn end_of_word_backward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	big: bool,
+) -> usize {
+	let class = class_at(buf, pos, big);
+	let mut p = pos;
+
+	if class != CharClass::Blank {
+		while p > 0 && class_at(buf, prev_char(buf, p), big) == class {
+			p = prev_char(buf, p);
+		}
+	}
+
+	if p == 0 {
+		return 0;
+	}
+
+	p = prev_char(buf, p);
+	while p > 0 && class_at(buf, p, big) == CharClass::Blank {
+		if is_empty_line_at(buf, p) {
+			return p;
+		}
+		p = prev_char(buf, p);
+	}
+	p
+}
+
+/// Moves `pos` off a line break or past the buffer end onto the last character
+/// of its line.
+pub(crate) fn clamp_to_line<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	let pos = pos.min(buf.len());
+	let row = buf.rowcol_at(pos).row;
+
+	if row > last_line(buf) {
+		buffer_end(buf)
+	} else {
+		pos.min(last_char(buf, row))
+	}
+}
+
+fn ends_sentence(ch: char) -> bool {
+	matches!(ch, '.' | '!' | '?')
+}
+
+fn closes_sentence(ch: char) -> bool {
+	matches!(ch, ')' | ']' | '"' | '\'')
+}
+
+/// Start of the sentence following `pos`, or the buffer length if there is
+/// none.
+///
+/// A sentence ends at a `.`, `!` or `?`, optionally followed by closing
+/// brackets or quotes, and then by whitespace. Empty lines are sentence
+/// boundaries.
+pub(crate) fn sentence_forward<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	let len = buf.len();
+	let mut p = pos;
+
+	if is_empty_line_at(buf, p) {
+		while p < len && class_at(buf, p, false) == CharClass::Blank {
+			p = next_char(buf, p);
+		}
+		return p;
+	}
+
+	while p < len {
+		let next = next_char(buf, p);
+		if next < len && is_empty_line_at(buf, next) {
+			return next;
+		}
+
+		if buf.char_at(p).is_some_and(ends_sentence) {
+			let mut q = next;
+			while buf.char_at(q).is_some_and(closes_sentence) {
+				q = next_char(buf, q);
+			}
+
+			if q >= len || class_at(buf, q, false) == CharClass::Blank {
+				while q < len && class_at(buf, q, false) == CharClass::Blank {
+					if is_empty_line_at(buf, q) {
+						return q;
+					}
+					q = next_char(buf, q);
+				}
+				return q;
+			}
+		}
+
+		p = next;
+	}
+
+	len
+}
+
+/// Start of the sentence containing `pos`, or of the previous sentence if
+/// `pos` already is at a sentence start.
+pub(crate) fn sentence_backward<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	if pos == 0 {
+		return 0;
+	}
+
+	let row = buf.rowcol_at(prev_char(buf, pos)).row;
+	let begin = line_start(buf, paragraph_backward(buf, row));
+
+	let mut start = begin;
+	let mut p = begin;
+	loop {
+		let next = sentence_forward(buf, p);
+		if next >= pos || next == p {
+			return start;
+		}
+		start = next;
+		p = next;
+	}
+}
+
+/// Row of the next empty line after the paragraph at `row`, or one past the
+/// last line if there is none.
+pub(crate) fn paragraph_forward<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	let last = last_line(buf);
+	let mut r = row;
+
+	while r <= last && is_empty_line(buf, r) {
+		r += 1;
+	}
+	while r <= last && !is_empty_line(buf, r) {
+		r += 1;
+	}
+	r
+}
+
+/// Row of the previous empty line before the paragraph at `row`, or `0` if
+/// there is none.
+pub(crate) fn paragraph_backward<B: TextBuffer>(buf: &B, row: usize) -> usize {
+	let mut r = row;
+
+	while r > 0 && is_empty_line(buf, r) {
+		r -= 1;
+	}
+	while r > 0 && !is_empty_line(buf, r) {
+		r -= 1;
+	}
+	r
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	fn nav(text: &str, pos: usize, nav: NavigationMotion, count: u32) -> usize {
+		navigate(&PieceTable::new(text.into()), pos, nav, count)
+	}
+
+	#[test]
+	fn char_motions_stay_on_line() {
+		let text = "abc\ndef";
+
+		assert_eq!(nav(text, 0, NavigationMotion::CharForward, 10), 2);
+		assert_eq!(nav(text, 5, NavigationMotion::CharBackward, 10), 4);
+	}
+
+	#[test]
+	fn line_motions_clamp_column() {
+		let text = "abcdef\nab\nabcdef\n";
+
+		assert_eq!(nav(text, 5, NavigationMotion::LineForward, 1), 8);
+		assert_eq!(nav(text, 5, NavigationMotion::LineForward, 2), 15);
+		assert_eq!(nav(text, 5, NavigationMotion::LineForward, 9), 15);
+		assert_eq!(nav(text, 15, NavigationMotion::LineBackward, 2), 5);
+
+		// columns count clusters, so `y` sits below `x`
+		let text = "e\u{301}x\n👩\u{200D}💻y";
+		assert_eq!(nav(text, 3, NavigationMotion::LineForward, 1), 16);
+		assert_eq!(nav(text, 16, NavigationMotion::LineBackward, 1), 3);
+	}
+
+	#[test]
+	fn word_motions() {
+		let text = "foo.bar baz\n\nqux";
+
+		assert_eq!(nav(text, 0, NavigationMotion::WordForward, 1), 3);
+		assert_eq!(nav(text, 0, NavigationMotion::BigWordForward, 1), 8);
+		assert_eq!(nav(text, 8, NavigationMotion::WordForward, 1), 12);
+		assert_eq!(nav(text, 8, NavigationMotion::WordForward, 2), 13);
+		assert_eq!(nav(text, 13, NavigationMotion::WordBackward, 1), 12);
+		assert_eq!(nav(text, 8, NavigationMotion::BigWordBackward, 1), 0);
+		assert_eq!(nav(text, 0, NavigationMotion::EndOfWordForward, 1), 2);
+		assert_eq!(nav(text, 0, NavigationMotion::BigEndOfWordForward, 1), 6);
+		assert_eq!(nav(text, 13, NavigationMotion::EndOfWordBackward, 1), 12);
+		assert_eq!(nav(text, 13, NavigationMotion::EndOfWordBackward, 2), 10);
+	}
+
+	#[test]
+	fn line_and_buffer_motions() {
+		let text = "  one\ntwo\n\nthree\n";
+
+		assert_eq!(nav(text, 3, NavigationMotion::LineStart, 1), 0);
+		assert_eq!(nav(text, 0, NavigationMotion::LineEnd, 1), 4);
+		assert_eq!(nav(text, 0, NavigationMotion::LineEnd, 2), 8);
+		assert_eq!(nav(text, 0, NavigationMotion::EmptyLineBelow, 1), 10);
+		assert_eq!(nav(text, 0, NavigationMotion::EmptyLineBelow, 2), 15);
+		assert_eq!(nav(text, 12, NavigationMotion::EmptyLineAbove, 1), 10);
+		assert_eq!(nav(text, 12, NavigationMotion::Top, 1), 2);
+		assert_eq!(nav(text, 0, NavigationMotion::Bottom, 1), 11);
+		assert_eq!(nav(text, 0, NavigationMotion::Bottom, 2), 6);
+	}
+}
@@ -0,0 +1,651 @@
+use alloc::string::String;
+
+use crate::{
+	regex::Regex,
+	Cursor,
+	MarkTable,
+	TextBuffer,
+};
+
+use super::{
+	meta,
+	navigation::{
+		self,
+		class_at,
+		first_non_blank,
+		last_line,
+		line_end,
+		line_start,
+		next_char,
+		next_word_start,
+		prev_char,
+		CharClass,
+	},
+	shift,
+	text_object,
+	GoToLineNumber,
+	Motion,
+	MotionType,
+	NavigationMotion,
+	Options,
+	Range,
+	Scope,
+};
+
+/// Whether a range covers whole lines or just the characters between its
+/// bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeKind {
+	Charwise,
+	Linewise,
+}
+
+/// The byte range `start..end` an operator acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+	pub start: usize,
+	pub end:   usize,
+	pub kind:  RangeKind,
+}
+
+impl TextRange {
+	pub fn charwise(start: usize, end: usize) -> Self {
+		Self {
+			start: start.min(end),
+			end:   start.max(end),
+			kind:  RangeKind::Charwise,
+		}
+	}
+
+	/// Range spanning the rows `first..=last`, including the line break of
+	/// `last`.
+	pub fn linewise<B: TextBuffer>(buf: &B, first: usize, last: usize) -> Self {
+		let (first, last) = (first.min(last), first.max(last));
+		let end = if last + 1 < buf.lines() {
+			line_start(buf, last + 1)
+		} else {
+			buf.len()
+		};
+
+		Self {
+			start: line_start(buf, first),
+			end,
+			kind: RangeKind::Linewise,
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+
+	pub fn is_linewise(&self) -> bool {
+		self.kind == RangeKind::Linewise
+	}
+}
+
+/// Resolves the range an operated `motion` acts on when the cursor is at
+/// `pos`. Returns `None` for pure navigations and for motions that fail,
+/// e.g. `dfx` without an `x` on the line.
+pub fn resolve<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	motion: &Motion,
+	marks: &MarkTable,
+) -> Option<TextRange> {
+	match motion {
+		Motion::NavigationOnly { .. } => None,
+		Motion::OperatedNavigation {
+			motion_type,
+			nav,
+			count,
+		} => resolve_navigation(buf, pos, *motion_type, *nav, *count),
+		Motion::OperatedRange { range, count, .. } => {
+			resolve_range(buf, pos, range, *count, marks)
+		}
+	}
+}
+
+fn resolve_navigation<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	op: MotionType,
+	nav: NavigationMotion,
+	count: u32,
+) -> Option<TextRange> {
+	let row = buf.rowcol_at(pos).row;
+	let target = navigation::navigate(buf, pos, nav, count);
+
+	match nav {
+		NavigationMotion::LineForward | NavigationMotion::LineBackward => {
+			let target_row = buf.rowcol_at(target).row;
+			if target_row == row {
+				return None;
+			}
+			Some(TextRange::linewise(buf, row, target_row))
+		}
+		NavigationMotion::Top | NavigationMotion::Bottom => {
+			let target_row = buf.rowcol_at(target).row;
+			Some(TextRange::linewise(buf, row, target_row))
+		}
+
+		NavigationMotion::CharForward => {
+			let limit = line_end(buf, row);
+			let end = (0..count.max(1))
+				.fold(pos, |p, _| next_char(buf, p).min(limit));
+			Some(TextRange::charwise(pos, end))
+		}
+
+		NavigationMotion::WordForward | NavigationMotion::BigWordForward => {
+			let big = nav == NavigationMotion::BigWordForward;
+
+			if op == MotionType::Change &&
+				class_at(buf, pos, big) != CharClass::Blank
+			{
+				let end = change_word_end(buf, pos, count, big);
+				return Some(TextRange::charwise(pos, next_char(buf, end)));
+			}
+
+			Some(TextRange::charwise(pos, word_end(buf, pos, count, big)))
+		}
+
+		NavigationMotion::EndOfWordForward |
+		NavigationMotion::BigEndOfWordForward => {
+			Some(TextRange::charwise(pos, next_char(buf, target)))
+		}
+		NavigationMotion::EndOfWordBackward |
+		NavigationMotion::BigEndOfWordBackward => {
+			Some(TextRange::charwise(target, next_char(buf, pos)))
+		}
+		NavigationMotion::LineEnd => {
+			let target_row = buf.rowcol_at(target).row;
+			Some(TextRange::charwise(pos, line_end(buf, target_row)))
+		}
+
+		NavigationMotion::EmptyLineBelow => {
+			let r = (0..count.max(1))
+				.fold(row, |r, _| navigation::paragraph_forward(buf, r));
+			if r > last_line(buf) {
+				Some(TextRange::charwise(pos, line_end(buf, last_line(buf))))
+			} else {
+				Some(exclusive(buf, pos, line_start(buf, r)))
+			}
+		}
+
+		NavigationMotion::CharBackward |
+		NavigationMotion::WordBackward |
+		NavigationMotion::BigWordBackward |
+		NavigationMotion::LineStart |
+		NavigationMotion::EmptyLineAbove => Some(exclusive(buf, target, pos)),
+	}
+}
+
+fn resolve_range<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	range: &Range,
+	count: u32,
+	marks: &MarkTable,
+) -> Option<TextRange> {
+	let count = count.max(1);
+	let row = buf.rowcol_at(pos).row;
+
+	match range {
+		Range::ForwardTo(c) => find_forward(buf, pos, *c, count)
+			.map(|p| TextRange::charwise(pos, next_char(buf, p))),
+		Range::ForwardTill(c) => find_forward(buf, pos, *c, count)
+			.map(|p| TextRange::charwise(pos, p)),
+		Range::BackwardsTo(c) => find_backward(buf, pos, *c, count)
+			.map(|p| TextRange::charwise(p, pos)),
+		Range::BackwardsTill(c) => find_backward(buf, pos, *c, count)
+			.map(|p| TextRange::charwise(next_char(buf, p), pos)),
+
+		Range::Word => {
+			let class = class_at(buf, pos, false);
+			let mut start = pos;
+			while start > 0 &&
+				class_at(buf, prev_char(buf, start), false) == class
+			{
+				start = prev_char(buf, start);
+			}
+			let end = change_word_end(buf, pos, count, false);
+			Some(TextRange::charwise(start, next_char(buf, end)))
+		}
+		Range::Line => {
+			let last = (row + count as usize - 1).min(last_line(buf));
+			Some(TextRange::linewise(buf, row, last))
+		}
+		Range::GoToLine(line) => {
+			let target = match line {
+				GoToLineNumber::First => 0,
+				GoToLineNumber::Last => last_line(buf),
+				GoToLineNumber::Number(n) => {
+					(n.saturating_sub(1) as usize).min(last_line(buf))
+				}
+			};
+			Some(TextRange::linewise(buf, row, target))
+		}
+
+		Range::Percent(scope) => {
+			matching_pair(buf, pos, *scope).map(|(open, close)| {
+				TextRange::charwise(open, next_char(buf, close))
+			})
+		}
+
+		Range::SentenceEnd => {
+			let target = (0..count)
+				.fold(pos, |p, _| navigation::sentence_forward(buf, p));
+			Some(exclusive(buf, pos, target))
+		}
+		Range::SentenceStart => {
+			let target = (0..count)
+				.fold(pos, |p, _| navigation::sentence_backward(buf, p));
+			Some(exclusive(buf, target, pos))
+		}
+
+		Range::Inside(scope) => {
+			text_object::resolve(buf, pos, *scope, false, count)
+		}
+		Range::Around(scope) => {
+			text_object::resolve(buf, pos, *scope, true, count)
+		}
+
+		Range::ForwardSearch(pattern) | Range::BackwardSearch(pattern) => {
+			let regex = Regex::new(pattern).ok()?;
+			let forward = matches!(range, Range::ForwardSearch(_));
+			let target = (0..count.max(1)).try_fold(pos, |p, _| {
+				let found = if forward {
+					regex.next_match(buf, p)
+				} else {
+					regex.prev_match(buf, p)
+				};
+				found.map(|m| m.start)
+			})?;
+
+			// searches wrap around, so the match may lie on either side
+			Some(exclusive(buf, pos.min(target), pos.max(target)))
+		}
+		Range::Mark(c) => {
+			let target = marks.jump_target(buf, *c, false)?;
+			Some(exclusive(buf, pos.min(target), pos.max(target)))
+		}
+		Range::MarkLine(c) => {
+			let target = buf.rowcol_at(marks.jump_target(buf, *c, true)?).row;
+			Some(TextRange::linewise(buf, row.min(target), row.max(target)))
+		}
+	}
+}
+
+/// Applies the rules for exclusive motions: if the range ends in the first
+/// column of a later line, the line break before it is not included, and if
+/// the range also starts at or before the first non-blank of its line, the
+/// motion becomes linewise.
+fn exclusive<B: TextBuffer>(buf: &B, start: usize, end: usize) -> TextRange {
+	let first = buf.rowcol_at(start).row;
+	let last = buf.rowcol_at(end).row;
+
+	if last > first && end == line_start(buf, last) {
+		if start <= first_non_blank(buf, first) {
+			return TextRange::linewise(buf, first, last - 1);
+		}
+		return TextRange::charwise(start, line_end(buf, last - 1));
+	}
+
+	TextRange::charwise(start, end)
+}
+
+/// End of `count` words for `dw`. The last word never extends past the end
+/// of its line.
+fn word_end<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	count: u32,
+	big: bool,
+) -> usize {
+	let from = (1..count.max(1)).fold(pos, |p, _| next_word_start(buf, p, big));
+	let end = next_word_start(buf, from, big);
+	let from_row = buf.rowcol_at(from.min(buf.len())).row;
+
+	if end > from && buf.rowcol_at(end).row > from_row {
+		return line_end(buf, from_row).max(pos);
+	}
+	end
+}
+
+/// Last character of the `count`th word for `cw`, which changes up to the
+/// end of the word rather than to the start of the next one.
+fn change_word_end<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	count: u32,
+	big: bool,
+) -> usize {
+	let class = class_at(buf, pos, big);
+	let mut end = pos;
+
+	loop {
+		let next = next_char(buf, end);
+		if next >= buf.len() || class_at(buf, next, big) != class {
+			break;
+		}
+		end = next;
+	}
+
+	(1..count.max(1))
+		.fold(end, |p, _| navigation::end_of_word_forward(buf, p, big))
+}
+
+/// Position of the `count`th `ch` after `pos` on the same line.
+fn find_forward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	ch: char,
+	count: u32,
+) -> Option<usize> {
+	let end = line_end(buf, buf.rowcol_at(pos).row);
+	let mut p = pos;
+	let mut found = 0;
+
+	while p < end {
+		p = next_char(buf, p);
+		if p < end && buf.char_at(p) == Some(ch) {
+			found += 1;
+			if found == count {
+				return Some(p);
+			}
+		}
+	}
+	None
+}
+
+/// Position of the `count`th `ch` before `pos` on the same line.
+fn find_backward<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	ch: char,
+	count: u32,
+) -> Option<usize> {
+	let start = line_start(buf, buf.rowcol_at(pos).row);
+	let mut p = pos;
+	let mut found = 0;
+
+	while p > start {
+		p = prev_char(buf, p);
+		if buf.char_at(p) == Some(ch) {
+			found += 1;
+			if found == count {
+				return Some(p);
+			}
+		}
+	}
+	None
+}
+
+/// Finds the first bracket of `scope` at or after `pos` on the current line
+/// and returns it together with its match, ordered by position.
+fn matching_pair<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	scope: Scope,
+) -> Option<(usize, usize)> {
+	let (open, close) = scope.delimiters().filter(|(o, c)| o != c)?;
+	let end = line_end(buf, buf.rowcol_at(pos).row);
+
+	let mut p = pos;
+	while p < end {
+		match buf.char_at(p) {
+			Some(ch) if ch == open => {
+				return scan_balanced(buf, p, open, close, true).map(|m| (p, m))
+			}
+			Some(ch) if ch == close => {
+				return scan_balanced(buf, p, open, close, false)
+					.map(|m| (m, p))
+			}
+			_ => p = next_char(buf, p),
+		}
+	}
+	None
+}
+
+/// Starting on a delimiter at `pos`, scans for the one balancing it.
+pub(crate) fn scan_balanced<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	open: char,
+	close: char,
+	forward: bool,
+) -> Option<usize> {
+	let (inc, dec) = if forward {
+		(open, close)
+	} else {
+		(close, open)
+	};
+	let mut depth = 0usize;
+	let mut p = pos;
+
+	loop {
+		match buf.char_at(p) {
+			Some(ch) if ch == inc => depth += 1,
+			Some(ch) if ch == dec => {
+				depth -= 1;
+				if depth == 0 {
+					return Some(p);
+				}
+			}
+			_ => {}
+		}
+
+		if forward {
+			p = next_char(buf, p);
+			if p >= buf.len() {
+				return None;
+			}
+		} else {
+			if p == 0 {
+				return None;
+			}
+			p = prev_char(buf, p);
+		}
+	}
+}
+
+/// Executes `op` on `range`, leaving the cursor where Vim would.
+///
+/// Returns the text the operator removed or copied. Linewise text always
+/// ends in a line break.
+pub fn execute<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	op: MotionType,
+	range: TextRange,
+	options: &Options,
+) -> Option<String> {
+	match op {
+		MotionType::Yank => {
+			let text = linewise_text(buf, range);
+			if range.start < cursor.pos {
+				cursor.pos = match range.kind {
+					RangeKind::Charwise => range.start,
+					RangeKind::Linewise => {
+						let row = buf.rowcol_at(range.start).row;
+						cursor.pos.max(range.start).min(line_end(buf, row))
+					}
+				};
+			}
+			Some(text)
+		}
+		MotionType::Delete => {
+			let text = linewise_text(buf, range);
+			let (start, end) = deletion_bounds(buf, range);
+			if start < end {
+				buf.delete(start, end);
+			}
+
+			cursor.pos = match range.kind {
+				RangeKind::Charwise => navigation::clamp_to_line(buf, start),
+				RangeKind::Linewise => {
+					let row = buf.rowcol_at(start.min(buf.len())).row;
+					first_non_blank(buf, row.min(last_line(buf)))
+				}
+			};
+			Some(text)
+		}
+		MotionType::Change => {
+			let text = linewise_text(buf, range);
+			let mut end = range.end;
+			if range.is_linewise() &&
+				buf.char_at(prev_char(buf, end)) == Some('\n')
+			{
+				end = prev_char(buf, end);
+			}
+			if range.start < end {
+				buf.delete(range.start, end);
+			}
+
+			cursor.pos = range.start;
+			Some(text)
+		}
+		MotionType::Visual => {
+			cursor.pos = prev_char(buf, range.end).max(range.start);
+			None
+		}
+		MotionType::ShiftRight | MotionType::ShiftLeft => {
+			let right = op == MotionType::ShiftRight;
+			shift::apply(buf, cursor, range, right, options.shift_width);
+			None
+		}
+		// the lines only change once the command for them is known, see
+		// `line_range`
+		MotionType::Filter => None,
+		MotionType::Meta(op) => {
+			meta::apply(buf, cursor, op, range, options);
+			None
+		}
+	}
+}
+
+fn linewise_text<B: TextBuffer>(buf: &B, range: TextRange) -> String {
+	let mut text = buf.slice(range.start, range.end);
+	if range.is_linewise() && !text.ends_with('\n') {
+		text.push('\n');
+	}
+	text
+}
+
+/// Deleting the last lines of a buffer without a trailing line break also
+/// removes the break before them, so no empty line is left behind.
+fn deletion_bounds<B: TextBuffer>(buf: &B, range: TextRange) -> (usize, usize) {
+	let unterminated = range.end == buf.len() &&
+		range.end > range.start &&
+		buf.char_at(prev_char(buf, range.end)) != Some('\n');
+
+	if range.is_linewise() && unterminated && range.start > 0 {
+		(prev_char(buf, range.start), range.end)
+	} else {
+		(range.start, range.end)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		motion::apply,
+		piece_table::PieceTable,
+	};
+
+	fn run(text: &str, pos: usize, motion: Motion) -> (String, String, usize) {
+		let mut buf = PieceTable::new(text.into());
+		let mut cursor = Cursor { pos };
+		let removed = apply(
+			&mut buf,
+			&mut cursor,
+			motion,
+			&Options::default(),
+			&MarkTable::default(),
+		)
+		.map(|reg| reg.text)
+		.unwrap_or_default();
+
+		(buf.slice(0, buf.len()), removed, cursor.pos)
+	}
+
+	fn op(
+		motion_type: MotionType,
+		nav: NavigationMotion,
+		count: u32,
+	) -> Motion {
+		Motion::OperatedNavigation {
+			motion_type,
+			nav,
+			count,
+		}
+	}
+
+	fn range(motion_type: MotionType, range: Range, count: u32) -> Motion {
+		Motion::OperatedRange {
+			motion_type,
+			range,
+			count,
+		}
+	}
+
+	#[test]
+	fn delete_word_stops_at_line_end() {
+		let dw = op(MotionType::Delete, NavigationMotion::WordForward, 1);
+
+		assert_eq!(run("foo bar\nbaz", 0, dw.clone()).0, "bar\nbaz");
+		assert_eq!(run("foo bar\nbaz", 4, dw).0, "foo \nbaz");
+	}
+
+	#[test]
+	fn change_word_keeps_trailing_space() {
+		let cw = op(MotionType::Change, NavigationMotion::WordForward, 2);
+
+		let (text, removed, cursor) = run("one two three", 0, cw);
+		assert_eq!(text, " three");
+		assert_eq!(removed, "one two");
+		assert_eq!(cursor, 0);
+	}
+
+	#[test]
+	fn linewise_delete_and_yank() {
+		let dd = range(MotionType::Delete, Range::Line, 2);
+		let (text, removed, cursor) = run("a\n  b\nc\n  d", 2, dd);
+		assert_eq!(text, "a\n  d");
+		assert_eq!(removed, "  b\nc\n");
+		assert_eq!(cursor, 4);
+
+		let dj = op(MotionType::Delete, NavigationMotion::LineForward, 1);
+		assert_eq!(run("a\nb\nc", 2, dj), ("a".into(), "b\nc\n".into(), 0));
+
+		let yk = op(MotionType::Yank, NavigationMotion::LineBackward, 1);
+		assert_eq!(run("ab\ncd\n", 4, yk).1, "ab\ncd\n");
+	}
+
+	#[test]
+	fn exclusive_motion_becomes_linewise() {
+		let d = op(MotionType::Delete, NavigationMotion::EmptyLineBelow, 1);
+
+		assert_eq!(run("a\nb\n\nc", 0, d.clone()).0, "\nc");
+		assert_eq!(run("a\nb\n\nc", 1, d).0, "a\n\nc");
+	}
+
+	#[test]
+	fn find_and_percent_ranges() {
+		let dt = range(MotionType::Delete, Range::ForwardTill(','), 2);
+		assert_eq!(run("a, b, c", 0, dt).0, ", c");
+
+		let df = range(MotionType::Delete, Range::BackwardsTo('a'), 1);
+		assert_eq!(run("abc", 2, df).0, "c");
+
+		let yp = range(MotionType::Yank, Range::Percent(Scope::Parentheses), 1);
+		assert_eq!(run("f(a, (b))", 0, yp).1, "(a, (b))");
+
+		let ds =
+			range(MotionType::Delete, Range::ForwardSearch("\\d+".into()), 2);
+		assert_eq!(run("a1 b22 c3", 0, ds).0, "22 c3");
+
+		let ys = range(MotionType::Yank, Range::BackwardSearch("b".into()), 1);
+		assert_eq!(run("ab abc", 4, ys).1, "b a");
+	}
+}
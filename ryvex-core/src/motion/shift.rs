@@ -0,0 +1,94 @@
+//! The `>` and `<` operators, which shift lines by `shift_width` columns.
+
+use alloc::string::String;
+
+use crate::{
+	Cursor,
+	TextBuffer,
+};
+
+use super::{
+	meta::{
+		width,
+		TAB_STOP,
+	},
+	navigation::{
+		first_non_blank,
+		line_end,
+		line_start,
+	},
+	operator::TextRange,
+};
+
+/// Shifts every line touched by `range` right or left by `shift_width`
+/// columns and leaves the cursor on the first non-blank of the first line.
+/// The new indentation uses tabs as far as it can, like Vim without
+/// `expandtab`. Empty lines are not indented.
+pub fn apply<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	range: TextRange,
+	right: bool,
+	shift_width: usize,
+) {
+	let first_row = buf.rowcol_at(range.start).row;
+	let last_row = buf
+		.rowcol_at(range.end.saturating_sub(1).max(range.start))
+		.row;
+
+	// bottom up, so the starts of the remaining lines stay put
+	for row in (first_row..=last_row).rev() {
+		let start = line_start(buf, row);
+		let line = buf.slice(start, line_end(buf, row));
+		if right && line.is_empty() {
+			continue;
+		}
+
+		let indent = &line[..line.len() - line.trim_start().len()];
+		let indent_end = start + indent.len();
+		let old = width(indent);
+		let new = if right {
+			old + shift_width
+		} else {
+			old.saturating_sub(shift_width)
+		};
+
+		let text = indentation(new);
+		if text != indent {
+			if start < indent_end {
+				buf.delete(start, indent_end);
+			}
+			buf.insert(start, &text);
+		}
+	}
+
+	cursor.pos = first_non_blank(buf, first_row);
+}
+
+fn indentation(width: usize) -> String {
+	let mut text = String::with_capacity(width);
+	text.extend(core::iter::repeat_n('\t', width / TAB_STOP));
+	text.extend(core::iter::repeat_n(' ', width % TAB_STOP));
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	#[test]
+	fn shift_lines() {
+		let run = |text: &str, right: bool, width: usize| {
+			let mut buf = PieceTable::new(text.into());
+			let mut cursor = Cursor { pos: 0 };
+			let range = TextRange::linewise(&buf, 0, 2);
+
+			apply(&mut buf, &mut cursor, range, right, width);
+			(buf.slice(0, buf.len()), cursor.pos)
+		};
+
+		assert_eq!(run("a\n\n    b\n", true, 4), ("    a\n\n\tb\n".into(), 4));
+		assert_eq!(run("\ta\n  b\nc", false, 4), ("    a\nb\nc".into(), 4));
+	}
+}
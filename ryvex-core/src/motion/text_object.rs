@@ -0,0 +1,463 @@
+use alloc::vec::Vec;
+
+use crate::TextBuffer;
+
+use super::{
+	navigation::{
+		class_at,
+		first_non_blank,
+		is_empty_line,
+		last_line,
+		line_end,
+		line_start,
+		next_char,
+		prev_char,
+		CharClass,
+	},
+	operator::{
+		scan_balanced,
+		TextRange,
+	},
+	Scope,
+};
+
+/// Resolves the text object `scope` around `pos`. `around` selects the `a`
+/// variant, which includes the delimiters or surrounding white space.
+///
+/// For brackets `count` selects the enclosing level, e.g. `2i(` acts on the
+/// parentheses around the innermost pair. For words and paragraphs it
+/// extends the object over `count` objects.
+pub fn resolve<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	scope: Scope,
+	around: bool,
+	count: u32,
+) -> Option<TextRange> {
+	let count = count.max(1);
+
+	match scope {
+		Scope::Word => word(buf, pos, around, count),
+		Scope::Paragraph => paragraph(buf, pos, around, count),
+		Scope::SingleQuote | Scope::DoubleQuote | Scope::Backtick => {
+			let (quote, _) = scope.delimiters()?;
+			quoted(buf, pos, quote, around)
+		}
+		Scope::Parentheses |
+		Scope::Brackets |
+		Scope::Braces |
+		Scope::AngleBrackets => {
+			let (open, close) = scope.delimiters()?;
+			bracketed(buf, pos, open, close, around, count)
+		}
+	}
+}
+
+fn bracketed<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	open: char,
+	close: char,
+	around: bool,
+	count: u32,
+) -> Option<TextRange> {
+	let mut start = enclosing_open(buf, pos, open, close)?;
+	for _ in 1..count {
+		if start == 0 {
+			return None;
+		}
+		start = enclosing_open(buf, prev_char(buf, start), open, close)?;
+	}
+	let end = scan_balanced(buf, start, open, close, true)?;
+
+	if around {
+		return Some(TextRange::charwise(start, next_char(buf, end)));
+	}
+
+	// `{` ending a line and `}` starting one select the lines in between
+	let first = buf.rowcol_at(start).row;
+	let last = buf.rowcol_at(end).row;
+	if buf.char_at(next_char(buf, start)) == Some('\n') &&
+		first_non_blank(buf, last) == end &&
+		last > first + 1
+	{
+		return Some(TextRange::linewise(buf, first + 1, last - 1));
+	}
+
+	Some(TextRange::charwise(next_char(buf, start), end))
+}
+
+/// Position of the unbalanced `open` at or before `pos`. A cursor on `close`
+/// belongs to the pair that `close` ends.
+fn enclosing_open<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	open: char,
+	close: char,
+) -> Option<usize> {
+	let mut depth = 0usize;
+	let mut p = pos;
+
+	loop {
+		match buf.char_at(p) {
+			Some(ch) if ch == open => {
+				if depth == 0 {
+					return Some(p);
+				}
+				depth -= 1;
+			}
+			Some(ch) if ch == close && p != pos => depth += 1,
+			_ => {}
+		}
+
+		if p == 0 {
+			return None;
+		}
+		p = prev_char(buf, p);
+	}
+}
+
+/// A quote preceded by an odd number of backslashes does not delimit.
+fn is_escaped<B: TextBuffer>(buf: &B, pos: usize) -> bool {
+	let mut escapes = 0;
+	let mut p = pos;
+
+	while p > 0 {
+		p = prev_char(buf, p);
+		if buf.char_at(p) != Some('\\') {
+			break;
+		}
+		escapes += 1;
+	}
+	escapes % 2 == 1
+}
+
+/// A cursor on a quote pairs it with its neighbour by counting the quotes
+/// before it on its line; a cursor before the first quote uses the first
+/// pair on the line. Only where the line has no pair for the cursor does a
+/// quote span lines, so a stray quote elsewhere cannot throw the common case
+/// off.
+fn quoted<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	quote: char,
+	around: bool,
+) -> Option<TextRange> {
+	let (open, close) = quotes_on_line(buf, pos, quote)
+		.or_else(|| quotes_across_lines(buf, pos, quote))?;
+
+	if !around {
+		return Some(TextRange::charwise(next_char(buf, open), close));
+	}
+
+	let start = line_start(buf, buf.rowcol_at(open).row);
+	let end = line_end(buf, buf.rowcol_at(close).row);
+
+	// trailing white space is included, or leading if there is none
+	let mut to = next_char(buf, close);
+	while to < end && class_at(buf, to, false) == CharClass::Blank {
+		to = next_char(buf, to);
+	}
+	let mut from = open;
+	if to == next_char(buf, close) {
+		while from > start &&
+			class_at(buf, prev_char(buf, from), false) == CharClass::Blank
+		{
+			from = prev_char(buf, from);
+		}
+	}
+
+	Some(TextRange::charwise(from, to))
+}
+
+/// The unescaped `quote`s in `start..end`.
+fn quotes_in<B: TextBuffer>(
+	buf: &B,
+	start: usize,
+	end: usize,
+	quote: char,
+) -> Vec<usize> {
+	let mut quotes = Vec::new();
+	let mut p = start;
+	while p < end {
+		if buf.char_at(p) == Some(quote) && !is_escaped(buf, p) {
+			quotes.push(p);
+		}
+		p = next_char(buf, p);
+	}
+	quotes
+}
+
+fn quotes_on_line<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	quote: char,
+) -> Option<(usize, usize)> {
+	let row = buf.rowcol_at(pos).row;
+	let quotes =
+		quotes_in(buf, line_start(buf, row), line_end(buf, row), quote);
+
+	match quotes.iter().position(|&q| q == pos) {
+		Some(i) if i % 2 == 0 => Some((pos, *quotes.get(i + 1)?)),
+		Some(i) => Some((quotes[i - 1], pos)),
+		None => {
+			let before = quotes.iter().rev().find(|&&q| q < pos);
+			let after = quotes.iter().find(|&&q| q > pos);
+
+			match (before, after) {
+				(Some(&b), Some(&a)) => Some((b, a)),
+				(None, Some(_)) => {
+					let mut rest = quotes.iter().filter(|&&q| q > pos);
+					Some((*rest.next()?, *rest.next()?))
+				}
+				_ => None,
+			}
+		}
+	}
+}
+
+/// Pairs the quotes of the whole buffer from its start, so the cursor is
+/// inside a quote if an odd number of them comes before it.
+fn quotes_across_lines<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	quote: char,
+) -> Option<(usize, usize)> {
+	let quotes = quotes_in(buf, 0, buf.len(), quote);
+
+	match quotes.binary_search(&pos) {
+		Ok(i) if i % 2 == 0 => Some((pos, *quotes.get(i + 1)?)),
+		Ok(i) => Some((quotes[i - 1], pos)),
+		Err(i) if i % 2 == 1 => Some((quotes[i - 1], *quotes.get(i)?)),
+		Err(_) => None,
+	}
+}
+
+/// Start of the run of characters sharing the class at `pos`, within its line.
+fn run_start<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	let class = class_at(buf, pos, false);
+	let first = line_start(buf, buf.rowcol_at(pos).row);
+	let mut p = pos;
+
+	while p > first && class_at(buf, prev_char(buf, p), false) == class {
+		p = prev_char(buf, p);
+	}
+	p
+}
+
+/// One past the run of characters sharing the class at `pos`, within its
+/// line.
+fn run_end<B: TextBuffer>(buf: &B, pos: usize) -> usize {
+	let class = class_at(buf, pos, false);
+	let end = line_end(buf, buf.rowcol_at(pos).row);
+	let mut p = pos;
+
+	while p < end && class_at(buf, p, false) == class {
+		p = next_char(buf, p);
+	}
+	p
+}
+
+fn is_blank_at<B: TextBuffer>(buf: &B, pos: usize) -> bool {
+	buf.char_at(pos)
+		.is_some_and(|ch| ch != '\n' && ch.is_whitespace())
+}
+
+/// `iw` selects runs of word characters, punctuation or white space, each
+/// counting as one object. `aw` adds the white space after each word, or
+/// before the first one if the line has none after it.
+fn word<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	around: bool,
+	count: u32,
+) -> Option<TextRange> {
+	let row = buf.rowcol_at(pos).row;
+	let end = line_end(buf, row);
+	if pos >= end {
+		return None;
+	}
+
+	let start = run_start(buf, pos);
+	let mut to = pos;
+
+	for _ in 0..count {
+		if to >= end {
+			break;
+		}
+
+		let blank = is_blank_at(buf, to);
+		to = run_end(buf, to);
+
+		// in `aw` white space and the word after it form one object
+		if around && to < end && (blank || is_blank_at(buf, to)) {
+			to = run_end(buf, to);
+		}
+	}
+
+	let mut from = start;
+	if around && !is_blank_at(buf, pos) && !is_blank_at(buf, prev_char(buf, to))
+	{
+		let first = line_start(buf, row);
+		while from > first && is_blank_at(buf, prev_char(buf, from)) {
+			from = prev_char(buf, from);
+		}
+	}
+
+	Some(TextRange::charwise(from, to))
+}
+
+/// `ip` selects the paragraph or run of empty lines under the cursor, `ap`
+/// also the empty lines after it, or before it if there are none after.
+fn paragraph<B: TextBuffer>(
+	buf: &B,
+	pos: usize,
+	around: bool,
+	count: u32,
+) -> Option<TextRange> {
+	let last = last_line(buf);
+	let row = buf.rowcol_at(pos).row.min(last);
+
+	let mut first = row;
+	let empty = is_empty_line(buf, row);
+	while first > 0 && is_empty_line(buf, first - 1) == empty {
+		first -= 1;
+	}
+
+	let block_end = |r: usize| {
+		let empty = is_empty_line(buf, r);
+		let mut r = r;
+		while r < last && is_empty_line(buf, r + 1) == empty {
+			r += 1;
+		}
+		r
+	};
+
+	let mut to = row;
+	for i in 0..count {
+		let from = if i == 0 { row } else { to + 1 };
+		if from > last {
+			break;
+		}
+		to = block_end(from);
+
+		if around && to < last {
+			to = block_end(to + 1);
+		}
+	}
+
+	if around && !empty && is_empty_line(buf, to) == empty && first > 0 {
+		while first > 0 && is_empty_line(buf, first - 1) {
+			first -= 1;
+		}
+	}
+
+	Some(TextRange::linewise(buf, first, to))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	fn object(
+		text: &str,
+		pos: usize,
+		scope: Scope,
+		around: bool,
+		count: u32,
+	) -> Option<String> {
+		let buf = PieceTable::new(text.into());
+		resolve(&buf, pos, scope, around, count)
+			.map(|r| buf.slice(r.start, r.end))
+	}
+
+	#[test]
+	fn nested_brackets() {
+		let text = "f(a, (b, c), d)";
+
+		assert_eq!(
+			object(text, 6, Scope::Parentheses, false, 1).unwrap(),
+			"b, c"
+		);
+		assert_eq!(
+			object(text, 6, Scope::Parentheses, true, 1).unwrap(),
+			"(b, c)"
+		);
+		assert_eq!(
+			object(text, 6, Scope::Parentheses, false, 2).unwrap(),
+			"a, (b, c), d"
+		);
+		assert_eq!(
+			object(text, 1, Scope::Parentheses, false, 1).unwrap(),
+			"a, (b, c), d"
+		);
+		assert_eq!(object(text, 6, Scope::Parentheses, false, 3), None);
+	}
+
+	#[test]
+	fn multi_line_block_is_linewise() {
+		let text = "fn f() {\n\tlet x;\n\tx\n}\n";
+
+		let buf = PieceTable::new(text.into());
+		let range = resolve(&buf, 11, Scope::Braces, false, 1).unwrap();
+		assert!(range.is_linewise());
+		assert_eq!(buf.slice(range.start, range.end), "\tlet x;\n\tx\n");
+	}
+
+	#[test]
+	fn quotes_skip_escapes() {
+		let text = r#"say "a \"b\" c" now"#;
+
+		assert_eq!(
+			object(text, 8, Scope::DoubleQuote, false, 1).unwrap(),
+			r#"a \"b\" c"#
+		);
+		assert_eq!(
+			object(text, 0, Scope::DoubleQuote, true, 1).unwrap(),
+			r#""a \"b\" c" "#
+		);
+	}
+
+	#[test]
+	fn quotes_across_lines() {
+		let text = "x = \"one\ntwo\nthree\";\ny = \"four\" and \"five\"\n";
+
+		// from the middle line, which has no quote of its own
+		assert_eq!(
+			object(text, 10, Scope::DoubleQuote, false, 1).unwrap(),
+			"one\ntwo\nthree"
+		);
+		assert_eq!(
+			object(text, 4, Scope::DoubleQuote, true, 1).unwrap(),
+			" \"one\ntwo\nthree\""
+		);
+		// a line with a pair of its own keeps to it
+		assert_eq!(
+			object(text, 27, Scope::DoubleQuote, false, 1).unwrap(),
+			"four"
+		);
+	}
+
+	#[test]
+	fn words_and_paragraphs() {
+		let text = "one two  three\n\nfour\nfive\n\n\nsix";
+
+		assert_eq!(object(text, 5, Scope::Word, false, 1).unwrap(), "two");
+		assert_eq!(
+			object(text, 5, Scope::Word, false, 3).unwrap(),
+			"two  three"
+		);
+		assert_eq!(object(text, 5, Scope::Word, true, 1).unwrap(), "two  ");
+		assert_eq!(object(text, 10, Scope::Word, true, 1).unwrap(), "  three");
+
+		assert_eq!(
+			object(text, 16, Scope::Paragraph, false, 1).unwrap(),
+			"four\nfive\n"
+		);
+		assert_eq!(
+			object(text, 16, Scope::Paragraph, true, 1).unwrap(),
+			"four\nfive\n\n\n"
+		);
+	}
+}
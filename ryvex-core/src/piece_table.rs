@@ -4,7 +4,11 @@ use alloc::{
 	vec::Vec,
 };
 
-use crate::TextBuffer;
+use crate::{
+	grapheme,
+	regex::Regex,
+	TextBuffer,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RowCol {
@@ -20,33 +24,240 @@ enum BufferKind {
 
 #[derive(Clone, Debug)]
 struct Piece {
-	source: BufferKind,
-	start:  usize,
-	len:    usize,
+	source:   BufferKind,
+	start:    usize,
+	len:      usize,
+	newlines: usize,
+}
+
+/// Pieces are kept in an AVL tree ordered by their position in the text.
+/// Every node caches the byte length and line break count of its subtree, so
+/// offsets and rows are found in `O(log n)` pieces.
+///
+/// Nodes are immutable and shared, an edit only rebuilds the path to the
+/// pieces it touches. This makes a [`Snapshot`] as cheap as cloning the root.
+type Tree = Option<Arc<Node>>;
+
+#[derive(Debug)]
+struct Node {
+	piece:    Piece,
+	left:     Tree,
+	right:    Tree,
+	height:   u8,
+	len:      usize,
+	newlines: usize,
+}
+
+fn height(tree: &Tree) -> u8 {
+	tree.as_ref().map_or(0, |n| n.height)
+}
+
+fn tree_len(tree: &Tree) -> usize {
+	tree.as_ref().map_or(0, |n| n.len)
+}
+
+fn tree_newlines(tree: &Tree) -> usize {
+	tree.as_ref().map_or(0, |n| n.newlines)
+}
+
+fn node(left: Tree, piece: Piece, right: Tree) -> Tree {
+	Some(Arc::new(Node {
+		height: 1 + height(&left).max(height(&right)),
+		len: tree_len(&left) + piece.len + tree_len(&right),
+		newlines: tree_newlines(&left) + piece.newlines + tree_newlines(&right),
+		piece,
+		left,
+		right,
+	}))
+}
+
+/// Builds a node whose subtrees differ in height by at most two, rotating
+/// it back into balance.
+fn balance(left: Tree, piece: Piece, right: Tree) -> Tree {
+	let (hl, hr) = (height(&left), height(&right));
+
+	if hl > hr + 1 {
+		let l = left.expect("left subtree is higher");
+		if height(&l.left) >= height(&l.right) {
+			return node(
+				l.left.clone(),
+				l.piece.clone(),
+				node(l.right.clone(), piece, right),
+			);
+		}
+
+		let lr = l.right.as_ref().expect("inner subtree is higher");
+		return node(
+			node(l.left.clone(), l.piece.clone(), lr.left.clone()),
+			lr.piece.clone(),
+			node(lr.right.clone(), piece, right),
+		);
+	}
+
+	if hr > hl + 1 {
+		let r = right.expect("right subtree is higher");
+		if height(&r.right) >= height(&r.left) {
+			return node(
+				node(left, piece, r.left.clone()),
+				r.piece.clone(),
+				r.right.clone(),
+			);
+		}
+
+		let rl = r.left.as_ref().expect("inner subtree is higher");
+		return node(
+			node(left, piece, rl.left.clone()),
+			rl.piece.clone(),
+			node(rl.right.clone(), r.piece.clone(), r.right.clone()),
+		);
+	}
+
+	node(left, piece, right)
+}
+
+/// Concatenates `left`, `piece` and `right`, whatever their heights.
+fn join(left: Tree, piece: Piece, right: Tree) -> Tree {
+	let (hl, hr) = (height(&left), height(&right));
+
+	if hl > hr + 1 {
+		let l = left.expect("left subtree is higher");
+		balance(
+			l.left.clone(),
+			l.piece.clone(),
+			join(l.right.clone(), piece, right),
+		)
+	} else if hr > hl + 1 {
+		let r = right.expect("right subtree is higher");
+		balance(
+			join(left, piece, r.left.clone()),
+			r.piece.clone(),
+			r.right.clone(),
+		)
+	} else {
+		node(left, piece, right)
+	}
+}
+
+fn concat(left: Tree, right: Tree) -> Tree {
+	match right {
+		None => left,
+		Some(r) => {
+			let (first, rest) = split_first(&r);
+			join(left, first, rest)
+		}
+	}
+}
+
+fn split_first(n: &Arc<Node>) -> (Piece, Tree) {
+	match &n.left {
+		None => (n.piece.clone(), n.right.clone()),
+		Some(l) => {
+			let (first, rest) = split_first(l);
+			(first, join(rest, n.piece.clone(), n.right.clone()))
+		}
+	}
+}
+
+fn split_last(n: &Arc<Node>) -> (Tree, Piece) {
+	match &n.right {
+		None => (n.left.clone(), n.piece.clone()),
+		Some(r) => {
+			let (rest, last) = split_last(r);
+			(join(n.left.clone(), n.piece.clone(), rest), last)
+		}
+	}
 }
 
 #[derive(Debug)]
 pub struct PieceTable {
-	original:    Arc<String>,
-	add:         String,
-	pieces:      Vec<Piece>,
-	line_starts: Vec<usize>,
+	original:       Arc<String>,
+	add:            String,
+	/// Offsets of the line breaks in `original` and `add`, used to count the
+	/// line breaks of any piece without looking at its text.
+	original_lines: Vec<usize>,
+	add_lines:      Vec<usize>,
+	root:           Tree,
+	version:        u64,
+	revision:       u64,
+}
+
+/// The state of a [`PieceTable`] at some point in time.
+///
+/// Both text buffers are append-only and tree nodes are never modified, so a
+/// snapshot only holds on to the root of the tree.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+	root:    Tree,
+	version: u64,
+}
+
+impl Snapshot {
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+}
+
+fn line_breaks(text: &str, base: usize, out: &mut Vec<usize>) {
+	out.extend(
+		text.bytes()
+			.enumerate()
+			.filter(|(_, b)| *b == b'\n')
+			.map(|(i, _)| base + i),
+	);
 }
 
 impl PieceTable {
 	pub fn new(initial: String) -> Self {
-		let mut pt = PieceTable {
-			original:    Arc::new(initial.clone()),
-			add:         String::new(),
-			pieces:      vec![Piece {
-				source: BufferKind::Original,
-				start:  0,
-				len:    initial.len(),
-			}],
-			line_starts: Vec::new(),
+		let mut original_lines = Vec::new();
+		line_breaks(&initial, 0, &mut original_lines);
+
+		let root = if initial.is_empty() {
+			None
+		} else {
+			node(
+				None,
+				Piece {
+					source:   BufferKind::Original,
+					start:    0,
+					len:      initial.len(),
+					newlines: original_lines.len(),
+				},
+				None,
+			)
 		};
-		pt.rebuild_line_index();
-		pt
+
+		PieceTable {
+			original: Arc::new(initial),
+			add: String::new(),
+			original_lines,
+			add_lines: Vec::new(),
+			root,
+			version: 0,
+			revision: 0,
+		}
+	}
+
+	/// Identifies the current content. Every edit yields a new version, and
+	/// restoring a snapshot brings back the version it was taken at.
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			root:    self.root.clone(),
+			version: self.version,
+		}
+	}
+
+	pub fn restore(&mut self, snapshot: &Snapshot) {
+		self.root = snapshot.root.clone();
+		self.version = snapshot.version;
+	}
+
+	fn bump_version(&mut self) {
+		self.revision += 1;
+		self.version = self.revision;
 	}
 
 	fn buffer_slice(&self, piece: &Piece) -> &str {
@@ -58,91 +269,184 @@ impl PieceTable {
 		}
 	}
 
-	fn push_bytes(
+	fn buffer_lines(&self, source: BufferKind) -> &[usize] {
+		match source {
+			BufferKind::Original => &self.original_lines,
+			BufferKind::Add => &self.add_lines,
+		}
+	}
+
+	/// Number of line breaks in the buffer range `start..end` of `source`.
+	fn count_newlines(
 		&self,
-		piece: &Piece,
-		rel_start: usize,
-		rel_end: usize,
-		out: &mut String,
-	) {
-		let slice = &self.buffer_slice(piece)[rel_start..rel_end];
-		out.push_str(slice);
+		source: BufferKind,
+		start: usize,
+		end: usize,
+	) -> usize {
+		let lines = self.buffer_lines(source);
+		lines.partition_point(|&p| p < end) -
+			lines.partition_point(|&p| p < start)
 	}
 
-	fn split_piece(&mut self, idx: usize, offset: usize) {
-		let p = &self.pieces[idx];
-		if offset == 0 || offset >= p.len {
-			return;
+	fn piece(&self, source: BufferKind, start: usize, len: usize) -> Piece {
+		Piece {
+			source,
+			start,
+			len,
+			newlines: self.count_newlines(source, start, start + len),
 		}
-		let right = Piece {
-			source: p.source,
-			start:  p.start + offset,
-			len:    p.len - offset,
-		};
-		self.pieces[idx].len = offset;
-		self.pieces.insert(idx + 1, right);
 	}
 
-	fn locate(&self, idx: usize) -> (usize, usize) {
-		let mut cur = 0;
-		for (i, p) in self.pieces.iter().enumerate() {
-			if idx < cur + p.len {
-				return (i, idx - cur);
-			}
-			cur += p.len;
+	/// Splits `piece` into `..offset` and `offset..`.
+	fn split_piece(&self, piece: &Piece, offset: usize) -> (Piece, Piece) {
+		(
+			self.piece(piece.source, piece.start, offset),
+			self.piece(piece.source, piece.start + offset, piece.len - offset),
+		)
+	}
+
+	/// Splits `tree` into the pieces before and after byte `idx`.
+	fn split(&self, tree: &Tree, idx: usize) -> (Tree, Tree) {
+		let Some(n) = tree else {
+			return (None, None);
+		};
+		let left_len = tree_len(&n.left);
+
+		if idx <= left_len {
+			let (a, b) = self.split(&n.left, idx);
+			(a, join(b, n.piece.clone(), n.right.clone()))
+		} else if idx >= left_len + n.piece.len {
+			let (a, b) = self.split(&n.right, idx - left_len - n.piece.len);
+			(join(n.left.clone(), n.piece.clone(), a), b)
+		} else {
+			let (head, tail) = self.split_piece(&n.piece, idx - left_len);
+			(
+				join(n.left.clone(), head, None),
+				join(None, tail, n.right.clone()),
+			)
 		}
-		let last = self.pieces.len() - 1;
-		(last, self.pieces[last].len)
 	}
 
-	fn rebuild_line_index(&mut self) {
-		self.line_starts.clear();
-		self.line_starts.push(0);
-		let full = self.slice(0, self.len());
-		for (i, c) in full.char_indices() {
-			if c == '\n' {
-				self.line_starts.push(i + 1);
+	/// The piece containing byte `idx` and the offset of `idx` within it.
+	fn locate(&self, idx: usize) -> Option<(&Piece, usize)> {
+		let mut tree = &self.root;
+		let mut idx = idx;
+
+		while let Some(n) = tree {
+			let left_len = tree_len(&n.left);
+			if idx < left_len {
+				tree = &n.left;
+			} else if idx < left_len + n.piece.len {
+				return Some((&n.piece, idx - left_len));
+			} else {
+				idx -= left_len + n.piece.len;
+				tree = &n.right;
 			}
 		}
+		None
 	}
 
-	fn update_line_index_on_insert(&mut self, idx: usize, text: &str) {
-		let delta = text.len();
+	fn collect(
+		&self,
+		tree: &Tree,
+		offset: usize,
+		start: usize,
+		end: usize,
+		out: &mut String,
+	) {
+		let Some(n) = tree else {
+			return;
+		};
+		if offset >= end || offset + n.len <= start {
+			return;
+		}
 
-		let mut new_lines = Vec::new();
-		for (i, c) in text.char_indices() {
-			if c == '\n' {
-				new_lines.push(idx + i + 1);
-			}
+		self.collect(&n.left, offset, start, end, out);
+
+		let piece_start = offset + tree_len(&n.left);
+		let piece_end = piece_start + n.piece.len;
+		if piece_start < end && piece_end > start {
+			let text = self.buffer_slice(&n.piece);
+			out.push_str(
+				&text[start.max(piece_start) - piece_start..
+					end.min(piece_end) - piece_start],
+			);
 		}
 
-		let pos = self.line_starts.partition_point(|&off| off <= idx);
+		self.collect(&n.right, piece_end, start, end, out);
+	}
+
+	/// Number of line breaks before byte `idx`.
+	fn newlines_before(&self, idx: usize) -> usize {
+		let mut tree = &self.root;
+		let mut idx = idx;
+		let mut count = 0;
 
-		self.line_starts.splice(pos..pos, new_lines.iter().cloned());
+		while let Some(n) = tree {
+			let left_len = tree_len(&n.left);
+			if idx < left_len {
+				tree = &n.left;
+				continue;
+			}
+
+			count += tree_newlines(&n.left);
+			let offset = idx - left_len;
+			if offset < n.piece.len {
+				let start = n.piece.start;
+				return count +
+					self.count_newlines(
+						n.piece.source,
+						start,
+						start + offset,
+					);
+			}
 
-		let start_shift = (pos + new_lines.len()).max(1);
-		for off in &mut self.line_starts[start_shift..] {
-			*off += delta;
+			count += n.piece.newlines;
+			idx = offset - n.piece.len;
+			tree = &n.right;
 		}
+		count
 	}
 
-	fn update_line_index_on_delete(&mut self, start: usize, end: usize) {
-		let delta = end - start;
+	/// Byte offset of the line break with index `k`.
+	fn newline_pos(&self, k: usize) -> Option<usize> {
+		let mut tree = &self.root;
+		let mut k = k;
+		let mut offset = 0;
 
-		self.line_starts.retain(|&off| off < start || off >= end);
+		while let Some(n) = tree {
+			let left_newlines = tree_newlines(&n.left);
+			if k < left_newlines {
+				tree = &n.left;
+				continue;
+			}
+
+			k -= left_newlines;
+			offset += tree_len(&n.left);
+			if k < n.piece.newlines {
+				let lines = self.buffer_lines(n.piece.source);
+				let first = lines.partition_point(|&p| p < n.piece.start);
+				return Some(offset + lines[first + k] - n.piece.start);
+			}
 
-		let pos = self.line_starts.partition_point(|&off| off < end);
+			k -= n.piece.newlines;
+			offset += n.piece.len;
+			tree = &n.right;
+		}
+		None
+	}
 
-		let start_shift = pos.max(1);
-		for off in &mut self.line_starts[start_shift..] {
-			*off -= delta;
+	fn line_start(&self, row: usize) -> Option<usize> {
+		match row {
+			0 => Some(0),
+			_ => self.newline_pos(row - 1).map(|p| p + 1),
 		}
 	}
 }
 
 impl TextBuffer for PieceTable {
 	fn len(&self) -> usize {
-		self.pieces.iter().map(|p| p.len).sum()
+		tree_len(&self.root)
 	}
 
 	fn is_empty(&self) -> bool {
@@ -150,112 +454,215 @@ impl TextBuffer for PieceTable {
 	}
 
 	fn char_at(&self, idx: usize) -> Option<char> {
-		if idx >= self.len() {
-			return None;
+		let (piece, off) = self.locate(idx)?;
+		let text = self.buffer_slice(piece);
+
+		if text.is_char_boundary(off) {
+			text[off..].chars().next()
+		} else {
+			None
 		}
-		let (i, off) = self.locate(idx);
-		self.buffer_slice(&self.pieces[i]).chars().nth(off)
 	}
 
 	fn slice(&self, start: usize, end: usize) -> String {
 		assert!(start <= end && end <= self.len());
 		let mut out = String::with_capacity(end - start);
-		let mut cur = 0;
-		for p in &self.pieces {
-			let next = cur + p.len;
-			if next <= start {
-				cur = next;
-				continue;
-			}
-			if cur >= end {
-				break;
-			}
-			let s = start.max(cur) - cur;
-			let e = end.min(next) - cur;
-			self.push_bytes(p, s, e, &mut out);
-			cur = next;
-		}
+		self.collect(&self.root, 0, start, end, &mut out);
 		out
 	}
 
 	fn find(&self, pattern: &str, from: usize) -> Option<usize> {
-		if pattern.is_empty() {
-			return Some(from);
-		}
-		let limit = self.len().saturating_sub(pattern.len());
-		let mut idx = from.min(limit);
-		while idx <= limit {
-			if self.slice(idx, idx + pattern.len()) == pattern {
-				return Some(idx);
-			}
-			idx += 1;
+		Regex::literal(pattern).find_at(self, from).map(|m| m.start)
+	}
+
+	fn chunk_at(&self, idx: usize) -> &str {
+		match self.locate(idx) {
+			Some((piece, off)) => &self.buffer_slice(piece)[off..],
+			None => "",
 		}
-		None
 	}
 
 	fn insert(&mut self, idx: usize, text: &str) {
 		assert!(idx <= self.len());
-
-		let (pi, off) = self.locate(idx);
-		self.split_piece(pi, off);
+		if text.is_empty() {
+			return;
+		}
+		let idx = self.floor_char_boundary(idx);
 
 		let add_start = self.add.len();
 		self.add.push_str(text);
-
-		let new_piece = Piece {
-			source: BufferKind::Add,
-			start:  add_start,
-			len:    text.len(),
+		line_breaks(text, add_start, &mut self.add_lines);
+
+		let (left, right) = self.split(&self.root, idx);
+
+		// typing appends to the add buffer right after the previous insert,
+		// so the piece in front usually just grows
+		let last = left.as_ref().map(split_last);
+		self.root = match last {
+			Some((rest, last))
+				if last.source == BufferKind::Add &&
+					last.start + last.len == add_start =>
+			{
+				let grown = self.piece(
+					BufferKind::Add,
+					last.start,
+					last.len + text.len(),
+				);
+				join(rest, grown, right)
+			}
+			_ => {
+				let piece = self.piece(BufferKind::Add, add_start, text.len());
+				join(left, piece, right)
+			}
 		};
 
-		let at = if off == 0 { pi } else { pi + 1 };
-		self.pieces.insert(at, new_piece);
-		self.update_line_index_on_insert(idx, text);
+		self.bump_version();
 	}
 
 	fn delete(&mut self, start: usize, end: usize) {
 		assert!(start < end && end <= self.len());
+		let start = self.floor_char_boundary(start);
+		let end = self.ceil_char_boundary(end);
 
-		let (si, soff) = self.locate(start);
-		self.split_piece(si, soff);
-
-		let (ei, eoff) = self.locate(end);
-		self.split_piece(ei, eoff);
-
-		let sidx = self.locate(start).0;
-		let (eidx, eoff2) = self.locate(end);
-		let to = eidx + if eoff2 > 0 { 1 } else { 0 };
+		let (left, rest) = self.split(&self.root, start);
+		let (_, right) = self.split(&rest, end - start);
 
-		self.pieces.drain(sidx..to);
-		self.update_line_index_on_delete(start, end);
+		self.root = concat(left, right);
+		self.bump_version();
 	}
 
 	fn rowcol_at(&self, idx: usize) -> RowCol {
-		let row = self
-			.line_starts
-			.partition_point(|&off| off <= idx)
-			.saturating_sub(1);
+		let idx = grapheme::floor_boundary(self, idx);
+		let row = self.newlines_before(idx);
+		let start = self.line_start(row).unwrap_or(0);
 
 		RowCol {
 			row,
-			col: idx - self.line_starts[row],
+			col: grapheme::graphemes(&self.slice(start, idx)).count(),
 		}
 	}
 
 	fn pos_from(&self, rc: RowCol) -> usize {
-		self.line_starts
-			.get(rc.row)
-			.map(|&off| off + rc.col)
-			.unwrap_or(self.len())
+		let Some(start) = self.line_start(rc.row) else {
+			return self.len();
+		};
+		let end = self.newline_pos(rc.row).unwrap_or(self.len());
+
+		let line = self.slice(start, end);
+		let offset: usize =
+			grapheme::graphemes(&line).take(rc.col).map(str::len).sum();
+
+		start + offset
 	}
 
 	fn lines(&self) -> usize {
-		self.line_starts.len()
+		tree_newlines(&self.root) + 1
 	}
 
 	fn line_len(&self, row: usize) -> usize {
-		let start = *self.line_starts.get(row).unwrap_or(&self.len());
-		let end = *self.line_starts.get(row + 1).unwrap_or(&self.len());
-		self.slice(start, end).chars().count()
+		let Some(start) = self.line_start(row) else {
+			return 0;
+		};
+		let end = self.line_start(row + 1).unwrap_or(self.len());
+
+		grapheme::graphemes(&self.slice(start, end)).count()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn text(pt: &PieceTable) -> String {
+		pt.slice(0, pt.len())
+	}
+
+	#[test]
+	fn columns_skip_multibyte_chars() {
+		let mut pt = PieceTable::new("größe\nü".into());
+
+		assert_eq!(pt.rowcol_at(4), RowCol { row: 0, col: 3 });
+		assert_eq!(pt.pos_from(RowCol { row: 0, col: 3 }), 4);
+		assert_eq!(pt.pos_from(RowCol { row: 0, col: 99 }), 7);
+		assert_eq!(pt.pos_from(RowCol { row: 1, col: 1 }), 10);
+		assert_eq!(pt.char_at(3), None);
+		assert_eq!(pt.line_len(0), 6);
+
+		// offsets inside a char never split it
+		pt.insert(3, "x");
+		assert_eq!(text(&pt), "grxöße\nü");
+		pt.delete(4, 5);
+		assert_eq!(text(&pt), "grxße\nü");
+	}
+
+	#[test]
+	fn columns_count_graphemes() {
+		// `e` with a combining acute accent, and a ZWJ sequence of two emoji
+		let pt = PieceTable::new("e\u{301}x\n👩\u{200D}💻y".into());
+
+		assert_eq!(pt.rowcol_at(1), RowCol { row: 0, col: 0 });
+		assert_eq!(pt.rowcol_at(3), RowCol { row: 0, col: 1 });
+		assert_eq!(pt.pos_from(RowCol { row: 0, col: 1 }), 3);
+		assert_eq!(pt.line_len(0), 3);
+
+		assert_eq!(pt.rowcol_at(9), RowCol { row: 1, col: 0 });
+		assert_eq!(pt.rowcol_at(16), RowCol { row: 1, col: 1 });
+		assert_eq!(pt.pos_from(RowCol { row: 1, col: 1 }), 16);
+		assert_eq!(pt.pos_from(RowCol { row: 1, col: 9 }), 17);
+		assert_eq!(pt.line_len(1), 2);
+	}
+
+	#[test]
+	fn many_edits_match_string() {
+		let mut pt = PieceTable::new("one\ntwo\nthree\n".into());
+		let mut expected = String::from("one\ntwo\nthree\n");
+
+		// a simple deterministic sequence of scattered edits
+		let mut seed = 7usize;
+		for i in 0..500 {
+			seed = (seed * 31 + 17) % 1009;
+			let at = seed % (expected.len() + 1);
+			let at = (0..=at)
+				.rev()
+				.find(|&p| expected.is_char_boundary(p))
+				.unwrap();
+
+			if i % 3 == 2 && at < expected.len() {
+				let end = (at + 3).min(expected.len());
+				let end = (end..=expected.len())
+					.find(|&p| expected.is_char_boundary(p))
+					.unwrap();
+				pt.delete(at, end);
+				expected.replace_range(at..end, "");
+			} else {
+				let s = if i % 5 == 0 { "ä\n" } else { "ab" };
+				pt.insert(at, s);
+				expected.insert_str(at, s);
+			}
+		}
+
+		assert_eq!(text(&pt), expected);
+		assert_eq!(pt.lines(), expected.matches('\n').count() + 1);
+
+		let mut offset = 0;
+		for (row, line) in expected.split('\n').enumerate() {
+			assert_eq!(pt.pos_from(RowCol { row, col: 0 }), offset);
+			assert_eq!(pt.rowcol_at(offset), RowCol { row, col: 0 });
+			offset += line.len() + 1;
+		}
+	}
+
+	#[test]
+	fn snapshots_share_structure() {
+		let mut pt = PieceTable::new("abc".into());
+		let before = pt.snapshot();
+
+		pt.insert(1, "xyz");
+		pt.delete(0, 1);
+		assert_eq!(text(&pt), "xyzbc");
+
+		pt.restore(&before);
+		assert_eq!(text(&pt), "abc");
+		assert_eq!(pt.version(), before.version());
 	}
 }
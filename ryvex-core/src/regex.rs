@@ -0,0 +1,857 @@
+//! Regular expressions over a [`TextBuffer`].
+//!
+//! Patterns are compiled to a Thompson NFA and run by a Pike VM, which keeps
+//! every possible match alive at once instead of backtracking. Matching takes
+//! time linear in the text searched, and the text is read piece by piece
+//! through [`TextBuffer::chunk_at`], so searching never copies the document.
+//!
+//! Supported syntax:
+//!
+//! - `.` any char except a line break, `\n`, `\t`, `\r` and `\` to escape
+//! - `[abc]`, `[^a-z]` and the classes `\d`, `\w`, `\s` and their negations
+//!   `\D`, `\W`, `\S`
+//! - `^` and `$` at the start and end of a line, `\<` and `\>` at the start and
+//!   end of a word, `\b` at either
+//! - `(...)` to group and `|` to separate alternatives
+//! - `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, each followed by `?` to match as
+//!   little as possible
+//!
+//! Among matches starting at the same position, the one the leftmost
+//! alternatives and greedy quantifiers prefer wins, as in Perl.
+
+use alloc::{
+	boxed::Box,
+	vec,
+	vec::Vec,
+};
+use core::{
+	fmt::{
+		self,
+		Display,
+	},
+	mem,
+};
+
+use ryvex_target::std::error::Error;
+
+use crate::{
+	grapheme::prev_char_boundary,
+	motion::navigation::{
+		line_end,
+		line_start,
+	},
+	TextBuffer,
+};
+
+/// Upper bound for compiled programs, reached by nesting counted repetitions.
+const MAX_PROGRAM: usize = 1 << 16;
+const MAX_REPEAT: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexError {
+	UnclosedGroup,
+	UnmatchedParen,
+	UnclosedClass,
+	InvalidRange(char, char),
+	NothingToRepeat,
+	InvalidRepetition,
+	TrailingBackslash,
+	TooLarge,
+}
+
+impl Error for RegexError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		None
+	}
+}
+
+impl Display for RegexError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RegexError::UnclosedGroup => f.write_str("unmatched '('"),
+			RegexError::UnmatchedParen => f.write_str("unmatched ')'"),
+			RegexError::UnclosedClass => f.write_str("unmatched '['"),
+			RegexError::InvalidRange(lo, hi) => {
+				write!(f, "invalid range '{lo}-{hi}'")
+			}
+			RegexError::NothingToRepeat => f.write_str("nothing to repeat"),
+			RegexError::InvalidRepetition => {
+				f.write_str("invalid repetition count")
+			}
+			RegexError::TrailingBackslash => f.write_str("trailing '\\'"),
+			RegexError::TooLarge => f.write_str("pattern too large"),
+		}
+	}
+}
+
+pub type Result<T> = core::result::Result<T, RegexError>;
+
+/// Byte range of a match in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+	pub start: usize,
+	pub end:   usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Perl {
+	Digit,
+	Word,
+	Space,
+}
+
+impl Perl {
+	fn matches(self, ch: char) -> bool {
+		match self {
+			Perl::Digit => ch.is_ascii_digit(),
+			Perl::Word => is_word(ch),
+			Perl::Space => ch.is_whitespace(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClassItem {
+	Range(char, char),
+	Perl(Perl, bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Class {
+	negated: bool,
+	items:   Vec<ClassItem>,
+}
+
+impl Class {
+	fn perl(perl: Perl, negated: bool) -> Self {
+		Self {
+			negated,
+			items: vec![ClassItem::Perl(perl, false)],
+		}
+	}
+
+	fn matches(&self, ch: char) -> bool {
+		let found = self.items.iter().any(|item| match *item {
+			ClassItem::Range(lo, hi) => (lo..=hi).contains(&ch),
+			ClassItem::Perl(perl, negated) => perl.matches(ch) != negated,
+		});
+		found != self.negated
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assertion {
+	LineStart,
+	LineEnd,
+	WordStart,
+	WordEnd,
+	WordBoundary,
+}
+
+fn is_word(ch: char) -> bool {
+	ch.is_alphanumeric() || ch == '_'
+}
+
+impl Assertion {
+	fn holds(self, prev: Option<char>, next: Option<char>) -> bool {
+		let word_before = prev.is_some_and(is_word);
+		let word_after = next.is_some_and(is_word);
+
+		match self {
+			Assertion::LineStart => prev.is_none_or(|c| c == '\n'),
+			Assertion::LineEnd => next.is_none_or(|c| c == '\n'),
+			Assertion::WordStart => !word_before && word_after,
+			Assertion::WordEnd => word_before && !word_after,
+			Assertion::WordBoundary => word_before != word_after,
+		}
+	}
+}
+
+#[derive(Debug)]
+enum Node {
+	Empty,
+	Char(char),
+	Any,
+	Class(Class),
+	Assert(Assertion),
+	Concat(Vec<Node>),
+	Alt(Vec<Node>),
+	Repeat {
+		node:   Box<Node>,
+		min:    u32,
+		max:    Option<u32>,
+		greedy: bool,
+	},
+}
+
+struct Parser<'a> {
+	chars: core::iter::Peekable<core::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+	fn eat(&mut self, ch: char) -> bool {
+		self.chars.next_if_eq(&ch).is_some()
+	}
+
+	fn alternation(&mut self) -> Result<Node> {
+		let mut branches = vec![self.concat()?];
+		while self.eat('|') {
+			branches.push(self.concat()?);
+		}
+
+		Ok(match branches.len() {
+			1 => branches.pop().unwrap_or(Node::Empty),
+			_ => Node::Alt(branches),
+		})
+	}
+
+	fn concat(&mut self) -> Result<Node> {
+		let mut items = Vec::new();
+		while let Some(&ch) = self.chars.peek() {
+			if ch == '|' || ch == ')' {
+				break;
+			}
+			let atom = self.atom()?;
+			items.push(self.quantified(atom)?);
+		}
+
+		Ok(match items.len() {
+			0 => Node::Empty,
+			1 => items.pop().unwrap_or(Node::Empty),
+			_ => Node::Concat(items),
+		})
+	}
+
+	fn atom(&mut self) -> Result<Node> {
+		let Some(ch) = self.chars.next() else {
+			return Ok(Node::Empty);
+		};
+
+		Ok(match ch {
+			'(' => {
+				let inner = self.alternation()?;
+				if !self.eat(')') {
+					return Err(RegexError::UnclosedGroup);
+				}
+				inner
+			}
+			'.' => Node::Any,
+			'^' => Node::Assert(Assertion::LineStart),
+			'$' => Node::Assert(Assertion::LineEnd),
+			'[' => Node::Class(self.class()?),
+			'*' | '+' | '?' => return Err(RegexError::NothingToRepeat),
+			'\\' => self.escape()?,
+			ch => Node::Char(ch),
+		})
+	}
+
+	fn escape(&mut self) -> Result<Node> {
+		let ch = self.chars.next().ok_or(RegexError::TrailingBackslash)?;
+
+		Ok(match ch {
+			'<' => Node::Assert(Assertion::WordStart),
+			'>' => Node::Assert(Assertion::WordEnd),
+			'b' => Node::Assert(Assertion::WordBoundary),
+			'd' => Node::Class(Class::perl(Perl::Digit, false)),
+			'D' => Node::Class(Class::perl(Perl::Digit, true)),
+			'w' => Node::Class(Class::perl(Perl::Word, false)),
+			'W' => Node::Class(Class::perl(Perl::Word, true)),
+			's' => Node::Class(Class::perl(Perl::Space, false)),
+			'S' => Node::Class(Class::perl(Perl::Space, true)),
+			ch => Node::Char(unescape(ch)),
+		})
+	}
+
+	fn class(&mut self) -> Result<Class> {
+		let negated = self.eat('^');
+		let mut items = Vec::new();
+		let mut first = true;
+
+		loop {
+			let ch = self.chars.next().ok_or(RegexError::UnclosedClass)?;
+			if ch == ']' && !first {
+				break;
+			}
+			first = false;
+
+			let lo = match ch {
+				'\\' => {
+					let esc =
+						self.chars.next().ok_or(RegexError::UnclosedClass)?;
+					let perl = match esc.to_ascii_lowercase() {
+						'd' => Some(Perl::Digit),
+						'w' => Some(Perl::Word),
+						's' => Some(Perl::Space),
+						_ => None,
+					};
+					if let Some(perl) = perl {
+						items.push(ClassItem::Perl(
+							perl,
+							esc.is_ascii_uppercase(),
+						));
+						continue;
+					}
+					unescape(esc)
+				}
+				ch => ch,
+			};
+
+			// a '-' right before ']' is literal
+			let is_range = self.chars.peek() == Some(&'-') && {
+				let mut ahead = self.chars.clone();
+				ahead.next();
+				ahead.peek().is_some_and(|&c| c != ']')
+			};
+			if !is_range {
+				items.push(ClassItem::Range(lo, lo));
+				continue;
+			}
+
+			self.chars.next();
+			let hi = match self.chars.next() {
+				Some('\\') => unescape(
+					self.chars.next().ok_or(RegexError::UnclosedClass)?,
+				),
+				Some(ch) => ch,
+				None => return Err(RegexError::UnclosedClass),
+			};
+			if hi < lo {
+				return Err(RegexError::InvalidRange(lo, hi));
+			}
+			items.push(ClassItem::Range(lo, hi));
+		}
+
+		Ok(Class { negated, items })
+	}
+
+	fn quantified(&mut self, atom: Node) -> Result<Node> {
+		let mut node = atom;
+
+		loop {
+			let bounds = match self.chars.peek().copied() {
+				Some('*') => (0, None),
+				Some('+') => (1, None),
+				Some('?') => (0, Some(1)),
+				Some('{') if self.counted_ahead() => {
+					self.chars.next();
+					let bounds = self.counts()?;
+					node = self.repeat(node, bounds)?;
+					continue;
+				}
+				_ => return Ok(node),
+			};
+			self.chars.next();
+			node = self.repeat(node, bounds)?;
+		}
+	}
+
+	fn repeat(
+		&mut self,
+		node: Node,
+		(min, max): (u32, Option<u32>),
+	) -> Result<Node> {
+		if matches!(node, Node::Empty | Node::Assert(_)) {
+			return Err(RegexError::NothingToRepeat);
+		}
+
+		Ok(Node::Repeat {
+			node: Box::new(node),
+			min,
+			max,
+			greedy: !self.eat('?'),
+		})
+	}
+
+	/// Whether a `{` starts a counted repetition rather than being literal.
+	fn counted_ahead(&self) -> bool {
+		let mut ahead = self.chars.clone();
+		ahead.next();
+		ahead.peek().is_some_and(|c| c.is_ascii_digit())
+	}
+
+	fn number(&mut self) -> Result<u32> {
+		let mut n: u32 = 0;
+		let mut digits = 0;
+		while let Some(d) = self.chars.peek().and_then(|c| c.to_digit(10)) {
+			self.chars.next();
+			n = n.saturating_mul(10).saturating_add(d);
+			digits += 1;
+		}
+
+		if digits == 0 || n > MAX_REPEAT {
+			return Err(RegexError::InvalidRepetition);
+		}
+		Ok(n)
+	}
+
+	/// Parses the rest of `{n}`, `{n,}` or `{n,m}`.
+	fn counts(&mut self) -> Result<(u32, Option<u32>)> {
+		let min = self.number()?;
+		let max = if self.eat(',') {
+			match self.chars.peek() {
+				Some('}') => None,
+				_ => Some(self.number()?),
+			}
+		} else {
+			Some(min)
+		};
+
+		if !self.eat('}') || max.is_some_and(|max| max < min) {
+			return Err(RegexError::InvalidRepetition);
+		}
+		Ok((min, max))
+	}
+}
+
+fn unescape(ch: char) -> char {
+	match ch {
+		'n' => '\n',
+		't' => '\t',
+		'r' => '\r',
+		ch => ch,
+	}
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+	Char(char),
+	Any,
+	Class(Class),
+	Assert(Assertion),
+	/// Continue at both targets, preferring the first.
+	Split(usize, usize),
+	Jmp(usize),
+	Accept,
+}
+
+#[derive(Default)]
+struct Compiler {
+	prog: Vec<Inst>,
+}
+
+impl Compiler {
+	fn push(&mut self, inst: Inst) -> Result<usize> {
+		if self.prog.len() >= MAX_PROGRAM {
+			return Err(RegexError::TooLarge);
+		}
+		self.prog.push(inst);
+		Ok(self.prog.len() - 1)
+	}
+
+	fn emit(&mut self, node: &Node) -> Result<()> {
+		match node {
+			Node::Empty => {}
+			Node::Char(ch) => {
+				self.push(Inst::Char(*ch))?;
+			}
+			Node::Any => {
+				self.push(Inst::Any)?;
+			}
+			Node::Class(class) => {
+				self.push(Inst::Class(class.clone()))?;
+			}
+			Node::Assert(assertion) => {
+				self.push(Inst::Assert(*assertion))?;
+			}
+			Node::Concat(nodes) => {
+				for node in nodes {
+					self.emit(node)?;
+				}
+			}
+			Node::Alt(branches) => {
+				let mut jumps = Vec::new();
+				for (i, branch) in branches.iter().enumerate() {
+					if i + 1 == branches.len() {
+						self.emit(branch)?;
+						break;
+					}
+
+					let split = self.push(Inst::Split(0, 0))?;
+					self.emit(branch)?;
+					jumps.push(self.push(Inst::Jmp(0))?);
+					self.prog[split] = Inst::Split(split + 1, self.prog.len());
+				}
+
+				let end = self.prog.len();
+				for jump in jumps {
+					self.prog[jump] = Inst::Jmp(end);
+				}
+			}
+			Node::Repeat {
+				node,
+				min,
+				max,
+				greedy,
+			} => {
+				for _ in 0..*min {
+					self.emit(node)?;
+				}
+
+				let mut splits = Vec::new();
+				match max {
+					None => {
+						let split = self.push(Inst::Split(0, 0))?;
+						self.emit(node)?;
+						self.push(Inst::Jmp(split))?;
+						splits.push(split);
+					}
+					Some(max) => {
+						for _ in *min..*max {
+							splits.push(self.push(Inst::Split(0, 0))?);
+							self.emit(node)?;
+						}
+					}
+				}
+
+				let end = self.prog.len();
+				for split in splits {
+					self.prog[split] = if *greedy {
+						Inst::Split(split + 1, end)
+					} else {
+						Inst::Split(end, split + 1)
+					};
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Threads of the VM, kept in priority order without duplicates.
+struct Threads {
+	/// Instruction and match start of every thread.
+	dense:  Vec<(usize, usize)>,
+	sparse: Vec<usize>,
+}
+
+impl Threads {
+	fn new(len: usize) -> Self {
+		Self {
+			dense:  Vec::with_capacity(len),
+			sparse: vec![0; len],
+		}
+	}
+
+	fn contains(&self, pc: usize) -> bool {
+		let idx = self.sparse[pc];
+		idx < self.dense.len() && self.dense[idx].0 == pc
+	}
+
+	fn insert(&mut self, pc: usize, start: usize) {
+		self.sparse[pc] = self.dense.len();
+		self.dense.push((pc, start));
+	}
+
+	fn clear(&mut self) {
+		self.dense.clear();
+	}
+}
+
+/// Reads a buffer char by char, one chunk at a time.
+struct Input<'a, B: TextBuffer> {
+	buf:   &'a B,
+	pos:   usize,
+	chars: core::str::Chars<'a>,
+}
+
+impl<'a, B: TextBuffer> Input<'a, B> {
+	fn new(buf: &'a B, pos: usize) -> Self {
+		Self {
+			buf,
+			pos,
+			chars: "".chars(),
+		}
+	}
+}
+
+impl<B: TextBuffer> Iterator for Input<'_, B> {
+	type Item = char;
+
+	fn next(&mut self) -> Option<char> {
+		loop {
+			if let Some(ch) = self.chars.next() {
+				self.pos += ch.len_utf8();
+				return Some(ch);
+			}
+
+			let chunk = self.buf.chunk_at(self.pos);
+			if chunk.is_empty() {
+				return None;
+			}
+			self.chars = chunk.chars();
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+	prog: Vec<Inst>,
+}
+
+impl Regex {
+	pub fn new(pattern: &str) -> Result<Self> {
+		let mut parser = Parser {
+			chars: pattern.chars().peekable(),
+		};
+		let node = parser.alternation()?;
+		if parser.chars.next().is_some() {
+			return Err(RegexError::UnmatchedParen);
+		}
+
+		let mut compiler = Compiler::default();
+		compiler.emit(&node)?;
+		compiler.push(Inst::Accept)?;
+
+		Ok(Self {
+			prog: compiler.prog,
+		})
+	}
+
+	/// A regex matching `text` literally.
+	pub fn literal(text: &str) -> Self {
+		let mut prog: Vec<Inst> = text.chars().map(Inst::Char).collect();
+		prog.push(Inst::Accept);
+
+		Self { prog }
+	}
+
+	/// First match starting at or after `from`.
+	pub fn find_at<B: TextBuffer>(
+		&self,
+		buf: &B,
+		from: usize,
+	) -> Option<Match> {
+		self.search(buf, from, buf.len())
+	}
+
+	/// Last match starting before `before`, or anywhere for `usize::MAX`.
+	/// Lines are searched one at a time from the end, so a match may still
+	/// reach past `before`.
+	pub fn rfind_at<B: TextBuffer>(
+		&self,
+		buf: &B,
+		before: usize,
+	) -> Option<Match> {
+		let mut row = buf.rowcol_at(before.min(buf.len())).row;
+
+		loop {
+			let start = line_start(buf, row);
+			let end = line_end(buf, row);
+
+			// every position up to and including the line break may start one
+			let last = if before > end {
+				Some(end)
+			} else if before > start {
+				Some(prev_char_boundary(buf, before))
+			} else {
+				None
+			};
+
+			let mut found = None;
+			let mut from = start;
+			while let Some(m) =
+				last.and_then(|last| self.search(buf, from, last))
+			{
+				found = Some(m);
+				from = match buf.char_at(m.start) {
+					Some(ch) => m.start + ch.len_utf8(),
+					None => break,
+				};
+			}
+			if found.is_some() {
+				return found;
+			}
+
+			if row == 0 {
+				return None;
+			}
+			row -= 1;
+		}
+	}
+
+	/// The next match after `pos`, wrapping around to the start of the
+	/// buffer.
+	pub fn next_match<B: TextBuffer>(
+		&self,
+		buf: &B,
+		pos: usize,
+	) -> Option<Match> {
+		let after = match buf.char_at(pos) {
+			Some(ch) => pos + ch.len_utf8(),
+			None => buf.len(),
+		};
+
+		self.search(buf, after, buf.len())
+			.or_else(|| self.search(buf, 0, pos))
+	}
+
+	/// The previous match before `pos`, wrapping around to the end of the
+	/// buffer.
+	pub fn prev_match<B: TextBuffer>(
+		&self,
+		buf: &B,
+		pos: usize,
+	) -> Option<Match> {
+		self.rfind_at(buf, pos)
+			.or_else(|| self.rfind_at(buf, usize::MAX))
+	}
+
+	/// Runs the VM from `from`, starting new attempts up to and including
+	/// `last_start`. Returns the leftmost match, preferring the alternatives
+	/// and quantifiers that come first.
+	fn search<B: TextBuffer>(
+		&self,
+		buf: &B,
+		from: usize,
+		last_start: usize,
+	) -> Option<Match> {
+		let from = buf.ceil_char_boundary(from);
+		if from > last_start {
+			return None;
+		}
+
+		let mut input = Input::new(buf, from);
+		let mut prev = (from > 0)
+			.then(|| buf.char_at(prev_char_boundary(buf, from)))
+			.flatten();
+		let mut cur = input.next();
+		let mut pos = from;
+
+		let mut clist = Threads::new(self.prog.len());
+		let mut nlist = Threads::new(self.prog.len());
+		let mut stack = Vec::new();
+		let mut matched = None;
+
+		loop {
+			if matched.is_none() && pos <= last_start {
+				self.add(&mut clist, &mut stack, 0, pos, prev, cur);
+			}
+			if clist.dense.is_empty() &&
+				(matched.is_some() || pos >= last_start)
+			{
+				break;
+			}
+
+			let next = cur.and_then(|_| input.next());
+			for i in 0..clist.dense.len() {
+				let (pc, start) = clist.dense[i];
+				let step = match (&self.prog[pc], cur) {
+					(Inst::Accept, _) => {
+						matched = Some(Match { start, end: pos });
+						// threads after this one have lower priority
+						break;
+					}
+					(Inst::Char(c), Some(ch)) => *c == ch,
+					(Inst::Any, Some(ch)) => ch != '\n',
+					(Inst::Class(class), Some(ch)) => class.matches(ch),
+					_ => false,
+				};
+				if step {
+					self.add(&mut nlist, &mut stack, pc + 1, start, cur, next);
+				}
+			}
+
+			let Some(ch) = cur else {
+				break;
+			};
+			mem::swap(&mut clist, &mut nlist);
+			nlist.clear();
+			pos += ch.len_utf8();
+			prev = cur;
+			cur = next;
+		}
+
+		matched
+	}
+
+	/// Adds the thread at `pc` along with everything reachable from it
+	/// without consuming input. `prev` and `next` surround the current
+	/// position for assertions.
+	fn add(
+		&self,
+		list: &mut Threads,
+		stack: &mut Vec<usize>,
+		pc: usize,
+		start: usize,
+		prev: Option<char>,
+		next: Option<char>,
+	) {
+		stack.push(pc);
+		while let Some(pc) = stack.pop() {
+			if list.contains(pc) {
+				continue;
+			}
+			list.insert(pc, start);
+
+			match &self.prog[pc] {
+				Inst::Jmp(target) => stack.push(*target),
+				Inst::Split(first, second) => {
+					stack.push(*second);
+					stack.push(*first);
+				}
+				Inst::Assert(assertion) if assertion.holds(prev, next) => {
+					stack.push(pc + 1)
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+		let buf = PieceTable::new(text.into());
+		let m = Regex::new(pattern).unwrap().find_at(&buf, 0)?;
+		Some((m.start, m.end))
+	}
+
+	#[test]
+	fn syntax() {
+		assert_eq!(find("b+", "abbbc"), Some((1, 4)));
+		assert_eq!(find("b+?", "abbbc"), Some((1, 2)));
+		assert_eq!(find("(ab|a)c", "xabc"), Some((1, 4)));
+		assert_eq!(find("a{2,3}", "aaaa"), Some((0, 3)));
+		assert_eq!(find("x{2}y", "xxxy"), Some((1, 4)));
+		assert_eq!(find("[^a-c\\d]+", "ab12de"), Some((4, 6)));
+		assert_eq!(find("[]x-]+", "a-]x"), Some((1, 4)));
+		assert_eq!(find("\\<is\\>", "this is"), Some((5, 7)));
+		assert_eq!(find("^b.*$", "ab\nbc\n"), Some((3, 5)));
+		assert_eq!(find("\\w+é", "  café"), Some((2, 7)));
+		assert_eq!(find("a{", "a{"), Some((0, 2)));
+		assert_eq!(find("z*", "abc"), Some((0, 0)));
+		assert_eq!(find("q", "abc"), None);
+
+		assert_eq!(Regex::new("(a").unwrap_err(), RegexError::UnclosedGroup);
+		assert_eq!(Regex::new("a)").unwrap_err(), RegexError::UnmatchedParen);
+		assert_eq!(Regex::new("*a").unwrap_err(), RegexError::NothingToRepeat);
+		assert_eq!(
+			Regex::new("[b-a]").unwrap_err(),
+			RegexError::InvalidRange('b', 'a')
+		);
+	}
+
+	#[test]
+	fn search_across_pieces() {
+		let mut buf = PieceTable::new("foo bar\nbaz foo\n".into());
+		buf.insert(4, "fo");
+		buf.insert(6, "o ");
+		assert_eq!(buf.slice(0, buf.len()), "foo foo bar\nbaz foo\n");
+
+		let re = Regex::new("fo+").unwrap();
+		let starts = |m: Option<Match>| m.map(|m| m.start);
+
+		assert_eq!(starts(re.find_at(&buf, 1)), Some(4));
+		assert_eq!(starts(re.rfind_at(&buf, buf.len())), Some(16));
+		assert_eq!(starts(re.rfind_at(&buf, 16)), Some(4));
+		assert_eq!(starts(re.rfind_at(&buf, 4)), Some(0));
+		assert_eq!(starts(re.rfind_at(&buf, 0)), None);
+
+		assert_eq!(starts(re.next_match(&buf, 16)), Some(0));
+		assert_eq!(starts(re.prev_match(&buf, 0)), Some(16));
+		assert_eq!(buf.find("baz", 0), Some(12));
+	}
+}
@@ -0,0 +1,316 @@
+use alloc::string::String;
+
+use crate::{
+	motion::{
+		navigation::{
+			first_non_blank,
+			line_end,
+			line_start,
+			next_char,
+			prev_char,
+		},
+		operator::RangeKind,
+	},
+	Cursor,
+	TextBuffer,
+};
+
+/// Text stored in a register, together with how it was taken from the
+/// buffer. Linewise text always ends in a line break.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+	pub text: String,
+	pub kind: RangeKind,
+}
+
+impl Register {
+	pub fn new(text: String, kind: RangeKind) -> Self {
+		let mut text = text;
+		if kind == RangeKind::Linewise && !text.ends_with('\n') {
+			text.push('\n');
+		}
+
+		Self { text, kind }
+	}
+
+	pub fn charwise(text: String) -> Self {
+		Self::new(text, RangeKind::Charwise)
+	}
+
+	pub fn linewise(text: String) -> Self {
+		Self::new(text, RangeKind::Linewise)
+	}
+
+	/// Appends `other` the way Vim does for `"A`: appending lines to
+	/// characters starts a new line and turns the register linewise.
+	fn append(&mut self, other: Register) {
+		if self.kind == RangeKind::Charwise && other.kind == RangeKind::Linewise
+		{
+			self.text.push('\n');
+			self.kind = RangeKind::Linewise;
+		}
+
+		self.text.push_str(&other.text);
+		if self.kind == RangeKind::Linewise && !self.text.ends_with('\n') {
+			self.text.push('\n');
+		}
+	}
+}
+
+pub const UNNAMED: char = '"';
+pub const BLACK_HOLE: char = '_';
+pub const SMALL_DELETE: char = '-';
+pub const LAST_SEARCH: char = '/';
+pub const CLIPBOARD: char = '+';
+pub const SELECTION: char = '*';
+
+/// Whether `name` can follow `"` to select a register.
+pub fn is_valid_name(name: char) -> bool {
+	name.is_ascii_alphanumeric() ||
+		matches!(name, UNNAMED | BLACK_HOLE | SMALL_DELETE | LAST_SEARCH) ||
+		matches!(name, CLIPBOARD | SELECTION)
+}
+
+/// The register store shared by all documents.
+///
+/// - `"` holds whatever was yanked or deleted last.
+/// - `0` holds the last yank, `1` the last delete spanning lines, shifting
+///   older deletes up to `9`. Smaller deletes go to `-` instead.
+/// - `a`-`z` are written by naming them, `A`-`Z` append to them.
+/// - `_` swallows everything written to it.
+/// - `/` holds the last search pattern.
+/// - `+` and `*` stand for the system clipboard and primary selection. They
+///   hold what was last copied to or pasted from there; the editor keeps the
+///   system in sync with them.
+#[derive(Debug, Default)]
+pub struct Registers {
+	unnamed:      Option<Register>,
+	numbered:     [Option<Register>; 10],
+	named:        [Option<Register>; 26],
+	small_delete: Option<Register>,
+	search:       Option<Register>,
+	clipboard:    Option<Register>,
+	selection:    Option<Register>,
+}
+
+impl Registers {
+	pub fn get(&self, name: char) -> Option<&Register> {
+		match name {
+			UNNAMED => self.unnamed.as_ref(),
+			SMALL_DELETE => self.small_delete.as_ref(),
+			LAST_SEARCH => self.search.as_ref(),
+			CLIPBOARD => self.clipboard.as_ref(),
+			SELECTION => self.selection.as_ref(),
+			'0'..='9' => self.numbered[name as usize - '0' as usize].as_ref(),
+			'a'..='z' | 'A'..='Z' => {
+				self.named[Self::named_index(name)].as_ref()
+			}
+			_ => None,
+		}
+	}
+
+	fn named_index(name: char) -> usize {
+		(name.to_ascii_lowercase() as u8 - b'a') as usize
+	}
+
+	/// Stores `register` in the named register `name`. Returns `false` if
+	/// `name` is not a writable register.
+	fn write_named(&mut self, name: char, register: Register) -> bool {
+		match name {
+			'a'..='z' => self.named[Self::named_index(name)] = Some(register),
+			'A'..='Z' => {
+				let slot = &mut self.named[Self::named_index(name)];
+				match slot {
+					Some(existing) => existing.append(register),
+					None => *slot = Some(register),
+				}
+			}
+			CLIPBOARD => self.clipboard = Some(register),
+			SELECTION => self.selection = Some(register),
+			_ => return false,
+		}
+		true
+	}
+
+	/// Records yanked text in `name`, or in `0` if no register was named.
+	pub fn yank(&mut self, name: Option<char>, register: Register) {
+		match name {
+			Some(BLACK_HOLE) => {}
+			None | Some(UNNAMED) => {
+				self.numbered[0] = Some(register.clone());
+				self.unnamed = Some(register);
+			}
+			Some(name) => {
+				if self.write_named(name, register) {
+					self.unnamed = self.get(name).cloned();
+				}
+			}
+		}
+	}
+
+	/// Records deleted text in `name`, or in `1` or `-` if no register was
+	/// named.
+	pub fn delete(&mut self, name: Option<char>, register: Register) {
+		match name {
+			Some(BLACK_HOLE) => {}
+			None | Some(UNNAMED) => {
+				if register.kind == RangeKind::Linewise ||
+					register.text.contains('\n')
+				{
+					self.numbered[1..].rotate_right(1);
+					self.numbered[1] = Some(register.clone());
+				} else {
+					self.small_delete = Some(register.clone());
+				}
+				self.unnamed = Some(register);
+			}
+			Some(name) => {
+				if self.write_named(name, register) {
+					self.unnamed = self.get(name).cloned();
+				}
+			}
+		}
+	}
+
+	pub fn set_search(&mut self, pattern: &str) {
+		self.search = Some(Register::charwise(pattern.into()));
+	}
+
+	/// Replaces `+` or `*` with what was pasted from the system, leaving
+	/// `"` alone.
+	pub fn set_system(&mut self, name: char, register: Register) {
+		match name {
+			CLIPBOARD => self.clipboard = Some(register),
+			SELECTION => self.selection = Some(register),
+			_ => {}
+		}
+	}
+}
+
+/// Puts `register` `count` times after the cursor (`p`) or before it (`P`).
+/// Lines go below or above the cursor line.
+pub fn put<B: TextBuffer>(
+	buf: &mut B,
+	cursor: &mut Cursor,
+	register: &Register,
+	before: bool,
+	count: u32,
+) {
+	let text = register.text.repeat(count.max(1) as usize);
+	if text.is_empty() {
+		return;
+	}
+
+	let pos = cursor.pos.min(buf.len());
+	let row = buf.rowcol_at(pos).row;
+
+	match register.kind {
+		RangeKind::Linewise => {
+			let end = line_end(buf, row);
+
+			if before {
+				buf.insert(line_start(buf, row), &text);
+				cursor.pos = first_non_blank(buf, row);
+			} else if end == buf.len() {
+				// the last line has no line break to insert after
+				let mut lines = String::from("\n");
+				lines.push_str(text.strip_suffix('\n').unwrap_or(&text));
+				buf.insert(end, &lines);
+				cursor.pos = first_non_blank(buf, row + 1);
+			} else {
+				buf.insert(end + 1, &text);
+				cursor.pos = first_non_blank(buf, row + 1);
+			}
+		}
+		RangeKind::Charwise => {
+			let at = if before || buf.char_at(pos).is_none_or(|c| c == '\n') {
+				pos
+			} else {
+				next_char(buf, pos)
+			};
+
+			buf.insert(at, &text);
+			cursor.pos = if text.contains('\n') {
+				at
+			} else {
+				prev_char(buf, at + text.len())
+			};
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	#[test]
+	fn numbered_shift_and_append() {
+		let mut regs = Registers::default();
+
+		regs.delete(None, Register::linewise("one".into()));
+		regs.delete(None, Register::linewise("two\n".into()));
+		regs.delete(None, Register::charwise("x".into()));
+		regs.yank(None, Register::charwise("y".into()));
+
+		assert_eq!(regs.get('1').unwrap().text, "two\n");
+		assert_eq!(regs.get('2').unwrap().text, "one\n");
+		assert_eq!(regs.get('-').unwrap().text, "x");
+		assert_eq!(regs.get('0').unwrap().text, "y");
+		assert_eq!(regs.get('"').unwrap().text, "y");
+
+		regs.yank(Some('a'), Register::charwise("foo".into()));
+		regs.yank(Some('A'), Register::linewise("bar".into()));
+		assert_eq!(
+			regs.get('a'),
+			Some(&Register::linewise("foo\nbar\n".into()))
+		);
+		assert_eq!(regs.get('"'), regs.get('a'));
+
+		regs.delete(Some('_'), Register::charwise("gone".into()));
+		assert_eq!(regs.get('"'), regs.get('a'));
+
+		regs.yank(Some('+'), Register::charwise("copied".into()));
+		assert_eq!(regs.get('"').unwrap().text, "copied");
+		regs.set_system('*', Register::charwise("selected".into()));
+		assert_eq!(regs.get('*').unwrap().text, "selected");
+		assert_eq!(regs.get('"').unwrap().text, "copied");
+	}
+
+	#[test]
+	fn put_lines_and_chars() {
+		let mut buf = PieceTable::new("ab\ncd".into());
+		let mut cursor = Cursor { pos: 0 };
+
+		put(
+			&mut buf,
+			&mut cursor,
+			&Register::charwise("xy".into()),
+			false,
+			2,
+		);
+		assert_eq!(buf.slice(0, buf.len()), "axyxyb\ncd");
+		assert_eq!(cursor.pos, 4);
+
+		cursor.pos = 8;
+		put(
+			&mut buf,
+			&mut cursor,
+			&Register::linewise("  l".into()),
+			false,
+			1,
+		);
+		assert_eq!(buf.slice(0, buf.len()), "axyxyb\ncd\n  l");
+		assert_eq!(cursor.pos, 12);
+
+		put(
+			&mut buf,
+			&mut cursor,
+			&Register::linewise("top".into()),
+			true,
+			1,
+		);
+		assert_eq!(buf.slice(0, buf.len()), "axyxyb\ncd\ntop\n  l");
+		assert_eq!(cursor.pos, 10);
+	}
+}
@@ -0,0 +1,395 @@
+use alloc::{
+	vec,
+	vec::Vec,
+};
+
+use crate::{
+	edit::Edit,
+	grapheme,
+	motion::navigation::{
+		line_end,
+		line_start,
+	},
+	piece_table::RowCol,
+	TextBuffer,
+};
+
+/// A selected range of text. `head` is where the cursor is, `anchor` where
+/// the selection started. Both ends are inclusive, so a selection with
+/// `anchor == head` covers the character under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+	pub anchor: usize,
+	pub head:   usize,
+}
+
+impl Selection {
+	pub fn new(anchor: usize, head: usize) -> Self {
+		Self { anchor, head }
+	}
+
+	pub fn point(pos: usize) -> Self {
+		Self::new(pos, pos)
+	}
+
+	/// Start of the first selected character.
+	pub fn from(&self) -> usize {
+		self.anchor.min(self.head)
+	}
+
+	/// Start of the last selected character.
+	pub fn to(&self) -> usize {
+		self.anchor.max(self.head)
+	}
+
+	/// End of the last selected character.
+	pub fn end<B: TextBuffer>(&self, buf: &B) -> usize {
+		grapheme::next_boundary(buf, self.to())
+	}
+
+	pub fn is_point(&self) -> bool {
+		self.anchor == self.head
+	}
+
+	pub fn map(&self, edit: &Edit) -> Self {
+		Self::new(edit.map(self.anchor), edit.map(self.head))
+	}
+
+	/// Extends the selection over `start..end`, the range a motion from the
+	/// head resolved to. Ranges starting at the head move it forward, ranges
+	/// ending at it move it back, and ranges around it, like text objects,
+	/// replace the selection.
+	pub fn extend<B: TextBuffer>(
+		&self,
+		buf: &B,
+		start: usize,
+		end: usize,
+	) -> Self {
+		let last = grapheme::prev_boundary(buf, end).max(start);
+
+		if start >= self.head {
+			Self::new(self.anchor, last)
+		} else if end <= self.head {
+			Self::new(self.anchor, start)
+		} else {
+			Self::new(start, last)
+		}
+	}
+}
+
+/// The selections of a document, sorted by position and never overlapping.
+/// One of them is the primary selection, which holds the visible cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selections {
+	ranges:  Vec<Selection>,
+	primary: usize,
+}
+
+impl Default for Selections {
+	fn default() -> Self {
+		Self::single(0)
+	}
+}
+
+impl Selections {
+	pub fn single(pos: usize) -> Self {
+		Self {
+			ranges:  vec![Selection::point(pos)],
+			primary: 0,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.ranges.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	pub fn get(&self, idx: usize) -> Selection {
+		self.ranges[idx]
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Selection> {
+		self.ranges.iter()
+	}
+
+	pub fn primary(&self) -> Selection {
+		self.ranges[self.primary]
+	}
+
+	pub fn primary_index(&self) -> usize {
+		self.primary
+	}
+
+	pub fn set_primary(&mut self, idx: usize) {
+		assert!(idx < self.ranges.len(), "primary selection out of bounds");
+		self.primary = idx;
+	}
+
+	/// Replaces the selection at `idx` without restoring the ordering, so
+	/// indices stay valid while selections are edited one by one. Call
+	/// [`Selections::normalize`] afterwards.
+	pub fn set(&mut self, idx: usize, selection: Selection) {
+		self.ranges[idx] = selection;
+	}
+
+	/// Adds `selection` and makes it the primary one.
+	pub fn push(&mut self, selection: Selection) {
+		self.ranges.push(selection);
+		self.primary = self.ranges.len() - 1;
+		self.normalize();
+	}
+
+	/// Drops every selection but the primary one, which shrinks to its head.
+	pub fn collapse(&mut self) {
+		let head = self.primary().head;
+		*self = Self::single(head);
+	}
+
+	/// Shrinks every selection to its head.
+	pub fn reduce_to_heads(&mut self) {
+		for sel in &mut self.ranges {
+			*sel = Selection::point(sel.head);
+		}
+		self.normalize();
+	}
+
+	/// Swaps anchor and head of every selection.
+	pub fn flip(&mut self) {
+		for sel in &mut self.ranges {
+			*sel = Selection::new(sel.head, sel.anchor);
+		}
+	}
+
+	/// Moves every selection but the one at `skip` along with `edit`.
+	pub fn map_except(&mut self, skip: usize, edit: &Edit) {
+		for (idx, sel) in self.ranges.iter_mut().enumerate() {
+			if idx != skip {
+				*sel = sel.map(edit);
+			}
+		}
+	}
+
+	/// Clamps every selection to the buffer.
+	pub fn clamp<B: TextBuffer>(&mut self, buf: &B) {
+		let clamp = |pos: usize| buf.floor_char_boundary(pos.min(buf.len()));
+		for sel in &mut self.ranges {
+			*sel = Selection::new(clamp(sel.anchor), clamp(sel.head));
+		}
+	}
+
+	/// Splits every selection spanning several lines into one selection per
+	/// line. The last part of the primary selection becomes primary.
+	pub fn split_lines<B: TextBuffer>(&mut self, buf: &B) {
+		let mut ranges = Vec::with_capacity(self.ranges.len());
+		let mut primary = 0;
+
+		for (idx, sel) in self.ranges.iter().enumerate() {
+			let (from, to) = (sel.from(), sel.to());
+			let first = buf.rowcol_at(from).row;
+			let last = buf.rowcol_at(to).row;
+
+			for row in first..=last {
+				let start = if row == first {
+					from
+				} else {
+					line_start(buf, row)
+				};
+				let end = if row == last {
+					to
+				} else {
+					let end = line_end(buf, row);
+					grapheme::prev_boundary(buf, end).max(start)
+				};
+				ranges.push(Selection::new(start, end));
+			}
+
+			if idx == self.primary {
+				primary = ranges.len() - 1;
+			}
+		}
+
+		self.ranges = ranges;
+		self.primary = primary;
+		self.normalize();
+	}
+
+	/// Turns every selection into a block: one selection per line, covering
+	/// the columns between anchor and head. Lines too short to reach the
+	/// block are left out. The line holding the primary head becomes
+	/// primary.
+	pub fn to_block<B: TextBuffer>(&mut self, buf: &B) {
+		let mut ranges = Vec::with_capacity(self.ranges.len());
+		let mut primary = None;
+
+		for (idx, sel) in self.ranges.iter().enumerate() {
+			let anchor = buf.rowcol_at(sel.anchor);
+			let head = buf.rowcol_at(sel.head);
+			let (left, right) =
+				(anchor.col.min(head.col), anchor.col.max(head.col));
+			let (top, bottom) =
+				(anchor.row.min(head.row), anchor.row.max(head.row));
+
+			for row in top..=bottom {
+				let width = buf.rowcol_at(line_end(buf, row)).col;
+				if width <= left {
+					continue;
+				}
+
+				let from = buf.pos_from(RowCol { row, col: left });
+				let to = buf.pos_from(RowCol {
+					row,
+					col: right.min(width - 1),
+				});
+				if head.col < anchor.col {
+					ranges.push(Selection::new(to, from));
+				} else {
+					ranges.push(Selection::new(from, to));
+				}
+
+				if idx == self.primary && (primary.is_none() || row == head.row)
+				{
+					primary = Some(ranges.len() - 1);
+				}
+			}
+		}
+
+		// a block entirely past the end of its lines keeps the primary head
+		if ranges.is_empty() {
+			*self = Self::single(self.primary().head);
+			return;
+		}
+
+		self.ranges = ranges;
+		self.primary = primary.unwrap_or(0);
+		self.normalize();
+	}
+
+	/// Sorts the selections and merges those that overlap, keeping track of
+	/// the primary one.
+	pub fn normalize(&mut self) {
+		let primary = self.ranges[self.primary];
+		self.ranges.sort_by_key(|sel| (sel.from(), sel.to()));
+
+		let mut merged: Vec<Selection> = Vec::with_capacity(self.ranges.len());
+		let mut primary_idx = 0;
+
+		for sel in self.ranges.drain(..) {
+			let is_primary = sel == primary;
+
+			match merged.last_mut() {
+				Some(last) if sel.from() <= last.to() => {
+					let from = last.from().min(sel.from());
+					let to = last.to().max(sel.to());
+					// keep the direction of whichever selection is primary
+					let forward = if is_primary {
+						sel.head >= sel.anchor
+					} else {
+						last.head >= last.anchor
+					};
+					*last = if forward {
+						Selection::new(from, to)
+					} else {
+						Selection::new(to, from)
+					};
+				}
+				_ => merged.push(sel),
+			}
+
+			if is_primary {
+				primary_idx = merged.len() - 1;
+			}
+		}
+
+		self.ranges = merged;
+		self.primary = primary_idx;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::piece_table::PieceTable;
+
+	#[test]
+	fn merge_map_and_split() {
+		let buf = PieceTable::new("ab\ncd\nef".into());
+		let mut sels = Selections::single(0);
+
+		sels.push(Selection::new(4, 7));
+		sels.push(Selection::point(5));
+		assert_eq!(sels.len(), 2);
+		assert_eq!(sels.primary(), Selection::new(4, 7));
+
+		sels.map_except(usize::MAX, &Edit {
+			start:    0,
+			removed:  0,
+			inserted: 2,
+		});
+		assert_eq!(sels.get(0), Selection::point(2));
+		assert_eq!(sels.primary(), Selection::new(6, 9));
+
+		let mut sels = Selections::single(1);
+		sels.set(0, Selection::new(1, 7));
+		sels.split_lines(&buf);
+		let parts: Vec<Selection> = sels.iter().copied().collect();
+		assert_eq!(parts, [
+			Selection::new(1, 1),
+			Selection::new(3, 4),
+			Selection::new(6, 7)
+		]);
+		assert_eq!(sels.primary_index(), 2);
+
+		sels.collapse();
+		assert_eq!(sels, Selections::single(7));
+	}
+
+	#[test]
+	fn merge_after_edit() {
+		let mut sels = Selections::single(8);
+		sels.push(Selection::new(0, 1));
+		sels.push(Selection::new(5, 3));
+		assert_eq!(sels.primary_index(), 1);
+
+		// deleting `1..4` pulls the primary back onto the first selection
+		sels.map_except(usize::MAX, &Edit {
+			start:    1,
+			removed:  3,
+			inserted: 0,
+		});
+		sels.normalize();
+		let parts: Vec<Selection> = sels.iter().copied().collect();
+		assert_eq!(parts, [Selection::new(2, 0), Selection::point(5)]);
+		assert_eq!(sels.primary_index(), 0);
+
+		// a point swallowed by the primary keeps the primary's direction
+		sels.map_except(usize::MAX, &Edit {
+			start:    2,
+			removed:  4,
+			inserted: 0,
+		});
+		sels.normalize();
+		assert_eq!(sels.len(), 1);
+		assert_eq!(sels.primary(), Selection::new(2, 0));
+	}
+
+	#[test]
+	fn block_and_extend() {
+		let buf = PieceTable::new("abcd\nx\nefgh".into());
+
+		// from `b` down to `g`, with the middle line too short
+		let mut sels = Selections::single(1);
+		sels.set(0, Selection::new(1, 9));
+		sels.to_block(&buf);
+		let parts: Vec<Selection> = sels.iter().copied().collect();
+		assert_eq!(parts, [Selection::new(1, 2), Selection::new(8, 9)]);
+		assert_eq!(sels.primary_index(), 1);
+
+		let sel = Selection::new(2, 2);
+		assert_eq!(sel.extend(&buf, 2, 4), Selection::new(2, 3));
+		assert_eq!(sel.extend(&buf, 0, 2), Selection::new(2, 0));
+		assert_eq!(sel.extend(&buf, 0, 4), Selection::new(0, 3));
+	}
+}
@@ -1,19 +1,23 @@
 use std::{
-	io::ErrorKind,
-	marker::PhantomData,
-	process::{
-		Command,
-		Stdio,
+	io::{
+		ErrorKind,
+		Write,
 	},
+	marker::PhantomData,
+	process,
+	thread,
 };
 
 use crate::std::{
 	error::IoError,
 	path::PathScheme,
 	process::{
+		Command,
 		Exitstatus,
+		Output,
 		Shell,
 		ShellError,
+		Stdio,
 	},
 	Result,
 	StdError,
@@ -33,23 +37,95 @@ impl<S: PathScheme> StdShell<S> {
 }
 
 impl<S: PathScheme> Shell for StdShell<S> {
-	fn status(&self, cmd: &str, args: &[&str]) -> Result<Exitstatus> {
-		let code = Command::new(cmd)
-			.args(args)
-			.stdin(Stdio::null())
-			.stdout(Stdio::null())
-			.stderr(Stdio::null())
-			.status()
-			.map_err(|err| -> StdError {
-				if err.kind() == ErrorKind::NotFound {
-					ShellError::CommandNotFound(cmd.to_string()).into()
-				} else {
-					IoError::from(err).into()
-				}
-			})?
-			.code()
-			.ok_or(ShellError::ExecutionFailed(cmd.to_string()))?;
-
-		Exitstatus::from_code(code)
+	fn run(&self, command: &Command) -> Result<Output> {
+		let stdio = |stdio: Stdio| match stdio {
+			Stdio::Null => process::Stdio::null(),
+			Stdio::Piped => process::Stdio::piped(),
+		};
+
+		let mut cmd = process::Command::new(&command.program);
+		cmd.args(&command.args)
+			.envs(command.env.iter().map(|(key, val)| (key, val)))
+			.stdin(match command.input {
+				Some(_) => process::Stdio::piped(),
+				None => process::Stdio::null(),
+			})
+			.stdout(stdio(command.stdout))
+			.stderr(stdio(command.stderr));
+		if let Some(cwd) = &command.cwd {
+			cmd.current_dir(cwd);
+		}
+
+		let mut child = cmd.spawn().map_err(|err| -> StdError {
+			if err.kind() == ErrorKind::NotFound {
+				ShellError::CommandNotFound(command.program.clone()).into()
+			} else {
+				IoError::from(err).into()
+			}
+		})?;
+
+		// the input is written while the output is read, so neither side
+		// blocks on a full pipe
+		let stdin = child.stdin.take();
+		let output = thread::scope(|scope| {
+			if let (Some(mut stdin), Some(input)) = (stdin, &command.input) {
+				scope.spawn(move || {
+					// a command may exit without reading all of its input
+					let _ = stdin.write_all(input);
+				});
+			}
+			child.wait_with_output()
+		})
+		.map_err(IoError::from)?;
+
+		Ok(Output {
+			status: exit_status(output.status)
+				.ok_or(ShellError::ExecutionFailed(command.to_string()))?,
+			stdout: output.stdout,
+			stderr: output.stderr,
+		})
+	}
+}
+
+fn exit_status(status: process::ExitStatus) -> Option<Exitstatus> {
+	if let Some(code) = status.code() {
+		return Some(Exitstatus::Code(code));
+	}
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::ExitStatusExt;
+
+		status.signal().map(Exitstatus::Signal)
+	}
+	#[cfg(not(unix))]
+	None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+	use crate::target::TargetPathScheme;
+
+	#[test]
+	fn capture_and_exit_code() {
+		let shell = StdShell::<TargetPathScheme>::new();
+		let command = Command::new("sh")
+			.args(&[
+				"-c",
+				"tr a-z A-Z; echo \"$GREETING\" >&2; pwd >&2; exit 2",
+			])
+			.input(b"piped")
+			.env("GREETING", "hi")
+			.current_dir("/");
+
+		let output = shell.run(&command).unwrap();
+		assert_eq!(output.status, Exitstatus::Code(2));
+		assert_eq!(output.stdout, b"PIPED");
+		assert_eq!(output.stderr, b"hi\n/\n");
+		assert!(output.exit_ok(&command).is_err());
+
+		let killed = Command::new("sh").args(&["-c", "kill -9 $$"]);
+		assert_eq!(shell.run(&killed).unwrap().status, Exitstatus::Signal(9));
 	}
 }
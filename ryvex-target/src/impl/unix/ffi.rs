@@ -1,6 +1,7 @@
 use std::{
 	ffi::{
 		c_int,
+		c_short,
 		c_ulong,
 		c_void,
 	},
@@ -10,6 +11,7 @@ use std::{
 		AsRawFd,
 		RawFd,
 	},
+	time::Duration,
 };
 
 use crate::term::console::Handle;
@@ -40,13 +42,55 @@ extern "C" {
 	fn c_isatty(fd: c_int) -> c_int;
 	#[link_name = "read"]
 	fn c_read(fd: c_int, buf: *mut c_void, count: usize) -> target::ssize_t;
+	#[link_name = "write"]
+	fn c_write(fd: c_int, buf: *const c_void, count: usize) -> target::ssize_t;
 	#[link_name = "close"]
 	fn c_close(fd: c_int) -> c_int;
 
 	#[link_name = "ioctl"]
 	fn c_ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+	#[link_name = "fcntl"]
+	fn c_fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+
+	#[link_name = "poll"]
+	fn c_poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int;
+
+	#[link_name = "signal"]
+	fn c_signal(signum: c_int, handler: usize) -> usize;
+	#[cfg(test)]
+	#[link_name = "raise"]
+	fn c_raise(signum: c_int) -> c_int;
+
+	#[cfg(target_os = "linux")]
+	#[link_name = "__errno_location"]
+	fn c_errno() -> *mut c_int;
+	#[cfg(target_os = "macos")]
+	#[link_name = "__error"]
+	fn c_errno() -> *mut c_int;
 }
 
+// `poll` has the same `struct pollfd` and `POLLIN` on Linux and macOS, only
+// the type of its count differs
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct pollfd {
+	fd:      c_int,
+	events:  c_short,
+	revents: c_short,
+}
+
+#[cfg(target_os = "macos")]
+#[allow(non_camel_case_types)]
+type nfds_t = std::ffi::c_uint;
+#[cfg(not(target_os = "macos"))]
+#[allow(non_camel_case_types)]
+type nfds_t = c_ulong;
+
+const POLLIN: c_short = 0x1;
+
+/// `SIG_ERR`, the `(void (*)(int)) -1` that `signal` fails with.
+const SIG_ERR: usize = usize::MAX;
+
 pub fn tcsetattr(
 	fd: RawFd,
 	action: c_int,
@@ -86,6 +130,33 @@ pub fn read(fd: RawFd, buf: &mut [u8]) -> io::Result<usize> {
 	}
 }
 
+pub fn write(fd: RawFd, buf: &[u8]) -> io::Result<usize> {
+	match unsafe { c_write(fd, buf.as_ptr() as *const _, buf.len()) } {
+		n if n >= 0 => Ok(n as usize),
+		_ => Err(io::Error::last_os_error()),
+	}
+}
+
+/// Makes reads and writes on `fd` fail with `WouldBlock` instead of waiting.
+pub fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+	let flags = unsafe { c_fcntl(fd, target::os::F_GETFL) };
+	if flags < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	let flags = flags | target::os::O_NONBLOCK;
+	io_result(unsafe { c_fcntl(fd, target::os::F_SETFL, flags) })
+}
+
+/// The calling thread's `errno`, which a signal handler has to leave as it
+/// found it.
+pub fn errno() -> c_int {
+	unsafe { *c_errno() }
+}
+
+pub fn set_errno(errno: c_int) {
+	unsafe { *c_errno() = errno };
+}
+
 pub fn ioctl(fd: &TtyFd, request: c_ulong) -> io::Result<winsize> {
 	let mut winsize = MaybeUninit::<winsize>::uninit();
 
@@ -96,6 +167,58 @@ pub fn ioctl(fd: &TtyFd, request: c_ulong) -> io::Result<winsize> {
 	Ok(unsafe { winsize.assume_init() })
 }
 
+#[cfg(test)]
+pub fn set_window_size(fd: RawFd, size: &winsize) -> io::Result<()> {
+	let request = target::os::TIOCSWINSZ as c_ulong;
+	io_result(unsafe { c_ioctl(fd, request, size as *const winsize) })
+}
+
+/// Waits until `fd` has input to read or `timeout` runs out, and returns
+/// whether there is input. Without a timeout it waits indefinitely.
+pub fn poll_input(fd: RawFd, timeout: Option<Duration>) -> io::Result<bool> {
+	poll_inputs([fd], timeout).map(|[ready]| ready)
+}
+
+/// Waits until any of `fds` has input to read or `timeout` runs out, and
+/// returns which of them have input.
+pub fn poll_inputs<const N: usize>(
+	fds: [RawFd; N],
+	timeout: Option<Duration>,
+) -> io::Result<[bool; N]> {
+	let timeout = match timeout {
+		// round up, so a short wait does not turn into a busy loop
+		Some(timeout) => timeout
+			.as_micros()
+			.div_ceil(1000)
+			.try_into()
+			.unwrap_or(c_int::MAX),
+		None => -1,
+	};
+	let mut fds = fds.map(|fd| pollfd {
+		fd,
+		events: POLLIN,
+		revents: 0,
+	});
+
+	match unsafe { c_poll(fds.as_mut_ptr(), N as nfds_t, timeout) } {
+		n if n < 0 => Err(io::Error::last_os_error()),
+		// a closed pipe reports `POLLHUP`, which a read then sees as the end
+		_ => Ok(fds.map(|fd| fd.revents != 0)),
+	}
+}
+
+pub fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> io::Result<()> {
+	match unsafe { c_signal(signum, handler as usize) } {
+		SIG_ERR => Err(io::Error::last_os_error()),
+		_ => Ok(()),
+	}
+}
+
+#[cfg(test)]
+pub fn raise(signum: c_int) -> io::Result<()> {
+	io_result(unsafe { c_raise(signum) })
+}
+
 #[inline]
 fn io_result(result: c_int) -> io::Result<()> {
 	match result {
@@ -1,4 +1,7 @@
 mod ffi;
+mod signal;
+// mirrors the C headers, so not every definition is used on every target
+#[allow(dead_code)]
 mod target;
 
 pub mod term;
@@ -0,0 +1,114 @@
+//! Signal handlers. A handler may do little more than store to an atomic, so
+//! each one raises a flag that the event source picks up later, and wakes
+//! the source by writing to a pipe it polls along with the tty. A signal
+//! that arrives after the source checked the flags but before it started
+//! waiting still leaves the pipe readable, so no signal is lost.
+
+use std::{
+	ffi::c_int,
+	io::{
+		self,
+		PipeReader,
+		PipeWriter,
+		Read,
+	},
+	os::fd::{
+		AsRawFd,
+		RawFd,
+	},
+	sync::{
+		atomic::{
+			AtomicBool,
+			AtomicI32,
+			Ordering,
+		},
+		OnceLock,
+	},
+};
+
+use super::ffi;
+
+// the same numbers on Linux and macOS
+const SIGHUP: c_int = 1;
+const SIGTERM: c_int = 15;
+pub(super) const SIGWINCH: c_int = 28;
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+static WAKE_PIPE: OnceLock<(PipeReader, PipeWriter)> = OnceLock::new();
+/// The write end of [`WAKE_PIPE`], or -1 before it exists.
+static WAKE_WRITER: AtomicI32 = AtomicI32::new(-1);
+
+fn wake() {
+	let fd = WAKE_WRITER.load(Ordering::Relaxed);
+	if fd < 0 {
+		return;
+	}
+	// a full pipe already wakes the source, so a failed write is fine, but
+	// it must not clobber the `errno` of the code the signal interrupted
+	let errno = ffi::errno();
+	let _ = ffi::write(fd, &[0]);
+	ffi::set_errno(errno);
+}
+
+extern "C" fn on_resize(_signum: c_int) {
+	RESIZED.store(true, Ordering::Relaxed);
+	wake();
+}
+
+extern "C" fn on_terminate(_signum: c_int) {
+	TERMINATED.store(true, Ordering::Relaxed);
+	wake();
+}
+
+/// The read end of the pipe the handlers write to, which has input whenever
+/// a signal arrived since the last [`drain_wakeups`].
+pub fn wake_fd() -> io::Result<RawFd> {
+	if let Some((reader, _)) = WAKE_PIPE.get() {
+		return Ok(reader.as_raw_fd());
+	}
+
+	let (reader, writer) = io::pipe()?;
+	ffi::set_nonblocking(reader.as_raw_fd())?;
+	ffi::set_nonblocking(writer.as_raw_fd())?;
+
+	let (reader, writer) = WAKE_PIPE.get_or_init(|| (reader, writer));
+	WAKE_WRITER.store(writer.as_raw_fd(), Ordering::Relaxed);
+	Ok(reader.as_raw_fd())
+}
+
+/// Empties the pipe from [`wake_fd`], once its wakeups were seen.
+pub fn drain_wakeups() {
+	let Some(mut reader) = WAKE_PIPE.get().map(|(reader, _)| reader) else {
+		return;
+	};
+	let mut buf = [0u8; 64];
+	while matches!(reader.read(&mut buf), Ok(n) if n > 0) {}
+}
+
+/// Installs the `SIGWINCH` handler.
+pub fn listen_for_resize() -> io::Result<()> {
+	wake_fd()?;
+	ffi::signal(SIGWINCH, on_resize)
+}
+
+/// Whether the terminal was resized since the last call.
+pub fn take_resized() -> bool {
+	RESIZED.swap(false, Ordering::Relaxed)
+}
+
+/// Installs handlers for `SIGTERM` and `SIGHUP`, so the editor gets to put
+/// the terminal back before it exits instead of dying with it in raw mode.
+pub fn listen_for_termination() -> io::Result<()> {
+	wake_fd()?;
+	for signum in [SIGTERM, SIGHUP] {
+		ffi::signal(signum, on_terminate)?;
+	}
+	Ok(())
+}
+
+/// Whether the process was asked to terminate since the last call.
+pub fn take_terminated() -> bool {
+	TERMINATED.swap(false, Ordering::Relaxed)
+}
@@ -20,11 +20,30 @@ use crate::term::command::{
 		SetCursorStyle,
 		Show,
 	},
+	style::{
+		ResetColor,
+		SetAttribute,
+		SetBackgroundColor,
+		SetForegroundColor,
+	},
 	terminal::{
+		BeginSynchronizedUpdate,
 		Clear,
+		DisableBracketedPaste,
+		DisableMouseCapture,
+		EnableBracketedPaste,
+		EnableMouseCapture,
+		EndSynchronizedUpdate,
+		EnterAlternateScreen,
+		LeaveAlternateScreen,
+		PopKeyboardEnhancement,
 		Print,
+		PushKeyboardEnhancement,
+		ResetScrollRegion,
 		ScrollDown,
 		ScrollUp,
+		SetClipboard,
+		SetScrollRegion,
 		SetSize,
 	},
 	ExecuteApi,
@@ -51,4 +70,21 @@ impl ExecuteApi for ScrollUp {}
 impl ExecuteApi for ScrollDown {}
 impl ExecuteApi for Clear {}
 impl ExecuteApi for SetSize {}
+impl ExecuteApi for EnableMouseCapture {}
+impl ExecuteApi for DisableMouseCapture {}
+impl ExecuteApi for EnableBracketedPaste {}
+impl ExecuteApi for DisableBracketedPaste {}
+impl ExecuteApi for EnterAlternateScreen {}
+impl ExecuteApi for LeaveAlternateScreen {}
+impl ExecuteApi for PushKeyboardEnhancement {}
+impl ExecuteApi for PopKeyboardEnhancement {}
+impl ExecuteApi for SetForegroundColor {}
+impl ExecuteApi for SetBackgroundColor {}
+impl ExecuteApi for SetAttribute {}
+impl ExecuteApi for ResetColor {}
+impl ExecuteApi for SetScrollRegion {}
+impl ExecuteApi for ResetScrollRegion {}
+impl ExecuteApi for BeginSynchronizedUpdate {}
+impl ExecuteApi for EndSynchronizedUpdate {}
+impl ExecuteApi for SetClipboard<'_> {}
 impl<T: Display> ExecuteApi for Print<T> {}
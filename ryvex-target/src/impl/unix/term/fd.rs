@@ -2,6 +2,7 @@ use std::{
 	fs::OpenOptions,
 	os::fd::{
 		IntoRawFd,
+		OwnedFd,
 		RawFd,
 	},
 };
@@ -61,6 +62,15 @@ impl TtyFd {
 	}
 }
 
+impl From<OwnedFd> for TtyFd {
+	fn from(fd: OwnedFd) -> Self {
+		Self {
+			fd:            fd.into_raw_fd(),
+			close_on_drop: true,
+		}
+	}
+}
+
 impl Handle<RawFd, TtyFdSettings> for TtyFd {
 	fn acquire(mode: TtyFdSettings) -> Result<Self> {
 		TtyFd::from_default_tty(mode.read, mode.write)
@@ -1,26 +1,37 @@
 mod command;
 
 pub mod fd;
+pub mod query;
 pub mod source;
 pub mod termios;
 
 use super::{
 	ffi,
+	signal,
 	target::os::TIOCGWINSZ,
 };
 use crate::std::error::IoError;
 use crate::std::Result;
 use ryvex_ui::graphics::Rect;
+use std::sync::OnceLock;
 
 pub use fd::TtyFd as Handle;
 pub use fd::TtyFdSettings as HandleMode;
+pub use query::{
+	query,
+	unread,
+};
 pub use source::UnixEventSource as TargetEventSource;
 pub use termios::Termios as ConsoleSettings;
 
-/// since we only ever open `stdin` or `stdout` ANSI escape codes are always
-/// supported
-pub(crate) fn supports_ansi() -> bool {
-	true
+static SUPPORTS_ANSI: OnceLock<bool> = OnceLock::new();
+
+/// Every terminal that sets `TERM` takes ANSI escape codes, except for the
+/// one that says it is `dumb`.
+pub fn supports_ansi() -> bool {
+	*SUPPORTS_ANSI.get_or_init(|| {
+		std::env::var("TERM").map_or(true, |term| term != "dumb")
+	})
 }
 
 pub fn get_terminal_size(fd: &fd::TtyFd) -> Result<Rect> {
@@ -0,0 +1,81 @@
+//! Asking the terminal about itself. The questions go out like any other
+//! escape sequence and the answers come back on the input, so they are read
+//! here before the event source takes over the tty.
+
+use std::{
+	io::ErrorKind,
+	os::fd::AsRawFd,
+	sync::Mutex,
+	time::{
+		Duration,
+		Instant,
+	},
+};
+
+use super::{
+	fd::TtyFd,
+	ffi,
+};
+use crate::{
+	r#impl::TargetOutWriter,
+	std::{
+		error::IoError,
+		write::Write,
+		Result,
+	},
+	term::console::Handle,
+};
+
+/// Input that was read along with the replies, for the event source.
+static UNREAD: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Writes `queries` to the terminal and collects what it sends back until
+/// `answered` accepts it or `timeout` passes.
+pub fn query(
+	queries: &str,
+	timeout: Duration,
+	answered: impl Fn(&[u8]) -> bool,
+) -> Result<Vec<u8>> {
+	let tty = TtyFd::from_default_tty(true, false)?;
+	let fd = tty.inner().as_raw_fd();
+
+	let mut out = TargetOutWriter::default();
+	out.write_all(queries.as_bytes())?;
+	out.flush()?;
+
+	let deadline = Instant::now() + timeout;
+	let mut replies = Vec::new();
+	while !answered(&replies) {
+		let wait = deadline.saturating_duration_since(Instant::now());
+		match ffi::poll_input(fd, Some(wait)) {
+			Ok(true) => {}
+			Ok(false) => break,
+			Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+			Err(e) => return Err(IoError::from(e).into()),
+		}
+
+		let mut buf = [0u8; 1024];
+		match ffi::read(fd, &mut buf) {
+			Ok(n) => replies.extend_from_slice(&buf[..n]),
+			Err(e) if e.kind() == ErrorKind::Interrupted => {}
+			Err(e) => return Err(IoError::from(e).into()),
+		}
+	}
+
+	Ok(replies)
+}
+
+/// Hands input read by [`query`] back, to be decoded before anything the
+/// event source reads itself.
+pub fn unread(bytes: Vec<u8>) {
+	if let Ok(mut unread) = UNREAD.lock() {
+		unread.extend(bytes);
+	}
+}
+
+pub(super) fn take_unread() -> Vec<u8> {
+	UNREAD
+		.lock()
+		.map(|mut unread| std::mem::take(&mut *unread))
+		.unwrap_or_default()
+}
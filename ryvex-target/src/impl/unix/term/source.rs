@@ -1,15 +1,24 @@
 use std::{
 	error::Error,
-	os::fd::AsRawFd,
-	time::Duration,
+	io::ErrorKind,
+	os::fd::{
+		AsRawFd,
+		RawFd,
+	},
+	time::{
+		Duration,
+		Instant,
+	},
 };
 
 use super::{
 	fd::TtyFd,
 	ffi,
+	get_terminal_size,
+	query,
+	signal,
 };
 use crate::{
-	key::AsciiKeyCode,
 	std::{
 		error::IoError,
 		Result,
@@ -20,35 +29,135 @@ use crate::{
 			Event,
 			EventSource,
 		},
+		parse::{
+			parse_event,
+			Parsed,
+		},
 	},
 };
 
+/// How long to wait for the rest of an escape sequence before taking what
+/// arrived for keys of its own, like a lone `Esc`.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(25);
+
 /// Unix event source implementation.
 pub struct UnixEventSource {
-	tty: TtyFd,
+	tty:     TtyFd,
+	/// Has input whenever a signal arrived, see [`signal::wake_fd`].
+	wake:    RawFd,
+	/// Bytes read from the tty that have not been decoded yet.
+	pending: Vec<u8>,
 }
 
 impl UnixEventSource {
 	/// Creates a new UnixEventSource by obtaining a tty file descriptor.
 	pub fn new() -> Result<Self> {
-		let tty = TtyFd::from_default_tty(false, true)?;
+		Self::with_tty(TtyFd::from_default_tty(false, true)?)
+	}
 
-		Ok(Self { tty })
+	/// Creates a UnixEventSource that reads from `tty`.
+	pub fn with_tty(tty: TtyFd) -> Result<Self> {
+		let wake = signal::wake_fd().map_err(IoError::from)?;
+		signal::listen_for_resize().map_err(IoError::from)?;
+		signal::listen_for_termination().map_err(IoError::from)?;
+
+		Ok(Self {
+			tty,
+			wake,
+			pending: query::take_unread(),
+		})
+	}
+
+	/// Decodes the next event from the pending bytes. With `more`, a
+	/// sequence cut short at the end stays pending for the rest to arrive.
+	/// Without it, the sequence is taken for what it looks like so far,
+	/// unless it cannot be anything but the start of something longer, like
+	/// a partial UTF-8 character or paste.
+	fn next_pending(&mut self, more: bool) -> Option<Event> {
+		while !self.pending.is_empty() {
+			match parse_event(&self.pending, more) {
+				Parsed::Event(event, len) => {
+					self.pending.drain(..len);
+					return Some(event);
+				}
+				Parsed::Ignored(len) => {
+					self.pending.drain(..len);
+				}
+				Parsed::Incomplete => break,
+			}
+		}
+		None
+	}
+
+	/// Appends whatever the tty has to the pending bytes.
+	fn read_pending(&mut self) -> std::io::Result<usize> {
+		let mut buf = [0u8; 1024];
+		let n = ffi::read(self.tty.inner().as_raw_fd(), &mut buf)?;
+		self.pending.extend_from_slice(&buf[..n]);
+		Ok(n)
+	}
+
+	fn resize_event(&self) -> Result<Event> {
+		let area = get_terminal_size(&self.tty)?;
+		Ok(Event::Resize(area.width, area.height))
 	}
 }
 
 impl EventSource for UnixEventSource {
-	fn try_read(
-		&mut self,
-		_timeout: Option<Duration>,
-	) -> Result<Option<Event>> {
-		let mut buf = [0u8; 1];
-
-		match ffi::read(self.tty.inner().as_raw_fd(), &mut buf) {
-			Ok(0) => Ok(None),
-			Ok(_) => Ok(Some(Event::Key(AsciiKeyCode::from_ascii(buf[0])))),
-			Err(e) if is_would_block(&e) => Ok(None),
-			Err(e) => Err(IoError::from(e).into()),
+	fn try_read(&mut self, timeout: Option<Duration>) -> Result<Option<Event>> {
+		let deadline = timeout.map(|timeout| Instant::now() + timeout);
+		// the pending bytes wait for more than the escape timeout
+		let mut stalled = false;
+
+		loop {
+			if signal::take_terminated() {
+				return Ok(Some(Event::Terminate));
+			}
+			if signal::take_resized() {
+				return self.resize_event().map(Some);
+			}
+			if let Some(event) = self.next_pending(true) {
+				return Ok(Some(event));
+			}
+
+			// what is left is the start of a sequence, whose rest follows
+			// right away if it is one
+			let incomplete = !self.pending.is_empty() && !stalled;
+			let wait = match deadline {
+				_ if incomplete => Some(ESCAPE_TIMEOUT),
+				Some(deadline) => {
+					Some(deadline.saturating_duration_since(Instant::now()))
+				}
+				None => None,
+			};
+
+			let fds = [self.tty.inner().as_raw_fd(), self.wake];
+			match ffi::poll_inputs(fds, wait) {
+				// a signal, which the next iteration checks for
+				Ok([_, true]) => {
+					signal::drain_wakeups();
+					continue;
+				}
+				Ok([true, false]) => stalled = false,
+				Ok(_) if incomplete => {
+					if let Some(event) = self.next_pending(false) {
+						return Ok(Some(event));
+					}
+					stalled = true;
+					continue;
+				}
+				Ok(_) => return Ok(None),
+				Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+				Err(e) => return Err(IoError::from(e).into()),
+			}
+
+			match self.read_pending() {
+				Ok(0) => return Ok(None),
+				Ok(_) => {}
+				Err(e) if e.kind() == ErrorKind::Interrupted => {}
+				Err(e) if is_would_block(&e) => return Ok(None),
+				Err(e) => return Err(IoError::from(e).into()),
+			}
 		}
 	}
 }
@@ -58,8 +167,103 @@ impl EventSource for UnixEventSource {
 fn is_would_block(err: &dyn Error) -> bool {
 	if let Some(source) = err.source() {
 		if let Some(io_err) = source.downcast_ref::<std::io::Error>() {
-			return io_err.kind() == std::io::ErrorKind::WouldBlock;
+			return io_err.kind() == ErrorKind::WouldBlock;
 		}
 	}
 	false
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fs::OpenOptions,
+		io::{
+			PipeWriter,
+			Write,
+		},
+		os::fd::OwnedFd,
+		sync::Mutex,
+		thread,
+	};
+
+	use super::*;
+	use crate::{
+		key::{
+			KeyCode,
+			KeyEvent,
+			Modifiers,
+		},
+		target::unix::target::os::winsize,
+	};
+
+	/// Held by tests that read events, which all share the signal flags.
+	static SIGNALS: Mutex<()> = Mutex::new(());
+
+	/// A source reading from a pipe, and the other end of it to type into.
+	fn piped() -> (UnixEventSource, PipeWriter) {
+		let (reader, writer) = std::io::pipe().unwrap();
+		let tty = OwnedFd::from(reader).into();
+		(UnixEventSource::with_tty(tty).unwrap(), writer)
+	}
+
+	#[test]
+	fn escape_timeout() {
+		let _signals = SIGNALS.lock().unwrap();
+		let (mut source, mut input) = piped();
+		let wait = Some(Duration::from_millis(200));
+		let key = |code, modifiers| {
+			Ok(Some(Event::Key(KeyEvent::new(code, modifiers))))
+		};
+
+		// nothing follows in time, so the escape is a key of its own
+		input.write_all(b"\x1b").unwrap();
+		assert_eq!(source.try_read(wait), key(KeyCode::Esc, Modifiers::NONE));
+
+		// arriving together, it is Alt held with the key after it
+		input.write_all(b"\x1bx").unwrap();
+		assert_eq!(
+			source.try_read(wait),
+			key(KeyCode::Char('x'), Modifiers::ALT)
+		);
+
+		// half a character can't be a key, so it stalls until the rest comes
+		input.write_all(&"ä".as_bytes()[..1]).unwrap();
+		assert_eq!(source.try_read(wait), Ok(None));
+		input.write_all(&"ä".as_bytes()[1..]).unwrap();
+		assert_eq!(
+			source.try_read(wait),
+			key(KeyCode::Char('ä'), Modifiers::NONE)
+		);
+	}
+
+	#[test]
+	fn signal_wakes_a_waiting_source() {
+		let _signals = SIGNALS.lock().unwrap();
+		// the master side of a pseudo terminal answers for its size too
+		let pty = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open("/dev/ptmx")
+			.unwrap();
+		let size = winsize {
+			ws_row:    40,
+			ws_col:    100,
+			ws_xpixel: 0,
+			ws_ypixel: 0,
+		};
+		ffi::set_window_size(pty.as_raw_fd(), &size).unwrap();
+		let mut source =
+			UnixEventSource::with_tty(OwnedFd::from(pty).into()).unwrap();
+
+		// raised on another thread, the signal does not interrupt the poll
+		// here, so only the wake pipe can end it
+		let raiser = thread::spawn(|| {
+			thread::sleep(Duration::from_millis(50));
+			ffi::raise(signal::SIGWINCH)
+		});
+		let event = source.try_read(Some(Duration::from_secs(5))).unwrap();
+		raiser.join().unwrap().unwrap();
+
+		assert_eq!(event, Some(Event::Resize(100, 40)));
+	}
+}
@@ -180,6 +180,7 @@ unsafe extern "system" {
 extern "C" {
 	pub fn _kbhit() -> c_int;
 	pub fn _getch() -> c_int;
+	pub fn _getwch() -> u16;
 }
 
 pub fn get_current_out_handle() -> io::Result<HANDLE> {
@@ -362,6 +363,10 @@ pub fn getch() -> i32 {
 	unsafe { _getch() }
 }
 
+pub fn getwch() -> u16 {
+	unsafe { _getwch() }
+}
+
 #[inline]
 fn ptr_opt<T>(opt: Option<&T>) -> *const T {
 	opt.map_or(ptr::null(), |r| r as *const _)
@@ -18,11 +18,30 @@ use crate::term::command::{
 		SetCursorStyle,
 		Show,
 	},
+	style::{
+		ResetColor,
+		SetAttribute,
+		SetBackgroundColor,
+		SetForegroundColor,
+	},
 	terminal::{
+		BeginSynchronizedUpdate,
 		Clear,
+		DisableBracketedPaste,
+		DisableMouseCapture,
+		EnableBracketedPaste,
+		EnableMouseCapture,
+		EndSynchronizedUpdate,
+		EnterAlternateScreen,
+		LeaveAlternateScreen,
+		PopKeyboardEnhancement,
 		Print,
+		PushKeyboardEnhancement,
+		ResetScrollRegion,
 		ScrollDown,
 		ScrollUp,
+		SetClipboard,
+		SetScrollRegion,
 		SetSize,
 	},
 	ExecuteApi,
@@ -158,6 +177,35 @@ impl ExecuteApi for SetSize {
 	}
 }
 
+// the console input read through `getwch` carries no mouse or paste events,
+// so there is nothing to switch on without ANSI support
+impl ExecuteApi for EnableMouseCapture {}
+impl ExecuteApi for DisableMouseCapture {}
+impl ExecuteApi for EnableBracketedPaste {}
+impl ExecuteApi for DisableBracketedPaste {}
+impl ExecuteApi for PushKeyboardEnhancement {}
+impl ExecuteApi for PopKeyboardEnhancement {}
+
+// the legacy console has a single screen buffer
+impl ExecuteApi for EnterAlternateScreen {}
+impl ExecuteApi for LeaveAlternateScreen {}
+
+// colors and attributes go without ANSI support, text is drawn plain
+impl ExecuteApi for SetForegroundColor {}
+impl ExecuteApi for SetBackgroundColor {}
+impl ExecuteApi for SetAttribute {}
+impl ExecuteApi for ResetColor {}
+
+// drawing is not held back without ANSI support, and scroll regions are
+// only used with it
+impl ExecuteApi for BeginSynchronizedUpdate {}
+impl ExecuteApi for EndSynchronizedUpdate {}
+impl ExecuteApi for SetScrollRegion {}
+impl ExecuteApi for ResetScrollRegion {}
+
+// the clipboard is only reached through OSC 52, which takes ANSI support
+impl ExecuteApi for SetClipboard<'_> {}
+
 impl<T: Display> ExecuteApi for Print<T> {
 	fn execute_api(&self) -> Result<(), IoError> {
 		terminal::write(&self.0.to_string()).map_err(IoError::from)
@@ -18,6 +18,7 @@ use ryvex_ui::graphics::Rect;
 use std::{
 	io,
 	sync::OnceLock,
+	time::Duration,
 };
 
 pub use console::ConsoleSettings;
@@ -70,3 +71,15 @@ pub fn get_terminal_size(handle: &handle::ConsoleHandle) -> Result<Rect> {
 		height,
 	})
 }
+
+/// The console input read through `getwch` carries no replies to queries,
+/// so there is nothing to ask.
+pub fn query(
+	_queries: &str,
+	_timeout: Duration,
+	_answered: impl Fn(&[u8]) -> bool,
+) -> Result<Vec<u8>> {
+	Ok(Vec::new())
+}
+
+pub fn unread(_bytes: Vec<u8>) {}
@@ -1,6 +1,9 @@
 use super::ffi;
 use crate::{
-	key::AsciiKeyCode,
+	key::{
+		KeyCode,
+		KeyEvent,
+	},
 	std::Result,
 	term::event::{
 		Event,
@@ -41,11 +44,45 @@ impl EventSource for WindowsEventSource {
 
 fn read_key_blocking() -> Event {
 	loop {
-		let ch = ffi::getch();
-		if ch == 0 || ch == 0xE0 {
-			let _ = ffi::getch();
-			continue;
+		let unit = ffi::getwch();
+		// extended keys arrive as a prefix and their scan code, which is
+		// already waiting; a lone 0xE0 is `à`
+		if unit == 0 || (unit == 0xE0 && ffi::kbhit()) {
+			match scan_code_key(i32::from(ffi::getwch())) {
+				Some(code) => return Event::Key(KeyEvent::plain(code)),
+				None => continue,
+			}
 		}
-		return Event::Key(AsciiKeyCode::from_ascii(ch as u8));
+
+		let units = if (0xD800..0xDC00).contains(&unit) {
+			vec![unit, ffi::getwch()]
+		} else {
+			vec![unit]
+		};
+		let Some(Ok(ch)) = char::decode_utf16(units).next() else {
+			continue;
+		};
+
+		return Event::Key(KeyEvent::from(ch));
 	}
 }
+
+/// Maps the scan code `getwch` returns after a `0` or `0xE0` prefix for keys
+/// that have no character.
+fn scan_code_key(scan: i32) -> Option<KeyCode> {
+	Some(match scan {
+		59..=68 => KeyCode::F((scan - 58) as u8),
+		71 => KeyCode::Home,
+		72 => KeyCode::Up,
+		73 => KeyCode::PageUp,
+		75 => KeyCode::Left,
+		77 => KeyCode::Right,
+		79 => KeyCode::End,
+		80 => KeyCode::Down,
+		81 => KeyCode::PageDown,
+		82 => KeyCode::Insert,
+		83 => KeyCode::Delete,
+		133 | 134 => KeyCode::F((scan - 122) as u8),
+		_ => return None,
+	})
+}
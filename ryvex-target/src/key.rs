@@ -1,5 +1,9 @@
 use core::{
 	fmt::Display,
+	ops::{
+		BitOr,
+		BitOrAssign,
+	},
 	str::FromStr,
 };
 
@@ -336,6 +340,319 @@ impl Display for AsciiKeyCode {
 	}
 }
 
+/// A key as the terminal reports it, independent of how it was encoded.
+///
+/// Control characters decode to their letter with [`Modifiers::CTRL`], so
+/// `Ctrl-R` is `Char('r')` with `CTRL` set. Only the keys that have a key of
+/// their own, like Enter, Tab and Esc, get their own code.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum KeyCode {
+	Char(char),
+	Enter,
+	Tab,
+	BackTab,
+	Backspace,
+	Esc,
+	Insert,
+	Delete,
+	Up,
+	Down,
+	Left,
+	Right,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	/// Function keys `F1` to `F12`.
+	F(u8),
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 17] = [
+	("CR", KeyCode::Enter),
+	("Enter", KeyCode::Enter),
+	("Tab", KeyCode::Tab),
+	("BS", KeyCode::Backspace),
+	("Esc", KeyCode::Esc),
+	("Insert", KeyCode::Insert),
+	("Del", KeyCode::Delete),
+	("Up", KeyCode::Up),
+	("Down", KeyCode::Down),
+	("Left", KeyCode::Left),
+	("Right", KeyCode::Right),
+	("Home", KeyCode::Home),
+	("End", KeyCode::End),
+	("PageUp", KeyCode::PageUp),
+	("PageDown", KeyCode::PageDown),
+	("Space", KeyCode::Char(' ')),
+	("lt", KeyCode::Char('<')),
+];
+
+/// Modifier keys held down with a key.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+	pub const NONE: Modifiers = Modifiers(0);
+	pub const SHIFT: Modifiers = Modifiers(1);
+	pub const ALT: Modifiers = Modifiers(2);
+	pub const CTRL: Modifiers = Modifiers(4);
+
+	/// Decodes the modifier parameter of an xterm key sequence, like the `5`
+	/// in `CSI 1;5A` for Ctrl-Up. Meta counts as Alt.
+	pub fn from_xterm(param: u16) -> Self {
+		let bits = param.saturating_sub(1);
+		let mut mods = Modifiers::NONE;
+		if bits & 1 != 0 {
+			mods |= Modifiers::SHIFT;
+		}
+		if bits & (2 | 8) != 0 {
+			mods |= Modifiers::ALT;
+		}
+		if bits & 4 != 0 {
+			mods |= Modifiers::CTRL;
+		}
+		mods
+	}
+
+	pub fn contains(self, other: Modifiers) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	pub fn without(self, other: Modifiers) -> Self {
+		Modifiers(self.0 & !other.0)
+	}
+}
+
+impl BitOr for Modifiers {
+	type Output = Modifiers;
+
+	fn bitor(self, rhs: Modifiers) -> Modifiers {
+		Modifiers(self.0 | rhs.0)
+	}
+}
+
+impl BitOrAssign for Modifiers {
+	fn bitor_assign(&mut self, rhs: Modifiers) {
+		self.0 |= rhs.0;
+	}
+}
+
+/// A key press: a [`KeyCode`] and the modifiers held with it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct KeyEvent {
+	pub code:      KeyCode,
+	pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+	pub fn new(code: KeyCode, modifiers: Modifiers) -> Self {
+		Self { code, modifiers }
+	}
+
+	pub fn plain(code: KeyCode) -> Self {
+		Self::new(code, Modifiers::NONE)
+	}
+
+	/// The character the key types, if it is a character key pressed without
+	/// Ctrl or Alt.
+	pub fn as_char(&self) -> Option<char> {
+		match self.code {
+			KeyCode::Char(ch)
+				if !self.modifiers.contains(Modifiers::CTRL) &&
+					!self.modifiers.contains(Modifiers::ALT) =>
+			{
+				Some(ch)
+			}
+			_ => None,
+		}
+	}
+
+	/// Parses a key sequence in Vim notation like `gg`, `<C-R>` or
+	/// `<A-S-Left>` into the keys it consists of.
+	pub fn parse_human_str(s: &str) -> Result<Vec<KeyEvent>, String> {
+		let mut keys = Vec::new();
+		let mut rest = s;
+
+		while let Some(ch) = rest.chars().next() {
+			let token = match (ch, rest.find('>')) {
+				('<', Some(end)) if end > 1 => &rest[..=end],
+				_ => &rest[..ch.len_utf8()],
+			};
+			keys.push(token.parse()?);
+			rest = &rest[token.len()..];
+		}
+
+		Ok(keys)
+	}
+
+	pub fn to_human_readable(self) -> String {
+		let name = match self.code {
+			KeyCode::Char(ch) if self.modifiers.is_empty() => {
+				match NAMED_KEYS.iter().find(|(_, code)| *code == self.code) {
+					Some((name, _)) => return format!("<{name}>"),
+					None => return ch.to_string(),
+				}
+			}
+			KeyCode::Char(ch) => match ch {
+				' ' => "Space".to_string(),
+				'<' => "lt".to_string(),
+				ch => ch.to_string(),
+			},
+			KeyCode::BackTab => "S-Tab".to_string(),
+			KeyCode::F(n) => format!("F{n}"),
+			code => NAMED_KEYS
+				.iter()
+				.find(|(_, named)| *named == code)
+				.map(|(name, _)| name.to_string())
+				.unwrap_or_default(),
+		};
+
+		let mut out = String::from("<");
+		for (modifier, prefix) in [
+			(Modifiers::CTRL, "C-"),
+			(Modifiers::ALT, "A-"),
+			(Modifiers::SHIFT, "S-"),
+		] {
+			if self.modifiers.contains(modifier) {
+				out.push_str(prefix);
+			}
+		}
+		out.push_str(&name);
+		out.push('>');
+		out
+	}
+}
+
+impl From<AsciiKeyCode> for KeyEvent {
+	/// Decodes a byte as typed on a terminal: control characters become
+	/// their letter with Ctrl held, except for those that have a key of
+	/// their own.
+	fn from(key: AsciiKeyCode) -> Self {
+		let code = match key {
+			AsciiKeyCode::Tab => KeyCode::Tab,
+			AsciiKeyCode::CarriageReturn => KeyCode::Enter,
+			AsciiKeyCode::Esc => KeyCode::Esc,
+			AsciiKeyCode::Del => KeyCode::Backspace,
+			AsciiKeyCode::Nul => {
+				return KeyEvent::new(KeyCode::Char(' '), Modifiers::CTRL)
+			}
+			key if key.is_control_character() || key.is_seperator() => {
+				if key == AsciiKeyCode::Space {
+					KeyCode::Char(' ')
+				} else {
+					let ch = char::from(key as u8 | 0x40).to_ascii_lowercase();
+					return KeyEvent::new(KeyCode::Char(ch), Modifiers::CTRL);
+				}
+			}
+			key => KeyCode::Char(key.to_char()),
+		};
+
+		KeyEvent::plain(code)
+	}
+}
+
+impl From<char> for KeyEvent {
+	fn from(ch: char) -> Self {
+		if ch.is_ascii() {
+			AsciiKeyCode::from_ascii(ch as u8).into()
+		} else {
+			KeyEvent::plain(KeyCode::Char(ch))
+		}
+	}
+}
+
+impl FromStr for KeyEvent {
+	type Err = String;
+
+	/// Parses a single key: a character, or a `<...>` name with optional
+	/// `C-`, `A-` (or `M-`) and `S-` prefixes.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut chars = s.chars();
+		if let (Some(ch), None) = (chars.next(), chars.next()) {
+			return Ok(ch.into());
+		}
+
+		let Some(mut inner) =
+			s.strip_prefix('<').and_then(|s| s.strip_suffix('>'))
+		else {
+			return Err(format!(
+				"invalid key '{s}': expected a single char or <...>"
+			));
+		};
+
+		let mut modifiers = Modifiers::NONE;
+		loop {
+			let modifier = match inner.get(..2) {
+				Some("C-") => Modifiers::CTRL,
+				Some("A-") | Some("M-") => Modifiers::ALT,
+				Some("S-") => Modifiers::SHIFT,
+				_ => break,
+			};
+			// a lone `-` is the key itself, as in `<C-->`
+			if inner.len() == 2 {
+				break;
+			}
+			modifiers |= modifier;
+			inner = &inner[2..];
+		}
+
+		let code = if let Some((_, code)) =
+			NAMED_KEYS.iter().find(|(name, _)| *name == inner)
+		{
+			*code
+		} else if let Some(n) = inner
+			.strip_prefix('F')
+			.and_then(|n| n.parse::<u8>().ok())
+			.filter(|n| (1..=12).contains(n))
+		{
+			KeyCode::F(n)
+		} else {
+			let mut chars = inner.chars();
+			match (chars.next(), chars.next()) {
+				(Some(ch), None) => KeyCode::Char(ch),
+				_ => return Err(format!("unknown key name '{s}'")),
+			}
+		};
+
+		Ok(normalize(KeyEvent::new(code, modifiers)))
+	}
+}
+
+/// Maps keys with several spellings to the one terminals send: `<C-[>` is
+/// Esc, `<C-M>` Enter, `<C-I>` Tab and `<S-Tab>` BackTab. Letters held with
+/// Ctrl are lowercase, since terminals cannot tell the two apart.
+pub(crate) fn normalize(key: KeyEvent) -> KeyEvent {
+	let ctrl = key.modifiers.contains(Modifiers::CTRL);
+	let rest = key.modifiers.without(Modifiers::CTRL);
+
+	match key.code {
+		KeyCode::Char(ch) if ctrl => match ch.to_ascii_lowercase() {
+			'[' => KeyEvent::new(KeyCode::Esc, rest),
+			'm' => KeyEvent::new(KeyCode::Enter, rest),
+			'i' => KeyEvent::new(KeyCode::Tab, rest),
+			'@' => KeyEvent::new(KeyCode::Char(' '), key.modifiers),
+			ch => KeyEvent::new(KeyCode::Char(ch), key.modifiers),
+		},
+		KeyCode::Tab if key.modifiers.contains(Modifiers::SHIFT) => {
+			KeyEvent::new(
+				KeyCode::BackTab,
+				key.modifiers.without(Modifiers::SHIFT),
+			)
+		}
+		_ => key,
+	}
+}
+
+impl Display for KeyEvent {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.to_human_readable())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -369,4 +686,27 @@ mod tests {
 		let a = AsciiKeyCode::LowerA;
 		assert!(!a.is_seperator());
 	}
+
+	#[test]
+	fn key_notation_round_trips() {
+		let keys =
+			KeyEvent::parse_human_str("g<C-R><lt><A-S-Left><F5>").unwrap();
+		assert_eq!(keys, [
+			KeyEvent::from('g'),
+			KeyEvent::new(KeyCode::Char('r'), Modifiers::CTRL),
+			KeyEvent::from('<'),
+			KeyEvent::new(KeyCode::Left, Modifiers::ALT | Modifiers::SHIFT),
+			KeyEvent::plain(KeyCode::F(5)),
+		]);
+
+		let text: String = keys.iter().map(|k| k.to_human_readable()).collect();
+		assert_eq!(text, "g<C-r><lt><A-S-Left><F5>");
+
+		assert_eq!("<C-[>".parse(), Ok(KeyEvent::plain(KeyCode::Esc)));
+		assert_eq!(KeyEvent::from('\r'), KeyEvent::plain(KeyCode::Enter));
+		assert_eq!(
+			KeyEvent::from('\x17'),
+			KeyEvent::new(KeyCode::Char('w'), Modifiers::CTRL)
+		);
+	}
 }
@@ -2,6 +2,7 @@
 pub extern crate alloc;
 
 pub mod key;
+pub mod mouse;
 pub mod std;
 pub mod term;
 
@@ -0,0 +1,31 @@
+//! Mouse input, as terminals report it with mouse capture enabled.
+
+use crate::key::Modifiers;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum MouseButton {
+	Left,
+	Middle,
+	Right,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum MouseEventKind {
+	Down(MouseButton),
+	Up(MouseButton),
+	/// The pointer moved while the button was held.
+	Drag(MouseButton),
+	ScrollUp,
+	ScrollDown,
+	ScrollLeft,
+	ScrollRight,
+}
+
+/// A mouse event at a zero-based screen cell.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct MouseEvent {
+	pub kind:      MouseEventKind,
+	pub column:    u16,
+	pub row:       u16,
+	pub modifiers: Modifiers,
+}
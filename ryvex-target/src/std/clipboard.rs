@@ -0,0 +1,312 @@
+//! The system clipboard, reached through the helpers of the display server
+//! or, where there are none like over SSH, through the terminal.
+
+use alloc::{
+	boxed::Box,
+	format,
+	string::String,
+};
+use core::{
+	fmt,
+	str::FromStr,
+};
+
+use super::{
+	env::Environment,
+	fs::FileSystem,
+	path::{
+		Path,
+		PathScheme,
+	},
+	process::{
+		Shell,
+		ShellError,
+	},
+	write::Write,
+	Result,
+};
+use crate::term::command::{
+	terminal::SetClipboard,
+	ExecutableCommand,
+};
+
+/// Which of the system's clipboards is meant, `+` or `*` in Vim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClipboardKind {
+	/// The clipboard of copy and paste.
+	Clipboard,
+	/// The primary selection of X11 and Wayland, pasted with the middle
+	/// mouse button.
+	Selection,
+}
+
+impl ClipboardKind {
+	fn index(self) -> usize {
+		match self {
+			ClipboardKind::Clipboard => 0,
+			ClipboardKind::Selection => 1,
+		}
+	}
+}
+
+pub trait ClipboardProvider {
+	/// A short name of the backend, for the log.
+	fn name(&self) -> &'static str;
+
+	/// The text on the clipboard, empty if there is none.
+	fn get(&mut self, kind: ClipboardKind) -> Result<String>;
+
+	fn set(&mut self, kind: ClipboardKind, text: &str) -> Result<()>;
+}
+
+impl fmt::Debug for dyn ClipboardProvider {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "ClipboardProvider({})", self.name())
+	}
+}
+
+/// Copies through the terminal with OSC 52. Few terminals let the clipboard
+/// be read back, so pasting gives what was copied last from here.
+#[derive(Debug)]
+pub struct Osc52Provider<W: Write> {
+	writer: W,
+	copied: [String; 2],
+}
+
+impl<W: Write> Osc52Provider<W> {
+	pub fn new(writer: W) -> Self {
+		Self {
+			writer,
+			copied: Default::default(),
+		}
+	}
+}
+
+impl<W: Write> ClipboardProvider for Osc52Provider<W> {
+	fn name(&self) -> &'static str {
+		"osc52"
+	}
+
+	fn get(&mut self, kind: ClipboardKind) -> Result<String> {
+		Ok(self.copied[kind.index()].clone())
+	}
+
+	fn set(&mut self, kind: ClipboardKind, text: &str) -> Result<()> {
+		self.writer.execute(SetClipboard(kind, text))?;
+		self.copied[kind.index()] = text.into();
+		Ok(())
+	}
+}
+
+/// The command line tools that reach the clipboard of a display server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardHelper {
+	/// `wl-copy` and `wl-paste` on Wayland.
+	WlClipboard,
+	Xclip,
+	Xsel,
+}
+
+impl ClipboardHelper {
+	/// The helpers in the order they are looked for.
+	const ALL: [ClipboardHelper; 3] = [
+		ClipboardHelper::WlClipboard,
+		ClipboardHelper::Xclip,
+		ClipboardHelper::Xsel,
+	];
+
+	/// The command and arguments that copy stdin to the clipboard.
+	fn copy(
+		self,
+		kind: ClipboardKind,
+	) -> (&'static str, &'static [&'static str]) {
+		use ClipboardKind::*;
+
+		match (self, kind) {
+			(ClipboardHelper::WlClipboard, Clipboard) => {
+				("wl-copy", &["--type", "text/plain"])
+			}
+			(ClipboardHelper::WlClipboard, Selection) => {
+				("wl-copy", &["--primary", "--type", "text/plain"])
+			}
+			(ClipboardHelper::Xclip, Clipboard) => {
+				("xclip", &["-i", "-selection", "clipboard"])
+			}
+			(ClipboardHelper::Xclip, Selection) => {
+				("xclip", &["-i", "-selection", "primary"])
+			}
+			(ClipboardHelper::Xsel, Clipboard) => ("xsel", &["-i", "-b"]),
+			(ClipboardHelper::Xsel, Selection) => ("xsel", &["-i", "-p"]),
+		}
+	}
+
+	/// The command and arguments that write the clipboard to stdout.
+	fn paste(
+		self,
+		kind: ClipboardKind,
+	) -> (&'static str, &'static [&'static str]) {
+		use ClipboardKind::*;
+
+		match (self, kind) {
+			(ClipboardHelper::WlClipboard, Clipboard) => {
+				("wl-paste", &["--no-newline"])
+			}
+			(ClipboardHelper::WlClipboard, Selection) => {
+				("wl-paste", &["--primary", "--no-newline"])
+			}
+			(ClipboardHelper::Xclip, Clipboard) => {
+				("xclip", &["-o", "-selection", "clipboard"])
+			}
+			(ClipboardHelper::Xclip, Selection) => {
+				("xclip", &["-o", "-selection", "primary"])
+			}
+			(ClipboardHelper::Xsel, Clipboard) => ("xsel", &["-o", "-b"]),
+			(ClipboardHelper::Xsel, Selection) => ("xsel", &["-o", "-p"]),
+		}
+	}
+
+	/// Whether the display server the helper talks to is running.
+	fn has_display(self, env: &impl Environment) -> bool {
+		let var = match self {
+			ClipboardHelper::WlClipboard => "WAYLAND_DISPLAY",
+			ClipboardHelper::Xclip | ClipboardHelper::Xsel => "DISPLAY",
+		};
+		env.var(var).is_some_and(|display| !display.is_empty())
+	}
+}
+
+/// Runs one of the [`ClipboardHelper`]s through the [`Shell`].
+#[derive(Debug)]
+pub struct CommandProvider<Sh: Shell> {
+	helper: ClipboardHelper,
+	shell:  Sh,
+}
+
+impl<Sh: Shell> CommandProvider<Sh> {
+	pub fn new(helper: ClipboardHelper, shell: Sh) -> Self {
+		Self { helper, shell }
+	}
+}
+
+impl<Sh: Shell> ClipboardProvider for CommandProvider<Sh> {
+	fn name(&self) -> &'static str {
+		match self.helper {
+			ClipboardHelper::WlClipboard => "wl-clipboard",
+			ClipboardHelper::Xclip => "xclip",
+			ClipboardHelper::Xsel => "xsel",
+		}
+	}
+
+	fn get(&mut self, kind: ClipboardKind) -> Result<String> {
+		let (cmd, args) = self.helper.paste(kind);
+		let bytes = self.shell.output(cmd, args)?;
+		Ok(core::str::from_utf8(&bytes)?.into())
+	}
+
+	fn set(&mut self, kind: ClipboardKind, text: &str) -> Result<()> {
+		let (cmd, args) = self.helper.copy(kind);
+		let status =
+			self.shell.status_with_input(cmd, args, text.as_bytes())?;
+		if !status.success() {
+			return Err(ShellError::ExecutionFailed(cmd.into()).into());
+		}
+		Ok(())
+	}
+}
+
+/// Picks the first helper whose display server is running and that is
+/// installed, or OSC 52 through `writer` if there is none.
+pub fn detect<S, Sh, W>(
+	env: &impl Environment,
+	fs: &impl FileSystem<S>,
+	shell: Sh,
+	writer: W,
+) -> Box<dyn ClipboardProvider>
+where
+	S: PathScheme,
+	Sh: Shell + 'static,
+	W: Write + 'static,
+{
+	let helper = ClipboardHelper::ALL.into_iter().find(|helper| {
+		helper.has_display(env) &&
+			is_installed(env, fs, helper.copy(ClipboardKind::Clipboard).0) &&
+			is_installed(env, fs, helper.paste(ClipboardKind::Clipboard).0)
+	});
+
+	match helper {
+		Some(helper) => Box::new(CommandProvider::new(helper, shell)),
+		None => Box::new(Osc52Provider::new(writer)),
+	}
+}
+
+/// Whether `binary` is in one of the directories of `PATH`.
+fn is_installed<S: PathScheme>(
+	env: &impl Environment,
+	fs: &impl FileSystem<S>,
+	binary: &str,
+) -> bool {
+	let Some(dirs) = env.var("PATH") else {
+		return false;
+	};
+
+	dirs.split(':').filter(|dir| !dir.is_empty()).any(|dir| {
+		Path::<S>::from_str(&format!("{dir}{}{binary}", S::MAIN_SEPARATOR))
+			.ok()
+			.and_then(|path| fs.metadata(&path).ok())
+			.is_some_and(|metadata| !metadata.is_dir)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{
+		string::ToString,
+		vec,
+		vec::Vec,
+	};
+	use core::cell::RefCell;
+
+	use super::*;
+	use crate::std::process::{
+		Command,
+		Exitstatus,
+		Output,
+	};
+
+	/// Records the commands it is asked to run and answers them with
+	/// whatever was piped in last.
+	#[derive(Default)]
+	struct Recorder {
+		runs:  RefCell<Vec<String>>,
+		piped: RefCell<Vec<u8>>,
+	}
+
+	impl Shell for &Recorder {
+		fn run(&self, command: &Command) -> Result<Output> {
+			self.runs.borrow_mut().push(command.to_string());
+			if let Some(input) = &command.input {
+				*self.piped.borrow_mut() = input.clone();
+			}
+
+			Ok(Output {
+				status: Exitstatus::SUCCESS,
+				stdout: self.piped.borrow().clone(),
+				stderr: Vec::new(),
+			})
+		}
+	}
+
+	#[test]
+	fn helper_commands() {
+		let shell = Recorder::default();
+		let mut clipboard =
+			CommandProvider::new(ClipboardHelper::Xclip, &shell);
+
+		clipboard.set(ClipboardKind::Selection, "ünï").unwrap();
+		assert_eq!(clipboard.get(ClipboardKind::Selection).unwrap(), "ünï");
+		assert_eq!(*shell.runs.borrow(), vec![
+			"xclip -i -selection primary".to_string(),
+			"xclip -o -selection primary".to_string(),
+		]);
+	}
+}
@@ -14,6 +14,7 @@ use super::{
 use alloc::{
 	format,
 	string::String,
+	vec::Vec,
 };
 use core::{
 	fmt,
@@ -81,6 +82,18 @@ pub trait File<S: PathScheme> {
 		Ok(())
 	}
 
+	fn read_to_end(&mut self, out: &mut Vec<u8>) -> Result<()> {
+		let mut tmp = [0u8; 4096];
+		loop {
+			let n = self.read(&mut tmp)?;
+			if n == 0 {
+				break;
+			}
+			out.extend_from_slice(&tmp[..n]);
+		}
+		Ok(())
+	}
+
 	fn write_all(&mut self, buf: &[u8]) -> Result<()> {
 		let mut off = 0;
 		while off < buf.len() {
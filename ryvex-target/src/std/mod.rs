@@ -5,6 +5,7 @@ use core::{
 
 use error::Error;
 
+pub mod clipboard;
 pub mod env;
 pub mod error;
 pub mod fs;
@@ -1,49 +1,208 @@
 use super::{
 	error::Error,
 	Result,
-	StdError,
 };
 use alloc::{
 	format,
-	string::String,
+	string::{
+		String,
+		ToString,
+	},
+	vec::Vec,
 };
 use core::fmt::Display;
 
 pub trait Shell {
+	/// Runs `command` to completion and returns how it exited, along with
+	/// the output it was asked to capture
+	fn run(&self, command: &Command) -> Result<Output>;
+
 	/// Executes a command and returns its status
-	fn status(&self, cmd: &str, args: &[&str]) -> Result<Exitstatus>;
+	fn status(&self, cmd: &str, args: &[&str]) -> Result<Exitstatus> {
+		let command = Command::new(cmd)
+			.args(args)
+			.stdout(Stdio::Null)
+			.stderr(Stdio::Null);
+		Ok(self.run(&command)?.status)
+	}
+
+	/// Executes a command with `input` on its stdin and returns its status
+	fn status_with_input(
+		&self,
+		cmd: &str,
+		args: &[&str],
+		input: &[u8],
+	) -> Result<Exitstatus> {
+		// clipboard helpers like `xclip` stay around to serve the selection,
+		// so their output is not waited for
+		let command = Command::new(cmd)
+			.args(args)
+			.input(input)
+			.stdout(Stdio::Null)
+			.stderr(Stdio::Null);
+		Ok(self.run(&command)?.status)
+	}
+
+	/// Executes a command and returns what it wrote to stdout, failing
+	/// unless it exits successfully
+	fn output(&self, cmd: &str, args: &[&str]) -> Result<Vec<u8>> {
+		let command = Command::new(cmd).args(args);
+		Ok(self.run(&command)?.exit_ok(&command)?.stdout)
+	}
+}
+
+/// Where the output of a [`Command`] goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stdio {
+	/// Thrown away.
+	Null,
+	/// Captured into the [`Output`].
+	Piped,
+}
+
+/// A command to [run](Shell::run), set up like `std::process::Command`. Its
+/// stdin is empty unless [`input`](Command::input) is given, and both
+/// stdout and stderr are captured by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+	pub program: String,
+	pub args:    Vec<String>,
+	/// The working directory, or the editor's own if `None`.
+	pub cwd:     Option<String>,
+	/// Variables set on top of the editor's environment.
+	pub env:     Vec<(String, String)>,
+	pub input:   Option<Vec<u8>>,
+	pub stdout:  Stdio,
+	pub stderr:  Stdio,
+}
+
+impl Command {
+	pub fn new(program: &str) -> Self {
+		Self {
+			program: program.into(),
+			args:    Vec::new(),
+			cwd:     None,
+			env:     Vec::new(),
+			input:   None,
+			stdout:  Stdio::Piped,
+			stderr:  Stdio::Piped,
+		}
+	}
+
+	pub fn arg(mut self, arg: &str) -> Self {
+		self.args.push(arg.into());
+		self
+	}
+
+	pub fn args(mut self, args: &[&str]) -> Self {
+		self.args.extend(args.iter().map(|arg| arg.to_string()));
+		self
+	}
+
+	pub fn current_dir(mut self, dir: &str) -> Self {
+		self.cwd = Some(dir.into());
+		self
+	}
+
+	pub fn env(mut self, key: &str, val: &str) -> Self {
+		self.env.push((key.into(), val.into()));
+		self
+	}
+
+	/// Bytes written to the command's stdin, which is closed after them.
+	pub fn input(mut self, input: &[u8]) -> Self {
+		self.input = Some(input.to_vec());
+		self
+	}
+
+	pub fn stdout(mut self, stdout: Stdio) -> Self {
+		self.stdout = stdout;
+		self
+	}
+
+	pub fn stderr(mut self, stderr: Stdio) -> Self {
+		self.stderr = stderr;
+		self
+	}
 }
 
-#[repr(i32)]
+impl Display for Command {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.program)?;
+		for arg in &self.args {
+			write!(f, " {arg}")?;
+		}
+		Ok(())
+	}
+}
+
+/// How a command ended and what it wrote. Output that was not
+/// [piped](Stdio::Piped) is empty.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+	pub status: Exitstatus,
+	pub stdout: Vec<u8>,
+	pub stderr: Vec<u8>,
+}
+
+impl Output {
+	/// Turns an unsuccessful exit of `command` into an error carrying its
+	/// stderr.
+	pub fn exit_ok(self, command: &Command) -> Result<Output> {
+		if self.status.success() {
+			return Ok(self);
+		}
+
+		let stderr = String::from_utf8_lossy(&self.stderr);
+		Err(ShellError::Failed {
+			command: command.to_string(),
+			status:  self.status,
+			stderr:  stderr.trim_end().into(),
+		}
+		.into())
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Exitstatus {
-	Success = 0,
-	Failure = 1,
+	/// The command exited with this code.
+	Code(i32),
+	/// The command was killed by this signal.
+	Signal(i32),
 }
 
 impl Exitstatus {
-	pub fn from_code(code: i32) -> Result<Self> {
-		match code {
-			x if x == Exitstatus::Success as i32 => Ok(Exitstatus::Success),
-			x if x == Exitstatus::Failure as i32 => Ok(Exitstatus::Failure),
-			_ => Err(ShellError::InvalidExitCode(code).into()),
+	pub const SUCCESS: Exitstatus = Exitstatus::Code(0);
+
+	pub fn code(&self) -> Option<i32> {
+		match self {
+			Exitstatus::Code(code) => Some(*code),
+			Exitstatus::Signal(_) => None,
+		}
+	}
+
+	pub fn signal(&self) -> Option<i32> {
+		match self {
+			Exitstatus::Code(_) => None,
+			Exitstatus::Signal(signal) => Some(*signal),
 		}
 	}
 
 	pub fn success(&self) -> bool {
-		self == &Self::Success
+		self == &Self::SUCCESS
 	}
 
 	pub fn failure(&self) -> bool {
-		self == &Self::Failure
+		!self.success()
 	}
 }
 
-impl TryFrom<i32> for Exitstatus {
-	type Error = StdError;
-
-	fn try_from(value: i32) -> core::result::Result<Self, Self::Error> {
-		Exitstatus::from_code(value)
+impl Display for Exitstatus {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Exitstatus::Code(code) => write!(f, "exit code {code}"),
+			Exitstatus::Signal(signal) => write!(f, "signal {signal}"),
+		}
 	}
 }
 
@@ -51,7 +210,12 @@ impl TryFrom<i32> for Exitstatus {
 pub enum ShellError {
 	ExecutionFailed(String),
 	CommandNotFound(String),
-	InvalidExitCode(i32),
+	/// The command ran, but did not exit successfully.
+	Failed {
+		command: String,
+		status:  Exitstatus,
+		stderr:  String,
+	},
 }
 
 impl Error for ShellError {
@@ -59,7 +223,7 @@ impl Error for ShellError {
 		match self {
 			ShellError::ExecutionFailed(_) => None,
 			ShellError::CommandNotFound(_) => None,
-			ShellError::InvalidExitCode(_) => None,
+			ShellError::Failed { .. } => None,
 		}
 	}
 }
@@ -73,8 +237,19 @@ impl Display for ShellError {
 			ShellError::CommandNotFound(s) => {
 				format!("command '{}' not found", s)
 			}
-			ShellError::InvalidExitCode(i) => {
-				format!("recieved invalid exit code '{}'", i)
+			ShellError::Failed {
+				command,
+				status,
+				stderr,
+			} => {
+				if stderr.is_empty() {
+					format!("command '{}' failed with {}", command, status)
+				} else {
+					format!(
+						"command '{}' failed with {}:\n{}",
+						command, status, stderr
+					)
+				}
 			}
 		};
 
@@ -0,0 +1,329 @@
+//! What the terminal can do, pieced together from the environment, the
+//! terminfo entry for `TERM`, and what the terminal answers when asked.
+
+use alloc::{
+	format,
+	string::String,
+	vec::Vec,
+};
+use core::{
+	str::FromStr,
+	time::Duration,
+};
+
+use ryvex_ui::style::ColorDepth;
+
+use crate::{
+	csi,
+	std::{
+		env::Environment,
+		fs::{
+			File,
+			FileSystem,
+			OpenOptions,
+		},
+		path::{
+			Path,
+			PathScheme,
+		},
+	},
+	target,
+	term::terminfo::Terminfo,
+};
+
+/// Asks for the kitty keyboard flags, the state of synchronized output
+/// (DEC mode 2026), the terminal's name and version (XTVERSION) and its
+/// primary device attributes (DA1). Every terminal answers the last one, so
+/// its reply marks the end of the replies to the others.
+const QUERIES: &str =
+	concat!(csi!("?u"), csi!("?2026$p"), csi!(">0q"), csi!("c"));
+
+/// How long to wait for the replies of a terminal that does not answer.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Where terminfo entries are looked for after `$TERMINFO`, `~/.terminfo`
+/// and `$TERMINFO_DIRS`.
+const TERMINFO_DIRS: [&str; 4] = [
+	"/etc/terminfo",
+	"/lib/terminfo",
+	"/usr/share/terminfo",
+	"/usr/lib/terminfo",
+];
+
+/// Terminals that report themselves through XTVERSION and draw true color.
+const TRUECOLOR_TERMINALS: [&str; 5] =
+	["kitty", "WezTerm", "foot", "ghostty", "iTerm2"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+	pub color_depth:          ColorDepth,
+	/// The cursor shape can be changed with `DECSCUSR`.
+	pub cursor_style:         bool,
+	/// The kitty keyboard protocol, see
+	/// [`PushKeyboardEnhancement`](super::command::terminal::PushKeyboardEnhancement).
+	pub keyboard_enhancement: bool,
+	/// Frames can be drawn atomically with DEC mode 2026.
+	pub synchronized_output:  bool,
+	/// The name and version the terminal reported for itself.
+	pub version:              Option<String>,
+}
+
+impl Default for Capabilities {
+	fn default() -> Self {
+		Self {
+			color_depth:          ColorDepth::Ansi16,
+			cursor_style:         false,
+			keyboard_enhancement: false,
+			synchronized_output:  false,
+			version:              None,
+		}
+	}
+}
+
+impl Capabilities {
+	/// Detects the capabilities of the controlling terminal. The terminal
+	/// has to be in raw mode, and nothing else may be reading its input yet.
+	pub fn detect<S: PathScheme>(
+		env: &impl Environment,
+		fs: &impl FileSystem<S>,
+	) -> Capabilities {
+		let mut caps = Capabilities::from_env(env);
+
+		let terminfo = env
+			.var("TERM")
+			.and_then(|term| read_terminfo(env, fs, &term));
+		if let Some(info) = terminfo {
+			caps.apply_terminfo(&info);
+		}
+
+		let answered = |bytes: &[u8]| {
+			split_replies(bytes)
+				.0
+				.iter()
+				.any(|reply| matches!(reply, Reply::Attributes))
+		};
+		if let Ok(bytes) = target::term::query(QUERIES, QUERY_TIMEOUT, answered)
+		{
+			let (replies, input) = split_replies(&bytes);
+			for reply in replies {
+				caps.apply_reply(reply);
+			}
+			// keys typed while the replies came in
+			target::term::unread(input);
+		}
+
+		caps
+	}
+
+	/// What `TERM` and `COLORTERM` tell about the terminal.
+	pub fn from_env(env: &impl Environment) -> Capabilities {
+		let term = env.var("TERM").unwrap_or_default();
+		let colorterm = env.var("COLORTERM").unwrap_or_default();
+
+		let color_depth = if colorterm == "truecolor" ||
+			colorterm == "24bit" ||
+			env.var("WT_SESSION").is_some()
+		{
+			ColorDepth::TrueColor
+		} else if term.contains("256color") {
+			ColorDepth::Indexed256
+		} else {
+			ColorDepth::Ansi16
+		};
+
+		Capabilities {
+			color_depth,
+			// the Linux console has a single cursor shape
+			cursor_style: !matches!(term.as_str(), "" | "dumb" | "linux"),
+			..Capabilities::default()
+		}
+	}
+
+	pub fn apply_terminfo(&mut self, info: &Terminfo) {
+		if info.has_ext_flag("Tc") || info.has_ext_flag("RGB") {
+			self.color_depth = ColorDepth::TrueColor;
+		} else if info.max_colors().is_some_and(|colors| colors >= 256) {
+			self.color_depth = self.color_depth.max(ColorDepth::Indexed256);
+		}
+		if info.ext_string("Ss").is_some() {
+			self.cursor_style = true;
+		}
+		if info.ext_string("Sync").is_some() {
+			self.synchronized_output = true;
+		}
+	}
+
+	fn apply_reply(&mut self, reply: Reply) {
+		match reply {
+			Reply::Attributes => {}
+			Reply::KeyboardFlags => self.keyboard_enhancement = true,
+			// 1 and 2 are set and reset, 0 and 4 unknown and unsupported
+			Reply::Mode { mode: 2026, state } => {
+				self.synchronized_output = matches!(state, 1 | 2)
+			}
+			Reply::Mode { .. } => {}
+			Reply::Version(version) => {
+				// terminals recent enough to report a version take DECSCUSR
+				self.cursor_style = true;
+				if TRUECOLOR_TERMINALS
+					.iter()
+					.any(|name| version.starts_with(name))
+				{
+					self.color_depth = ColorDepth::TrueColor;
+				}
+				self.version = Some(version);
+			}
+		}
+	}
+}
+
+/// Reads the compiled terminfo entry for `term` from the first directory
+/// that has it. Entries sit below the first letter of their name, or on
+/// macOS below its hex code.
+fn read_terminfo<S: PathScheme>(
+	env: &impl Environment,
+	fs: &impl FileSystem<S>,
+	term: &str,
+) -> Option<Terminfo> {
+	let first = term.chars().next()?;
+
+	let mut dirs = Vec::new();
+	dirs.extend(env.var("TERMINFO"));
+	dirs.extend(env.var("HOME").map(|home| format!("{home}/.terminfo")));
+	if let Some(list) = env.var("TERMINFO_DIRS") {
+		// an empty element stands for the default directories
+		dirs.extend(
+			list.split(':')
+				.filter(|dir| !dir.is_empty())
+				.map(String::from),
+		);
+	}
+	dirs.extend(TERMINFO_DIRS.iter().map(|dir| String::from(*dir)));
+
+	dirs.iter()
+		.flat_map(|dir| {
+			[
+				format!("{dir}/{first}/{term}"),
+				format!("{dir}/{:x}/{term}", first as u32),
+			]
+		})
+		.find_map(|path| {
+			let path = Path::<S>::from_str(&path).ok()?;
+			let mut file = fs.open(&path, OpenOptions::read_only()).ok()?;
+			let mut bytes = Vec::new();
+			file.read_to_end(&mut bytes).ok()?;
+			Terminfo::parse(&bytes)
+		})
+}
+
+/// A reply to one of the [`QUERIES`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reply {
+	/// `CSI ? Ps ; ... c`
+	Attributes,
+	/// `CSI ? flags u`
+	KeyboardFlags,
+	/// `CSI ? mode ; state $ y`
+	Mode { mode: u16, state: u8 },
+	/// `DCS > | text ST`
+	Version(String),
+}
+
+/// Picks the replies out of `bytes`, along with whatever else was read with
+/// them.
+fn split_replies(bytes: &[u8]) -> (Vec<Reply>, Vec<u8>) {
+	let mut replies = Vec::new();
+	let mut rest = Vec::new();
+
+	let mut i = 0;
+	while i < bytes.len() {
+		match parse_reply(&bytes[i..]) {
+			Some((reply, len)) => {
+				replies.push(reply);
+				i += len;
+			}
+			None => {
+				rest.push(bytes[i]);
+				i += 1;
+			}
+		}
+	}
+
+	(replies, rest)
+}
+
+fn parse_reply(buf: &[u8]) -> Option<(Reply, usize)> {
+	if let Some(text) = buf.strip_prefix(b"\x1bP>|") {
+		let end = text.windows(2).position(|w| w == b"\x1b\\")?;
+		let version = String::from_utf8_lossy(&text[..end]).into_owned();
+		return Some((Reply::Version(version), 4 + end + 2));
+	}
+
+	let params = buf.strip_prefix(b"\x1b[?")?;
+	let len = params
+		.iter()
+		.position(|b| !(b.is_ascii_digit() || *b == b';' || *b == b'$'))?;
+	let reply = match (&params[..len], params[len]) {
+		(_, b'c') => Reply::Attributes,
+		(_, b'u') => Reply::KeyboardFlags,
+		(mode, b'y') => {
+			let (mode, state) = core::str::from_utf8(mode.strip_suffix(b"$")?)
+				.ok()?
+				.split_once(';')?;
+			Reply::Mode {
+				mode:  mode.parse().ok()?,
+				state: state.parse().ok()?,
+			}
+		}
+		_ => return None,
+	};
+
+	Some((reply, 3 + len + 1))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replies() {
+		let bytes =
+			b"\x1b[?1u\x1b[?2026;2$yj\x1bP>|kitty(0.35.2)\x1b\\\x1b[?62;22c";
+		let (replies, rest) = split_replies(bytes);
+
+		assert_eq!(replies, [
+			Reply::KeyboardFlags,
+			Reply::Mode {
+				mode:  2026,
+				state: 2,
+			},
+			Reply::Version(String::from("kitty(0.35.2)")),
+			Reply::Attributes,
+		]);
+		assert_eq!(rest, b"j");
+
+		let mut caps = Capabilities::default();
+		for reply in replies {
+			caps.apply_reply(reply);
+		}
+		assert!(caps.keyboard_enhancement);
+		assert!(caps.synchronized_output);
+		assert!(caps.cursor_style);
+		assert_eq!(caps.color_depth, ColorDepth::TrueColor);
+	}
+
+	#[test]
+	fn unanswered() {
+		// a terminal without the kitty protocol or XTVERSION
+		let (replies, rest) = split_replies(b"\x1b[?2026;0$y\x1b[?1;2c");
+		let mut caps = Capabilities::default();
+		for reply in replies {
+			caps.apply_reply(reply);
+		}
+
+		assert!(rest.is_empty());
+		assert!(!caps.keyboard_enhancement);
+		assert!(!caps.synchronized_output);
+		assert_eq!(caps.version, None);
+	}
+}
@@ -12,6 +12,7 @@ use crate::{
 };
 
 pub mod cursor;
+pub mod style;
 pub mod terminal;
 
 pub trait Command: WriteAnsi + ExecuteApi {}
@@ -0,0 +1,153 @@
+use core::fmt;
+
+use ryvex_ui::style::Color;
+
+use crate::csi;
+
+use super::WriteAnsi;
+
+/// Text attributes as SGR turns them on and off. Each `No*` undoes the one
+/// before it; `NormalIntensity` ends both `Bold` and `Dim`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attribute {
+	/// Back to the default colors and no attributes.
+	Reset,
+	Bold,
+	Dim,
+	Italic,
+	Underlined,
+	Blink,
+	Reverse,
+	Hidden,
+	CrossedOut,
+	NormalIntensity,
+	NoItalic,
+	NoUnderline,
+	NoBlink,
+	NoReverse,
+	NoHidden,
+	NotCrossedOut,
+}
+
+impl Attribute {
+	fn sgr(self) -> u8 {
+		match self {
+			Attribute::Reset => 0,
+			Attribute::Bold => 1,
+			Attribute::Dim => 2,
+			Attribute::Italic => 3,
+			Attribute::Underlined => 4,
+			Attribute::Blink => 5,
+			Attribute::Reverse => 7,
+			Attribute::Hidden => 8,
+			Attribute::CrossedOut => 9,
+			Attribute::NormalIntensity => 22,
+			Attribute::NoItalic => 23,
+			Attribute::NoUnderline => 24,
+			Attribute::NoBlink => 25,
+			Attribute::NoReverse => 27,
+			Attribute::NoHidden => 28,
+			Attribute::NotCrossedOut => 29,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetAttribute(pub Attribute);
+
+impl WriteAnsi for SetAttribute {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		write!(f, csi!("{}m"), self.0.sgr())
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetForegroundColor(pub Color);
+
+impl WriteAnsi for SetForegroundColor {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		write_color(f, self.0, 30)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetBackgroundColor(pub Color);
+
+impl WriteAnsi for SetBackgroundColor {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		write_color(f, self.0, 40)
+	}
+}
+
+/// Puts both colors back to the terminal's defaults, leaving the attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetColor;
+
+impl WriteAnsi for ResetColor {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("39;49m"))
+	}
+}
+
+/// Writes the SGR sequence for `color`, where `base` is 30 for the
+/// foreground and 40 for the background.
+fn write_color(f: &mut impl fmt::Write, color: Color, base: u8) -> fmt::Result {
+	let (code, bright) = match color {
+		Color::Reset => return write!(f, csi!("{}m"), base + 9),
+		Color::Indexed(index) => {
+			return write!(f, csi!("{};5;{}m"), base + 8, index)
+		}
+		Color::Rgb(r, g, b) => {
+			return write!(f, csi!("{};2;{};{};{}m"), base + 8, r, g, b)
+		}
+		Color::Black => (0, false),
+		Color::Red => (1, false),
+		Color::Green => (2, false),
+		Color::Yellow => (3, false),
+		Color::Blue => (4, false),
+		Color::Magenta => (5, false),
+		Color::Cyan => (6, false),
+		Color::Gray => (7, false),
+		Color::DarkGray => (0, true),
+		Color::LightRed => (1, true),
+		Color::LightGreen => (2, true),
+		Color::LightYellow => (3, true),
+		Color::LightBlue => (4, true),
+		Color::LightMagenta => (5, true),
+		Color::LightCyan => (6, true),
+		Color::White => (7, true),
+	};
+
+	// the bright colors sit 60 above the normal ones
+	let offset = if bright { 60 } else { 0 };
+	write!(f, csi!("{}m"), base + offset + code)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+
+	use super::*;
+
+	fn ansi(command: impl WriteAnsi) -> String {
+		let mut out = String::new();
+		command.write_ansi(&mut out).unwrap();
+		out
+	}
+
+	#[test]
+	fn colors() {
+		assert_eq!(ansi(SetForegroundColor(Color::Red)), "\x1b[31m");
+		assert_eq!(ansi(SetBackgroundColor(Color::White)), "\x1b[107m");
+		assert_eq!(ansi(SetForegroundColor(Color::Reset)), "\x1b[39m");
+		assert_eq!(
+			ansi(SetBackgroundColor(Color::Indexed(238))),
+			"\x1b[48;5;238m"
+		);
+		assert_eq!(
+			ansi(SetForegroundColor(Color::Rgb(1, 2, 3))),
+			"\x1b[38;2;1;2;3m"
+		);
+		assert_eq!(ansi(SetAttribute(Attribute::NoReverse)), "\x1b[27m");
+	}
+}
@@ -1,7 +1,10 @@
 use core::fmt;
 use core::fmt::Display;
 
-use crate::csi;
+use crate::{
+	csi,
+	std::clipboard::ClipboardKind,
+};
 
 use super::WriteAnsi;
 
@@ -62,6 +65,49 @@ impl WriteAnsi for Clear {
 	}
 }
 
+/// Limits scrolling to the rows `top..bottom`, so [`ScrollUp`] and
+/// [`ScrollDown`] leave the rows around them alone. This command is zero
+/// based and moves the cursor to the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetScrollRegion(pub u16, pub u16);
+
+impl WriteAnsi for SetScrollRegion {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		write!(f, csi!("{};{}r"), self.0 + 1, self.1)
+	}
+}
+
+/// Makes the whole screen scroll again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetScrollRegion;
+
+impl WriteAnsi for ResetScrollRegion {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("r"))
+	}
+}
+
+/// Makes the terminal hold back what it draws until
+/// [`EndSynchronizedUpdate`], so a frame shows up at once instead of line by
+/// line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeginSynchronizedUpdate;
+
+impl WriteAnsi for BeginSynchronizedUpdate {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?2026h"))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndSynchronizedUpdate;
+
+impl WriteAnsi for EndSynchronizedUpdate {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?2026l"))
+	}
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SetSize(pub u16, pub u16);
 
@@ -85,3 +131,147 @@ impl<T: Display> Display for Print<T> {
 		self.0.fmt(f)
 	}
 }
+
+/// Makes the terminal report mouse presses, releases, drags and the wheel
+/// in the SGR (1006) encoding, which has no limit on coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnableMouseCapture;
+
+impl WriteAnsi for EnableMouseCapture {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(concat!(csi!("?1000h"), csi!("?1002h"), csi!("?1006h")))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisableMouseCapture;
+
+impl WriteAnsi for DisableMouseCapture {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(concat!(csi!("?1006l"), csi!("?1002l"), csi!("?1000l")))
+	}
+}
+
+/// Makes the terminal mark pasted text with `ESC [ 200 ~` and `ESC [ 201 ~`,
+/// so it can be told apart from typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnableBracketedPaste;
+
+impl WriteAnsi for EnableBracketedPaste {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?2004h"))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisableBracketedPaste;
+
+impl WriteAnsi for DisableBracketedPaste {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?2004l"))
+	}
+}
+
+/// Switches to the alternate screen, which has no scrollback and is put
+/// away again with [`LeaveAlternateScreen`], bringing back the shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnterAlternateScreen;
+
+impl WriteAnsi for EnterAlternateScreen {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?1049h"))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaveAlternateScreen;
+
+impl WriteAnsi for LeaveAlternateScreen {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("?1049l"))
+	}
+}
+
+/// Asks terminals with the kitty keyboard protocol to report keys that are
+/// ambiguous as plain bytes, like Alt-`[` or a lone Esc, as `CSI u`
+/// sequences. Others ignore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PushKeyboardEnhancement;
+
+impl WriteAnsi for PushKeyboardEnhancement {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!(">1u"))
+	}
+}
+
+/// Undoes [`PushKeyboardEnhancement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PopKeyboardEnhancement;
+
+impl WriteAnsi for PopKeyboardEnhancement {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(csi!("<u"))
+	}
+}
+
+/// Puts text on the system clipboard or primary selection through the
+/// terminal (OSC 52), which also reaches the clipboard of the machine the
+/// terminal runs on when the editor runs over SSH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetClipboard<'a>(pub ClipboardKind, pub &'a str);
+
+impl WriteAnsi for SetClipboard<'_> {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		let target = match self.0 {
+			ClipboardKind::Clipboard => 'c',
+			ClipboardKind::Selection => 'p',
+		};
+		write!(f, "\x1b]52;{target};")?;
+		write_base64(f, self.1.as_bytes())?;
+		f.write_str("\x1b\\")
+	}
+}
+
+const BASE64: &[u8; 64] =
+	b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn write_base64(f: &mut impl fmt::Write, bytes: &[u8]) -> fmt::Result {
+	for chunk in bytes.chunks(3) {
+		let b = [
+			chunk[0],
+			chunk.get(1).copied().unwrap_or(0),
+			chunk.get(2).copied().unwrap_or(0),
+		];
+		let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				let sextet = (n >> (18 - 6 * i)) & 0x3f;
+				f.write_char(BASE64[sextet as usize] as char)?;
+			} else {
+				f.write_char('=')?;
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+
+	use super::*;
+
+	#[test]
+	fn clipboard() {
+		let mut out = String::new();
+		SetClipboard(ClipboardKind::Clipboard, "hello")
+			.write_ansi(&mut out)
+			.unwrap();
+		assert_eq!(out, "\x1b]52;c;aGVsbG8=\x1b\\");
+
+		out.clear();
+		write_base64(&mut out, b"ab").unwrap();
+		write_base64(&mut out, b"abc").unwrap();
+		assert_eq!(out, "YWI=YWJj");
+	}
+}
@@ -1,16 +1,28 @@
-use alloc::boxed::Box;
+use alloc::{
+	boxed::Box,
+	string::String,
+};
 use core::time::Duration;
 
 use crate::{
-	key::AsciiKeyCode,
+	key::KeyEvent,
+	mouse::MouseEvent,
 	std::Result,
 	target::term::TargetEventSource,
 };
 
-#[derive(PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub enum Event {
-	Key(AsciiKeyCode),
+	Key(KeyEvent),
+	Mouse(MouseEvent),
+	/// Text pasted into the terminal, with `\n` line breaks.
+	Paste(String),
+	/// The terminal was resized to this many columns and rows.
 	Resize(u16, u16),
+	/// No input arrived for a tick; see [`SyncEventStream::ticking`].
+	Tick,
+	/// The process was asked to terminate, by `SIGTERM` or `SIGHUP`.
+	Terminate,
 }
 
 pub trait EventSource: Sync + Send {
@@ -20,6 +32,7 @@ pub trait EventSource: Sync + Send {
 
 pub struct SyncEventStream {
 	inner: Box<dyn EventSource>,
+	tick:  Option<Duration>,
 }
 
 impl SyncEventStream {
@@ -28,22 +41,32 @@ impl SyncEventStream {
 
 		Ok(Self {
 			inner: Box::new(source),
+			tick:  None,
 		})
 	}
+
+	/// Makes the stream yield [`Event::Tick`] whenever no input arrives for
+	/// `interval`, instead of blocking until it does.
+	pub fn ticking(mut self, interval: Duration) -> Self {
+		self.tick = Some(interval);
+		self
+	}
 }
 
 impl Iterator for SyncEventStream {
 	type Item = Result<Event>;
 
-	/// Block indefinitely until an event is available.
+	/// Block until an event is available, or until a tick passes.
 	fn next(&mut self) -> Option<Self::Item> {
-		match self.inner.try_read(None) {
-			Ok(Some(event)) => Some(Ok(event)),
-			Ok(None) => {
-				// If we get None, simply try again.
-				self.next()
+		loop {
+			match self.inner.try_read(self.tick) {
+				Ok(Some(event)) => return Some(Ok(event)),
+				Ok(None) if self.tick.is_some() => {
+					return Some(Ok(Event::Tick))
+				}
+				Ok(None) => {}
+				Err(e) => return Some(Err(e)),
 			}
-			Err(e) => Some(Err(e)),
 		}
 	}
 }
@@ -1,3 +1,6 @@
+pub mod capabilities;
 pub mod command;
 pub mod console;
 pub mod event;
+pub mod parse;
+pub mod terminfo;
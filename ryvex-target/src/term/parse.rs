@@ -0,0 +1,412 @@
+//! Decodes the bytes a terminal sends into events.
+//!
+//! Besides plain characters and control codes, terminals encode keys that
+//! have no character of their own as escape sequences: `CSI` sequences
+//! (`ESC [`) like `ESC [ A` for Up or `ESC [ 1 ; 5 C` for Ctrl-Right, and
+//! `SS3` sequences (`ESC O`) like `ESC O P` for F1. An `ESC` followed by any
+//! other key is that key with Alt held. Text beyond ASCII arrives as UTF-8,
+//! and mouse events as SGR sequences like `ESC [ < 0 ; 10 ; 5 M`. With
+//! bracketed paste, pasted text comes between `ESC [ 200 ~` and `ESC [ 201 ~`.
+
+use alloc::string::String;
+
+use crate::{
+	key::{
+		normalize,
+		AsciiKeyCode,
+		KeyCode,
+		KeyEvent,
+		Modifiers,
+	},
+	mouse::{
+		MouseButton,
+		MouseEvent,
+		MouseEventKind,
+	},
+	term::event::Event,
+};
+
+const ESC: u8 = 0x1b;
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// What the start of an input buffer decodes to.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Parsed {
+	/// An event and the number of bytes it took.
+	Event(Event, usize),
+	/// A sequence that is well-formed but means nothing to us, and its
+	/// length. The bytes should be dropped.
+	Ignored(usize),
+	/// The bytes start a sequence that has not fully arrived yet.
+	Incomplete,
+}
+
+/// Decodes the first event in `buf`, which must not be empty.
+///
+/// `more` tells whether further bytes may still be on their way. Without
+/// it, a sequence cut short is taken for what it looks like so far: a lone
+/// `ESC` is the Escape key and `ESC [` is Alt-`[`.
+pub fn parse_event(buf: &[u8], more: bool) -> Parsed {
+	match buf {
+		[] => Parsed::Incomplete,
+		_ if buf.starts_with(PASTE_START) => parse_paste(buf),
+		[ESC] if more => Parsed::Incomplete,
+		[ESC] => key(KeyEvent::plain(KeyCode::Esc), 1),
+		[ESC, b'[', ..] => match parse_csi(&buf[2..]) {
+			Some(parsed) => parsed.offset(2),
+			None if more => Parsed::Incomplete,
+			None => alt(b'[', 2),
+		},
+		[ESC, b'O'] if more => Parsed::Incomplete,
+		[ESC, b'O', final_byte, ..] => match ss3_key(*final_byte) {
+			Some(code) => key(KeyEvent::plain(code), 3),
+			None => alt(b'O', 2),
+		},
+		[ESC, ESC, ..] => key(KeyEvent::plain(KeyCode::Esc), 1),
+		[ESC, byte, ..] if byte.is_ascii() => alt(*byte, 2),
+		[ESC, ..] => match parse_utf8(&buf[1..]) {
+			Parsed::Event(Event::Key(mut event), len) => {
+				event.modifiers |= Modifiers::ALT;
+				key(event, len + 1)
+			}
+			// not a character, so the escape stands on its own
+			Parsed::Ignored(_) => key(KeyEvent::plain(KeyCode::Esc), 1),
+			parsed => parsed,
+		},
+		[byte, ..] if byte.is_ascii() => {
+			key(AsciiKeyCode::from_ascii(*byte).into(), 1)
+		}
+		_ => parse_utf8(buf),
+	}
+}
+
+impl Parsed {
+	fn offset(self, by: usize) -> Self {
+		match self {
+			Parsed::Event(event, len) => Parsed::Event(event, len + by),
+			Parsed::Ignored(len) => Parsed::Ignored(len + by),
+			Parsed::Incomplete => Parsed::Incomplete,
+		}
+	}
+}
+
+fn key(key: KeyEvent, len: usize) -> Parsed {
+	Parsed::Event(Event::Key(key), len)
+}
+
+fn alt(byte: u8, len: usize) -> Parsed {
+	let mut event = KeyEvent::from(AsciiKeyCode::from_ascii(byte));
+	event.modifiers |= Modifiers::ALT;
+	key(event, len)
+}
+
+/// Decodes a bracketed paste, which stays incomplete until its end marker
+/// arrives, however long that takes. Terminals send line breaks as `\r`.
+fn parse_paste(buf: &[u8]) -> Parsed {
+	let body = &buf[PASTE_START.len()..];
+	let Some(end) = body
+		.windows(PASTE_END.len())
+		.position(|window| window == PASTE_END)
+	else {
+		return Parsed::Incomplete;
+	};
+
+	let text = String::from_utf8_lossy(&body[..end])
+		.replace("\r\n", "\n")
+		.replace('\r', "\n");
+	let len = PASTE_START.len() + end + PASTE_END.len();
+
+	Parsed::Event(Event::Paste(text), len)
+}
+
+/// Decodes the UTF-8 encoded character at the start of `buf`. A character
+/// whose bytes have not all arrived is incomplete even without `more`: the
+/// terminal has sent its first bytes, so the rest is on the way.
+fn parse_utf8(buf: &[u8]) -> Parsed {
+	let len = match buf[0] {
+		0xc2..=0xdf => 2,
+		0xe0..=0xef => 3,
+		0xf0..=0xf4 => 4,
+		_ => return Parsed::Ignored(1),
+	};
+
+	let available = buf.len().min(len);
+	if !buf[1..available].iter().all(|b| b & 0xc0 == 0x80) {
+		return Parsed::Ignored(1);
+	}
+	if available < len {
+		return Parsed::Incomplete;
+	}
+
+	match core::str::from_utf8(&buf[..len]) {
+		Ok(text) => {
+			let ch = text.chars().next().unwrap_or_default();
+			key(KeyEvent::plain(KeyCode::Char(ch)), len)
+		}
+		// overlong encodings and surrogates
+		Err(_) => Parsed::Ignored(1),
+	}
+}
+
+fn ss3_key(final_byte: u8) -> Option<KeyCode> {
+	Some(match final_byte {
+		b'A' => KeyCode::Up,
+		b'B' => KeyCode::Down,
+		b'C' => KeyCode::Right,
+		b'D' => KeyCode::Left,
+		b'H' => KeyCode::Home,
+		b'F' => KeyCode::End,
+		b'P' => KeyCode::F(1),
+		b'Q' => KeyCode::F(2),
+		b'R' => KeyCode::F(3),
+		b'S' => KeyCode::F(4),
+		_ => return None,
+	})
+}
+
+/// Decodes a CSI sequence from the bytes after `ESC [`. Returns `None` if
+/// the final byte has not arrived yet.
+fn parse_csi(buf: &[u8]) -> Option<Parsed> {
+	let end = buf.iter().position(|b| (0x40..=0x7e).contains(b))?;
+	let (params, final_byte) = (&buf[..end], buf[end]);
+	let len = end + 1;
+
+	// parameters and intermediates only, anything else is garbage
+	if !params.iter().all(|b| (0x20..=0x3f).contains(b)) {
+		return Some(Parsed::Ignored(len));
+	}
+
+	if let ([b'<', mouse @ ..], b'M' | b'm') = (params, final_byte) {
+		return Some(match parse_sgr_mouse(mouse, final_byte == b'm') {
+			Some(event) => Parsed::Event(Event::Mouse(event), len),
+			None => Parsed::Ignored(len),
+		});
+	}
+
+	let mut numbers = [0u16; 2];
+	for (slot, part) in numbers.iter_mut().zip(params.split(|&b| b == b';')) {
+		match parse_number(part) {
+			Some(n) => *slot = n,
+			None => return Some(Parsed::Ignored(len)),
+		}
+	}
+	let modifiers = Modifiers::from_xterm(numbers[1]);
+
+	let code = match (final_byte, numbers[0]) {
+		(b'u', code) => return Some(csi_u(code, modifiers, len)),
+		(b'~', n) => match tilde_key(n) {
+			Some(code) => code,
+			None => return Some(Parsed::Ignored(len)),
+		},
+		(b'Z', _) => KeyCode::BackTab,
+		(byte, _) => match ss3_key(byte) {
+			Some(code) => code,
+			None => return Some(Parsed::Ignored(len)),
+		},
+	};
+
+	Some(key(KeyEvent::new(code, modifiers), len))
+}
+
+fn parse_number(digits: &[u8]) -> Option<u16> {
+	digits.iter().try_fold(0u16, |n, &b| {
+		let digit = (b as char).to_digit(10)?;
+		Some(n.saturating_mul(10).saturating_add(digit as u16))
+	})
+}
+
+/// Decodes the `button ; column ; row` parameters of an SGR mouse report,
+/// which ends in `M`, or `m` for a release.
+fn parse_sgr_mouse(params: &[u8], release: bool) -> Option<MouseEvent> {
+	let mut numbers = params.split(|&b| b == b';').map(parse_number);
+	let (button, column, row) =
+		(numbers.next()??, numbers.next()??, numbers.next()??);
+
+	let mut modifiers = Modifiers::NONE;
+	for (bit, modifier) in [
+		(4, Modifiers::SHIFT),
+		(8, Modifiers::ALT),
+		(16, Modifiers::CTRL),
+	] {
+		if button & bit != 0 {
+			modifiers |= modifier;
+		}
+	}
+
+	let pressed = match button & 0b11 {
+		0 => MouseButton::Left,
+		1 => MouseButton::Middle,
+		_ => MouseButton::Right,
+	};
+	let kind = match button & !0b11100 {
+		64 => MouseEventKind::ScrollUp,
+		65 => MouseEventKind::ScrollDown,
+		66 => MouseEventKind::ScrollLeft,
+		67 => MouseEventKind::ScrollRight,
+		// motion without a button held
+		35 => return None,
+		32..=34 => MouseEventKind::Drag(pressed),
+		0..=2 if release => MouseEventKind::Up(pressed),
+		0..=2 => MouseEventKind::Down(pressed),
+		_ => return None,
+	};
+
+	Some(MouseEvent {
+		kind,
+		column: column.saturating_sub(1),
+		row: row.saturating_sub(1),
+		modifiers,
+	})
+}
+
+/// The keys of `CSI n ~` sequences, as sent by xterm and the Linux console.
+fn tilde_key(n: u16) -> Option<KeyCode> {
+	Some(match n {
+		1 | 7 => KeyCode::Home,
+		2 => KeyCode::Insert,
+		3 => KeyCode::Delete,
+		4 | 8 => KeyCode::End,
+		5 => KeyCode::PageUp,
+		6 => KeyCode::PageDown,
+		11..=15 => KeyCode::F((n - 10) as u8),
+		17..=21 => KeyCode::F((n - 11) as u8),
+		23 | 24 => KeyCode::F((n - 12) as u8),
+		_ => return None,
+	})
+}
+
+/// Decodes `CSI code ; modifiers u`, which terminals with the fixterms or
+/// kitty protocol send for keys that are ambiguous as plain bytes.
+fn csi_u(code: u16, mut modifiers: Modifiers, len: usize) -> Parsed {
+	let code = match code {
+		9 => KeyCode::Tab,
+		13 => KeyCode::Enter,
+		27 => KeyCode::Esc,
+		127 => KeyCode::Backspace,
+		code => match char::from_u32(u32::from(code)) {
+			// shifted letters arrive lowercase
+			Some(ch)
+				if modifiers.contains(Modifiers::SHIFT) &&
+					ch.is_ascii_lowercase() =>
+			{
+				modifiers = modifiers.without(Modifiers::SHIFT);
+				KeyCode::Char(ch.to_ascii_uppercase())
+			}
+			Some(ch) => KeyCode::Char(ch),
+			None => return Parsed::Ignored(len),
+		},
+	};
+
+	// spelled like the legacy bytes for the same keys
+	key(normalize(KeyEvent::new(code, modifiers)), len)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_key(bytes: &[u8]) -> (KeyEvent, usize) {
+		match parse_event(bytes, false) {
+			Parsed::Event(Event::Key(key), len) => (key, len),
+			other => panic!("{bytes:?} parsed to {other:?}"),
+		}
+	}
+
+	#[test]
+	fn escape_sequences() {
+		let ctrl = |code| KeyEvent::new(code, Modifiers::CTRL);
+
+		assert_eq!(parse_key(b"\x1b[A"), (KeyEvent::plain(KeyCode::Up), 3));
+		assert_eq!(parse_key(b"\x1bOP"), (KeyEvent::plain(KeyCode::F(1)), 3));
+		assert_eq!(parse_key(b"\x1b[1;5C"), (ctrl(KeyCode::Right), 6));
+		assert_eq!(
+			parse_key(b"\x1b[6~x"),
+			(KeyEvent::plain(KeyCode::PageDown), 4)
+		);
+		assert_eq!(parse_key(b"\x1b[24;2~").0.code, KeyCode::F(12));
+		assert_eq!(parse_key(b"\x1b[97;5u"), (ctrl(KeyCode::Char('a')), 7));
+		assert_eq!(parse_key(b"\x1b[Z").0.code, KeyCode::BackTab);
+		assert_eq!(parse_key(b"\x1b[91;5u").0, KeyEvent::plain(KeyCode::Esc));
+		assert_eq!(parse_event(b"\x1b[?1;2c", false), Parsed::Ignored(7));
+	}
+
+	#[test]
+	fn escape_and_alt() {
+		assert_eq!(parse_event(b"\x1b", true), Parsed::Incomplete);
+		assert_eq!(parse_event(b"\x1b[1;", true), Parsed::Incomplete);
+		assert_eq!(parse_key(b"\x1b"), (KeyEvent::plain(KeyCode::Esc), 1));
+		assert_eq!(
+			parse_key(b"\x1bx"),
+			(KeyEvent::new(KeyCode::Char('x'), Modifiers::ALT), 2)
+		);
+		assert_eq!(
+			parse_key(b"\x1b[1;"),
+			(KeyEvent::new(KeyCode::Char('['), Modifiers::ALT), 2)
+		);
+		assert_eq!(
+			parse_key(b"\x7f"),
+			(KeyEvent::plain(KeyCode::Backspace), 1)
+		);
+	}
+
+	#[test]
+	fn utf8() {
+		let char_key = |ch| KeyEvent::plain(KeyCode::Char(ch));
+
+		assert_eq!(parse_key("äx".as_bytes()), (char_key('ä'), 2));
+		assert_eq!(parse_key("語".as_bytes()), (char_key('語'), 3));
+		assert_eq!(parse_key("🦀".as_bytes()), (char_key('🦀'), 4));
+		assert_eq!(
+			parse_event(&"語".as_bytes()[..2], false),
+			Parsed::Incomplete
+		);
+		assert_eq!(parse_event(b"\xe8x", false), Parsed::Ignored(1));
+		assert_eq!(parse_event(b"\xed\xa0\x80", false), Parsed::Ignored(1));
+		assert_eq!(
+			parse_key("\x1bé".as_bytes()),
+			(KeyEvent::new(KeyCode::Char('é'), Modifiers::ALT), 3)
+		);
+	}
+
+	#[test]
+	fn sgr_mouse() {
+		let mouse = |bytes: &[u8]| match parse_event(bytes, false) {
+			Parsed::Event(Event::Mouse(event), len) => {
+				assert_eq!(len, bytes.len());
+				(event.kind, event.column, event.row, event.modifiers)
+			}
+			other => panic!("{bytes:?} parsed to {other:?}"),
+		};
+		let left = MouseButton::Left;
+
+		assert_eq!(
+			mouse(b"\x1b[<0;10;5M"),
+			(MouseEventKind::Down(left), 9, 4, Modifiers::NONE)
+		);
+		assert_eq!(
+			mouse(b"\x1b[<0;10;5m"),
+			(MouseEventKind::Up(left), 9, 4, Modifiers::NONE)
+		);
+		assert_eq!(
+			mouse(b"\x1b[<48;300;2M"),
+			(MouseEventKind::Drag(left), 299, 1, Modifiers::CTRL)
+		);
+		assert_eq!(mouse(b"\x1b[<65;1;1M").0, MouseEventKind::ScrollDown);
+		assert_eq!(parse_event(b"\x1b[<35;1;1M", false), Parsed::Ignored(10));
+	}
+
+	#[test]
+	fn bracketed_paste() {
+		let paste = b"\x1b[200~a\x1b[A\r\nb\rc\x1b[201~x";
+
+		assert_eq!(
+			parse_event(paste, false),
+			Parsed::Event(
+				Event::Paste("a\x1b[A\nb\nc".into()),
+				paste.len() - 1
+			)
+		);
+		assert_eq!(parse_event(&paste[..12], false), Parsed::Incomplete);
+	}
+}
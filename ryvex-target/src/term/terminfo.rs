@@ -0,0 +1,242 @@
+//! A reader for compiled terminfo entries, the files `tic` writes below
+//! `/usr/share/terminfo`. Only the numbers and the extended capabilities are
+//! kept, which is where the few things the editor asks about live.
+
+use alloc::{
+	collections::BTreeMap,
+	string::String,
+	vec::Vec,
+};
+
+/// The header magic of entries with 16 bit numbers.
+const MAGIC_LEGACY: i16 = 0o432;
+/// The header magic of entries with 32 bit numbers, written by ncurses 6.1
+/// and later.
+const MAGIC_32BIT: i16 = 0o1036;
+
+/// The position of `colors` among the standard numbers.
+const MAX_COLORS: usize = 13;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Terminfo {
+	/// The standard numbers in their fixed order, negative when absent.
+	numbers:      Vec<i32>,
+	ext_booleans: Vec<String>,
+	ext_numbers:  BTreeMap<String, i32>,
+	ext_strings:  BTreeMap<String, Vec<u8>>,
+}
+
+impl Terminfo {
+	/// Parses a compiled entry, or returns `None` if `bytes` is not one.
+	pub fn parse(bytes: &[u8]) -> Option<Terminfo> {
+		let mut r = Reader { bytes, pos: 0 };
+
+		let wide = match r.i16()? {
+			MAGIC_LEGACY => false,
+			MAGIC_32BIT => true,
+			_ => return None,
+		};
+		let name_size = r.count()?;
+		let bool_count = r.count()?;
+		let num_count = r.count()?;
+		let str_count = r.count()?;
+		let str_size = r.count()?;
+
+		r.skip(name_size + bool_count)?;
+		r.align();
+		let numbers = (0..num_count)
+			.map(|_| r.number(wide))
+			.collect::<Option<Vec<_>>>()?;
+		r.skip(str_count * 2 + str_size)?;
+		r.align();
+
+		let mut info = Terminfo {
+			numbers,
+			..Terminfo::default()
+		};
+		// entries from before the extended format end here
+		if r.pos < bytes.len() {
+			info.parse_extended(&mut r, wide)?;
+		}
+		Some(info)
+	}
+
+	fn parse_extended(&mut self, r: &mut Reader, wide: bool) -> Option<()> {
+		let bool_count = r.count()?;
+		let num_count = r.count()?;
+		let str_count = r.count()?;
+		let offset_count = r.count()?;
+		let table_size = r.count()?;
+
+		let booleans = r.take(bool_count)?.to_vec();
+		r.align();
+		let numbers = (0..num_count)
+			.map(|_| r.number(wide))
+			.collect::<Option<Vec<_>>>()?;
+		let offsets = (0..offset_count)
+			.map(|_| r.i16())
+			.collect::<Option<Vec<_>>>()?;
+		let table = r.take(table_size)?;
+
+		let (values, names) = offsets.split_at_checked(str_count)?;
+		// the names follow the last of the values
+		let names_start = values
+			.iter()
+			.filter_map(|&offset| {
+				let value = string_at(table, offset)?;
+				Some(offset as usize + value.len() + 1)
+			})
+			.max()
+			.unwrap_or(0);
+		let values: Vec<Option<&[u8]>> = values
+			.iter()
+			.map(|&offset| string_at(table, offset))
+			.collect();
+		let mut names = names.iter().map(|&offset| {
+			string_at(table.get(names_start..)?, offset)
+				.map(|name| String::from_utf8_lossy(name).into_owned())
+		});
+
+		for set in booleans {
+			let name = names.next()??;
+			if set == 1 {
+				self.ext_booleans.push(name);
+			}
+		}
+		for number in numbers {
+			let name = names.next()??;
+			if number >= 0 {
+				self.ext_numbers.insert(name, number);
+			}
+		}
+		for value in values {
+			let name = names.next()??;
+			if let Some(value) = value {
+				self.ext_strings.insert(name, value.to_vec());
+			}
+		}
+		Some(())
+	}
+
+	/// How many colors the terminal has, the `colors` capability.
+	pub fn max_colors(&self) -> Option<i32> {
+		self.numbers.get(MAX_COLORS).copied().filter(|&n| n >= 0)
+	}
+
+	/// Whether an extended boolean like `Tc` is set.
+	pub fn has_ext_flag(&self, name: &str) -> bool {
+		self.ext_booleans.iter().any(|flag| flag == name)
+	}
+
+	pub fn ext_number(&self, name: &str) -> Option<i32> {
+		self.ext_numbers.get(name).copied()
+	}
+
+	/// The value of an extended string like `Ss`, still with its `%`
+	/// parameters.
+	pub fn ext_string(&self, name: &str) -> Option<&[u8]> {
+		self.ext_strings.get(name).map(Vec::as_slice)
+	}
+}
+
+/// The nul terminated string at `offset` in `table`; negative offsets mark
+/// absent and cancelled capabilities.
+fn string_at(table: &[u8], offset: i16) -> Option<&[u8]> {
+	let rest = table.get(usize::try_from(offset).ok()?..)?;
+	let len = rest.iter().position(|&b| b == 0)?;
+	Some(&rest[..len])
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	pos:   usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+		let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+		self.pos += len;
+		Some(bytes)
+	}
+
+	fn skip(&mut self, len: usize) -> Option<()> {
+		self.take(len).map(|_| ())
+	}
+
+	/// Sections start at even offsets.
+	fn align(&mut self) {
+		self.pos += self.pos % 2;
+	}
+
+	fn i16(&mut self) -> Option<i16> {
+		self.take(2).map(|b| i16::from_le_bytes([b[0], b[1]]))
+	}
+
+	fn count(&mut self) -> Option<usize> {
+		usize::try_from(self.i16()?).ok()
+	}
+
+	fn number(&mut self, wide: bool) -> Option<i32> {
+		if wide {
+			self.take(4)
+				.map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+		} else {
+			self.i16().map(i32::from)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// An entry named `t` with `colors#256` and the extended `Tc`, `Ss` and
+	/// `Sync` capabilities, laid out like `tic` does it.
+	fn entry() -> Vec<u8> {
+		let mut b = Vec::new();
+		let words = |b: &mut Vec<u8>, words: &[i16]| {
+			for word in words {
+				b.extend_from_slice(&word.to_le_bytes());
+			}
+		};
+
+		// header: 2 name bytes, 1 boolean, 14 numbers, no strings
+		words(&mut b, &[MAGIC_LEGACY, 2, 1, 14, 0, 0]);
+		b.extend_from_slice(b"t\0");
+		b.push(0);
+		b.push(0); // padding
+		let mut numbers = [-1; 14];
+		numbers[MAX_COLORS] = 256;
+		words(&mut b, &numbers);
+
+		// extended: 1 boolean, no numbers, 2 strings and 3 names
+		let values = b"\x1b[%p1%d q\0\x1b[?2026%?%p1%{1}%-%tl%eh%;\0";
+		let names = b"Tc\0Ss\0Sync\0";
+		let table_size = (values.len() + names.len()) as i16;
+		words(&mut b, &[1, 0, 2, 5, table_size]);
+		b.push(1);
+		b.push(0); // padding
+		words(&mut b, &[0, 10, 0, 3, 6]);
+		b.extend_from_slice(values);
+		b.extend_from_slice(names);
+		b
+	}
+
+	#[test]
+	fn extended() {
+		let info = Terminfo::parse(&entry()).unwrap();
+
+		assert_eq!(info.max_colors(), Some(256));
+		assert!(info.has_ext_flag("Tc"));
+		assert!(!info.has_ext_flag("RGB"));
+		assert_eq!(info.ext_string("Ss"), Some(&b"\x1b[%p1%d q"[..]));
+		assert!(info.ext_string("Sync").is_some());
+	}
+
+	#[test]
+	fn truncated() {
+		let entry = entry();
+		assert!(Terminfo::parse(&entry[..20]).is_none());
+		assert!(Terminfo::parse(b"not terminfo").is_none());
+	}
+}
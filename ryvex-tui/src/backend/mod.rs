@@ -2,6 +2,8 @@ pub mod term;
 #[cfg(test)]
 pub mod test;
 
+use core::ops::Range;
+
 use ryvex_target::std::Result;
 use ryvex_ui::graphics::{
 	CursorKind,
@@ -21,4 +23,24 @@ pub trait Backend {
 	fn clear(&mut self) -> Result<()>;
 	fn size(&self) -> Result<Rect>;
 	fn flush(&mut self) -> Result<()>;
+	/// Whether the terminal can scroll part of the screen with
+	/// [`Backend::scroll_region_up`] and [`Backend::scroll_region_down`].
+	fn supports_scroll_region(&self) -> bool;
+	/// Moves the rows `region` up by `amount`, blanking the rows at the
+	/// bottom.
+	fn scroll_region_up(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> Result<()>;
+	/// Moves the rows `region` down by `amount`, blanking the rows at the top.
+	fn scroll_region_down(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> Result<()>;
+	/// Holds back what is drawn until [`Backend::end_update`], if the
+	/// terminal can, so a frame shows up at once.
+	fn begin_update(&mut self) -> Result<()>;
+	fn end_update(&mut self) -> Result<()>;
 }
@@ -1,3 +1,5 @@
+use core::ops::Range;
+
 use super::Backend;
 use ryvex_target::{
 	execute,
@@ -11,33 +13,108 @@ use ryvex_target::{
 		self,
 		term::Handle,
 	},
-	term::command::{
-		cursor::{
-			Hide,
-			MoveTo,
-			SetCursorStyle,
-			Show,
-		},
-		terminal::{
-			Clear,
-			ClearType,
-			Print,
+	term::{
+		capabilities::Capabilities,
+		command::{
+			cursor::{
+				Hide,
+				MoveTo,
+				SetCursorStyle,
+				Show,
+			},
+			style::{
+				Attribute,
+				SetAttribute,
+				SetBackgroundColor,
+				SetForegroundColor,
+			},
+			terminal::{
+				BeginSynchronizedUpdate,
+				Clear,
+				ClearType,
+				EndSynchronizedUpdate,
+				Print,
+				ResetScrollRegion,
+				ScrollDown,
+				ScrollUp,
+				SetScrollRegion,
+			},
 		},
 	},
 };
-use ryvex_ui::graphics::CursorKind;
+use ryvex_ui::{
+	graphics::CursorKind,
+	style::{
+		Color,
+		Modifier,
+	},
+};
 
 pub struct TerminalBackend {
-	buffer: TargetOutWriter,
-	fd:     Handle,
+	buffer:       TargetOutWriter,
+	fd:           Handle,
+	capabilities: Capabilities,
 }
 
 impl TerminalBackend {
-	pub fn new(fd: Handle) -> Self {
+	pub fn new(fd: Handle, capabilities: Capabilities) -> Self {
 		Self {
 			fd,
 			buffer: TargetOutWriter::default(),
+			capabilities,
+		}
+	}
+}
+
+impl TerminalBackend {
+	/// Emits the attribute changes that turn `from` into `to`.
+	fn set_modifier(
+		&mut self,
+		from: Modifier,
+		to: Modifier,
+	) -> Result<(), IoError> {
+		let removed = from.without(to);
+		let added = to.without(from);
+
+		// there is no way to end just one of bold and dim, so the one that
+		// stays is turned on again
+		let intensity = Modifier::BOLD | Modifier::DIM;
+		let reapplied = if removed.intersects(intensity) {
+			queue!(self.buffer, SetAttribute(Attribute::NormalIntensity))?;
+			to & intensity
+		} else {
+			Modifier::NONE
+		};
+
+		for (modifier, off) in [
+			(Modifier::ITALIC, Attribute::NoItalic),
+			(Modifier::UNDERLINED, Attribute::NoUnderline),
+			(Modifier::BLINK, Attribute::NoBlink),
+			(Modifier::REVERSED, Attribute::NoReverse),
+			(Modifier::HIDDEN, Attribute::NoHidden),
+			(Modifier::CROSSED_OUT, Attribute::NotCrossedOut),
+		] {
+			if removed.contains(modifier) {
+				queue!(self.buffer, SetAttribute(off))?;
+			}
+		}
+
+		for (modifier, on) in [
+			(Modifier::BOLD, Attribute::Bold),
+			(Modifier::DIM, Attribute::Dim),
+			(Modifier::ITALIC, Attribute::Italic),
+			(Modifier::UNDERLINED, Attribute::Underlined),
+			(Modifier::BLINK, Attribute::Blink),
+			(Modifier::REVERSED, Attribute::Reverse),
+			(Modifier::HIDDEN, Attribute::Hidden),
+			(Modifier::CROSSED_OUT, Attribute::CrossedOut),
+		] {
+			if (added | reapplied).contains(modifier) {
+				queue!(self.buffer, SetAttribute(on))?;
+			}
 		}
+
+		Ok(())
 	}
 }
 
@@ -56,16 +133,42 @@ impl Backend for TerminalBackend {
 	where
 		I: Iterator<Item = (u16, u16, &'a crate::buffer::Cell)>,
 	{
+		// every draw starts and ends with the terminal's default style
+		let mut fg = Color::Reset;
+		let mut bg = Color::Reset;
+		let mut modifier = Modifier::NONE;
+
 		let mut last_pos: Option<(u16, u16)> = None;
 		for (x, y, cell) in content {
 			if !matches!(last_pos, Some(p) if x == p.0 + 1 && y == p.1) {
-				execute!(self.buffer, MoveTo(y + 1, x + 1))?;
+				queue!(self.buffer, MoveTo(y + 1, x + 1))?;
 			}
 			last_pos = Some((x, y));
 
+			if cell.modifier != modifier {
+				self.set_modifier(modifier, cell.modifier)?;
+				modifier = cell.modifier;
+			}
+			// compared after fitting, so colors that end up the same on this
+			// terminal are not set again
+			let depth = self.capabilities.color_depth;
+			let (cell_fg, cell_bg) = (cell.fg.fit(depth), cell.bg.fit(depth));
+			if cell_fg != fg {
+				queue!(self.buffer, SetForegroundColor(cell_fg))?;
+				fg = cell_fg;
+			}
+			if cell_bg != bg {
+				queue!(self.buffer, SetBackgroundColor(cell_bg))?;
+				bg = cell_bg;
+			}
+
 			queue!(self.buffer, Print(&cell.symbol))?;
 		}
 
+		if fg != Color::Reset || bg != Color::Reset || !modifier.is_empty() {
+			queue!(self.buffer, SetAttribute(Attribute::Reset))?;
+		}
+
 		Ok(())
 	}
 
@@ -74,6 +177,9 @@ impl Backend for TerminalBackend {
 		kind: ryvex_ui::graphics::CursorKind,
 	) -> super::Result<()> {
 		execute!(self.buffer, Show)?;
+		if !self.capabilities.cursor_style {
+			return Ok(());
+		}
 
 		match kind {
 			CursorKind::Block => {
@@ -95,7 +201,7 @@ impl Backend for TerminalBackend {
 	}
 
 	fn set_cursor(&mut self, x: u16, y: u16) -> super::Result<()> {
-		Ok(execute!(self.buffer, MoveTo(x, y))?)
+		Ok(execute!(self.buffer, MoveTo(y + 1, x + 1))?)
 	}
 
 	fn clear(&mut self) -> super::Result<()> {
@@ -113,4 +219,48 @@ impl Backend for TerminalBackend {
 	fn hide_cursor(&mut self) -> super::Result<()> {
 		Ok(execute!(self.buffer, Hide)?)
 	}
+
+	fn supports_scroll_region(&self) -> bool {
+		target::term::supports_ansi()
+	}
+
+	fn scroll_region_up(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> super::Result<()> {
+		Ok(queue!(
+			self.buffer,
+			SetScrollRegion(region.start, region.end),
+			ScrollUp(amount),
+			ResetScrollRegion
+		)?)
+	}
+
+	fn scroll_region_down(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> super::Result<()> {
+		Ok(queue!(
+			self.buffer,
+			SetScrollRegion(region.start, region.end),
+			ScrollDown(amount),
+			ResetScrollRegion
+		)?)
+	}
+
+	fn begin_update(&mut self) -> super::Result<()> {
+		if self.capabilities.synchronized_output {
+			queue!(self.buffer, BeginSynchronizedUpdate)?;
+		}
+		Ok(())
+	}
+
+	fn end_update(&mut self) -> super::Result<()> {
+		if self.capabilities.synchronized_output {
+			queue!(self.buffer, EndSynchronizedUpdate)?;
+		}
+		Ok(())
+	}
 }
@@ -1,44 +1,126 @@
 use super::Backend;
+use crate::buffer::{
+	Buffer,
+	Cell,
+};
+use core::ops::Range;
 use ryvex_target::std::Result;
+use ryvex_ui::graphics::{
+	CursorKind,
+	Rect,
+};
 
-pub struct TestBackend;
+/// A backend that draws into a [`Buffer`] instead of a terminal, so tests
+/// can look at the screen and at how much was drawn to get there.
+pub struct TestBackend {
+	buffer:        Buffer,
+	cursor:        (u16, u16),
+	scroll_region: bool,
+	/// Cells drawn since the last [`TestBackend::take_drawn`].
+	drawn:         usize,
+}
+
+impl TestBackend {
+	pub fn new(width: u16, height: u16) -> Self {
+		Self {
+			buffer:        Buffer::empty(Rect {
+				x: 0,
+				y: 0,
+				width,
+				height,
+			}),
+			cursor:        (0, 0),
+			scroll_region: false,
+			drawn:         0,
+		}
+	}
+
+	/// Lets the terminal scroll rows instead of drawing them again.
+	pub fn with_scroll_region(mut self) -> Self {
+		self.scroll_region = true;
+		self
+	}
+
+	pub fn buffer(&self) -> &Buffer {
+		&self.buffer
+	}
+
+	/// Number of cells drawn since the last call.
+	pub fn take_drawn(&mut self) -> usize {
+		core::mem::take(&mut self.drawn)
+	}
+}
 
 impl Backend for TestBackend {
-	fn draw<'a, I>(&mut self, _content: I) -> Result<()>
+	fn draw<'a, I>(&mut self, content: I) -> Result<()>
 	where
-		I: Iterator<Item = (u16, u16, &'a crate::buffer::Cell)>,
+		I: Iterator<Item = (u16, u16, &'a Cell)>,
 	{
-		todo!("TestBackend")
+		for (x, y, cell) in content {
+			let idx = self.buffer.index_of(x, y);
+			self.buffer.content[idx] = cell.clone();
+			self.drawn += 1;
+		}
+		Ok(())
 	}
 
-	fn show_cursor(
-		&mut self,
-		_kind: ryvex_ui::graphics::CursorKind,
-	) -> Result<()> {
-		todo!("TestBackend")
+	fn show_cursor(&mut self, _kind: CursorKind) -> Result<()> {
+		Ok(())
 	}
 
 	fn get_cursor(&mut self) -> Result<(u16, u16)> {
-		todo!("TestBackend")
+		Ok(self.cursor)
 	}
 
-	fn set_cursor(&mut self, _x: u16, _y: u16) -> Result<()> {
-		todo!("TestBackend")
+	fn set_cursor(&mut self, x: u16, y: u16) -> Result<()> {
+		self.cursor = (x, y);
+		Ok(())
 	}
 
 	fn clear(&mut self) -> Result<()> {
-		todo!("TestBackend")
+		self.buffer.reset();
+		Ok(())
 	}
 
-	fn size(&self) -> Result<ryvex_ui::graphics::Rect> {
-		todo!("TestBackend")
+	fn size(&self) -> Result<Rect> {
+		Ok(self.buffer.area)
 	}
 
 	fn flush(&mut self) -> Result<()> {
-		todo!("TestBackend")
+		Ok(())
 	}
 
 	fn hide_cursor(&mut self) -> Result<()> {
-		todo!("TestBackend")
+		Ok(())
+	}
+
+	fn supports_scroll_region(&self) -> bool {
+		self.scroll_region
+	}
+
+	fn scroll_region_up(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> Result<()> {
+		self.buffer.scroll_up(region, amount);
+		Ok(())
+	}
+
+	fn scroll_region_down(
+		&mut self,
+		region: Range<u16>,
+		amount: u16,
+	) -> Result<()> {
+		self.buffer.scroll_down(region, amount);
+		Ok(())
+	}
+
+	fn begin_update(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	fn end_update(&mut self) -> Result<()> {
+		Ok(())
 	}
 }
@@ -6,9 +6,19 @@ use alloc::{
 	vec,
 	vec::Vec,
 };
-use core::fmt;
+use core::{
+	fmt,
+	ops::Range,
+};
 
-use ryvex_ui::graphics::Rect;
+use ryvex_ui::{
+	graphics::Rect,
+	style::{
+		Color,
+		Modifier,
+		Style,
+	},
+};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Buffer {
@@ -41,18 +51,72 @@ impl Buffer {
 			return;
 		}
 
-		let mut idx = self.index_of(x, y);
-		for c in string.as_ref().chars() {
+		let start = self.index_of(x, y);
+		for (idx, c) in (start..self.content.len()).zip(string.as_ref().chars())
+		{
 			self.content[idx].set_symbol(&c.to_string());
+		}
+	}
 
-			if idx + 1 > self.content.len() {
-				break;
-			}
+	/// Writes one symbol per cell, starting at `(x, y)`. Unlike
+	/// [`Buffer::set_string`] this keeps multi-char symbols such as a letter
+	/// with combining accents in a single cell.
+	pub fn set_symbols<'a, I>(&mut self, x: u16, y: u16, symbols: I)
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
+		if !self.in_bounds(x, y) {
+			return;
+		}
+
+		let start = self.index_of(x, y);
+		for (idx, symbol) in (start..self.content.len()).zip(symbols) {
+			self.content[idx].set_symbol(symbol);
+		}
+	}
 
-			idx += 1;
+	/// Patches `style` onto every cell of `area` within the buffer.
+	pub fn set_style(&mut self, area: Rect, style: Style) {
+		for y in area.top()..area.bottom() {
+			for x in area.left()..area.right() {
+				if self.in_bounds(x, y) {
+					let idx = self.index_of(x, y);
+					self.content[idx].set_style(style);
+				}
+			}
 		}
 	}
 
+	/// Moves the rows `region` up by `amount` like a terminal scrolls them:
+	/// the top ones drop off and blank rows come in at the bottom.
+	pub fn scroll_up(&mut self, region: Range<u16>, amount: u16) {
+		let width = self.area.width as usize;
+		let rows = self.rows_mut(region);
+		let amount = (amount as usize * width).min(rows.len());
+		rows.rotate_left(amount);
+		let blank = rows.len() - amount;
+		rows[blank..].iter_mut().for_each(Cell::reset);
+	}
+
+	/// Moves the rows `region` down by `amount`, with blank rows coming in at
+	/// the top.
+	pub fn scroll_down(&mut self, region: Range<u16>, amount: u16) {
+		let width = self.area.width as usize;
+		let rows = self.rows_mut(region);
+		let amount = (amount as usize * width).min(rows.len());
+		rows.rotate_right(amount);
+		rows[..amount].iter_mut().for_each(Cell::reset);
+	}
+
+	/// The cells of the rows `region`, clamped to the buffer.
+	fn rows_mut(&mut self, region: Range<u16>) -> &mut [Cell] {
+		let width = self.area.width as usize;
+		let top = region.start.clamp(self.area.top(), self.area.bottom());
+		let bottom = region.end.clamp(top, self.area.bottom());
+		let (top, bottom) = (top - self.area.y, bottom - self.area.y);
+		&mut self.content[top as usize * width..bottom as usize * width]
+	}
+
 	pub fn in_bounds(&self, x: u16, y: u16) -> bool {
 		x >= self.area.left() &&
 			x < self.area.right() &&
@@ -92,15 +156,54 @@ impl Buffer {
 	}
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Cell {
-	pub symbol: String,
+	pub symbol:   String,
+	pub fg:       Color,
+	pub bg:       Color,
+	pub modifier: Modifier,
+}
+
+/// A blank cell, like a cleared screen has.
+impl Default for Cell {
+	fn default() -> Self {
+		Cell {
+			symbol:   String::from(" "),
+			fg:       Color::Reset,
+			bg:       Color::Reset,
+			modifier: Modifier::NONE,
+		}
+	}
 }
 
 impl Cell {
 	pub fn reset(&mut self) {
 		self.symbol.clear();
 		self.symbol.push(' ');
+		self.fg = Color::Reset;
+		self.bg = Color::Reset;
+		self.modifier = Modifier::NONE;
+	}
+
+	pub fn set_style(&mut self, style: Style) -> &mut Cell {
+		if let Some(fg) = style.fg {
+			self.fg = fg;
+		}
+		if let Some(bg) = style.bg {
+			self.bg = bg;
+		}
+		self.modifier =
+			self.modifier.without(style.sub_modifier) | style.add_modifier;
+		self
+	}
+
+	pub fn style(&self) -> Style {
+		Style {
+			fg:           Some(self.fg),
+			bg:           Some(self.bg),
+			add_modifier: self.modifier,
+			sub_modifier: Modifier::NONE,
+		}
 	}
 
 	pub fn set_symbol(&mut self, symbol: &str) -> &mut Cell {
@@ -1,3 +1,15 @@
+use alloc::{
+	vec,
+	vec::Vec,
+};
+use core::{
+	hash::{
+		Hash,
+		Hasher,
+	},
+	ops::Range,
+};
+
 use ryvex_ui::graphics::{
 	CursorKind,
 	Rect,
@@ -5,7 +17,10 @@ use ryvex_ui::graphics::{
 
 use crate::{
 	backend::Backend,
-	buffer::Buffer,
+	buffer::{
+		Buffer,
+		Cell,
+	},
 };
 
 use ryvex_target::std::Result;
@@ -38,6 +53,7 @@ where
 		cursor_position: Option<(u16, u16)>,
 		cursor_kind: CursorKind,
 	) -> Result<()> {
+		self.backend.begin_update()?;
 		self.backend.hide_cursor()?;
 		self.flush()?;
 
@@ -45,6 +61,7 @@ where
 			self.backend.set_cursor(x, y)?;
 		}
 		self.backend.show_cursor(cursor_kind)?;
+		self.backend.end_update()?;
 
 		self.buffers[1 - self.current].reset();
 		self.current = 1 - self.current;
@@ -54,11 +71,33 @@ where
 		Ok(())
 	}
 
+	/// Draws what changed since the last frame. Rows that only moved, like
+	/// the text when the view scrolls, are scrolled on the terminal instead
+	/// of being drawn again.
 	pub fn flush(&mut self) -> Result<()> {
-		let previous_buffer = &self.buffers[1 - self.current];
-		let current_buffer = &self.buffers[self.current];
-		let updates = previous_buffer.diff(current_buffer);
+		let [first, second] = &mut self.buffers;
+		let (previous_buffer, current_buffer) = if self.current == 0 {
+			(second, first)
+		} else {
+			(first, second)
+		};
 
+		if self.backend.supports_scroll_region() {
+			if let Some(Scroll { region, shift }) =
+				find_scroll(previous_buffer, current_buffer)
+			{
+				let amount = shift.unsigned_abs() as u16;
+				if shift > 0 {
+					self.backend.scroll_region_up(region.clone(), amount)?;
+					previous_buffer.scroll_up(region, amount);
+				} else {
+					self.backend.scroll_region_down(region.clone(), amount)?;
+					previous_buffer.scroll_down(region, amount);
+				}
+			}
+		}
+
+		let updates = previous_buffer.diff(current_buffer);
 		self.backend.draw(updates.into_iter())
 	}
 
@@ -68,6 +107,14 @@ where
 		Ok(())
 	}
 
+	/// Reallocates both buffers for a terminal of size `area` and clears the
+	/// screen, so that the next draw repaints everything.
+	pub fn resize(&mut self, area: Rect) -> Result<()> {
+		self.buffers = [Buffer::empty(area), Buffer::empty(area)];
+		self.current = 0;
+		self.backend.clear()
+	}
+
 	pub fn current_buffer_mut(&mut self) -> &mut Buffer {
 		&mut self.buffers[self.current]
 	}
@@ -76,3 +123,195 @@ where
 		self.backend.size()
 	}
 }
+
+/// Scrolling costs about as much as drawing a short row, so it is only
+/// worth it if it saves drawing at least this many rows.
+const MIN_SCROLL_SAVING: usize = 2;
+
+/// Rows `region` of the screen, moved up by `shift` rows, or down if it is
+/// negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Scroll {
+	region: Range<u16>,
+	shift:  i32,
+}
+
+/// Finds the scroll that leaves the fewest rows of `previous` to draw again
+/// to get to `next`, if it saves enough of them.
+fn find_scroll(previous: &Buffer, next: &Buffer) -> Option<Scroll> {
+	let width = previous.area.width as usize;
+	if width == 0 || previous.area != next.area {
+		return None;
+	}
+
+	let rows = |buffer: &Buffer| {
+		buffer
+			.content
+			.chunks(width)
+			.map(hash_row)
+			.collect::<Vec<_>>()
+	};
+	let (prev, next) = (rows(previous), rows(next));
+	let blank = hash_row(&vec![Cell::default(); width]);
+	let height = prev.len() as i32;
+
+	let mut best: Option<(usize, Range<i32>, i32)> = None;
+	for distance in 1..height {
+		for shift in [distance, -distance] {
+			let moved = |y: i32| {
+				(0..height).contains(&(y + shift)) &&
+					next[y as usize] == prev[(y + shift) as usize]
+			};
+
+			let mut y = 0;
+			while y < height {
+				if !moved(y) {
+					y += 1;
+					continue;
+				}
+				let start = y;
+				while y < height && moved(y) {
+					y += 1;
+				}
+
+				// the rows that moved and the ones they moved over
+				let region = start.min(start + shift)..y.max(y + shift);
+				let shifted = |y: i32| {
+					if region.contains(&(y + shift)) {
+						prev[(y + shift) as usize]
+					} else {
+						blank
+					}
+				};
+				let before = region
+					.clone()
+					.filter(|&y| next[y as usize] != prev[y as usize])
+					.count();
+				let after = region
+					.clone()
+					.filter(|&y| next[y as usize] != shifted(y))
+					.count();
+				let saving = before.saturating_sub(after);
+
+				if best.as_ref().is_none_or(|(most, ..)| saving > *most) {
+					best = Some((saving, region, shift));
+				}
+			}
+		}
+	}
+
+	let (saving, region, shift) = best?;
+	(saving >= MIN_SCROLL_SAVING).then(|| Scroll {
+		region: region.start as u16 + previous.area.y..
+			region.end as u16 + previous.area.y,
+		shift,
+	})
+}
+
+/// FNV-1a, enough to tell rows apart: two rows taken for the same only cost
+/// a scroll that did not help.
+struct RowHasher(u64);
+
+impl Hasher for RowHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+		}
+	}
+}
+
+fn hash_row(row: &[Cell]) -> u64 {
+	let mut hasher = RowHasher(0xcbf2_9ce4_8422_2325);
+	row.hash(&mut hasher);
+	hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::test::TestBackend;
+
+	fn screen(rows: &[&str]) -> Buffer {
+		let mut buffer = Buffer::empty(Rect {
+			x:      0,
+			y:      0,
+			width:  4,
+			height: rows.len() as u16,
+		});
+		for (y, row) in rows.iter().enumerate() {
+			buffer.set_string(0, y as u16, row);
+		}
+		buffer
+	}
+
+	#[test]
+	fn scroll_text_above_status_line() {
+		let mut previous = screen(&["a", "b", "c", "d", "e", "1|1"]);
+		let next = screen(&["c", "d", "e", "f", "g", "3|1"]);
+
+		let scroll = find_scroll(&previous, &next).unwrap();
+		assert_eq!(scroll, Scroll {
+			region: 0..5,
+			shift:  2,
+		});
+
+		previous.scroll_up(scroll.region, 2);
+		// the two new lines and the line number in the status line
+		assert_eq!(previous.diff(&next).len(), 3);
+	}
+
+	#[test]
+	fn scroll_down() {
+		let mut previous = screen(&["a", "b", "c", "d", "e", "1|1"]);
+		let next = screen(&["z", "a", "b", "c", "d", "1|1"]);
+
+		let scroll = find_scroll(&previous, &next).unwrap();
+		assert_eq!(scroll, Scroll {
+			region: 0..5,
+			shift:  -1,
+		});
+
+		previous.scroll_down(scroll.region, 1);
+		assert_eq!(previous.diff(&next).len(), 1);
+	}
+
+	/// Draws `rows` as the next frame and returns how many cells that took.
+	fn draw_frame(
+		terminal: &mut Terminal<TestBackend>,
+		rows: &[&str],
+	) -> usize {
+		for (y, row) in rows.iter().enumerate() {
+			terminal.current_buffer_mut().set_string(0, y as u16, row);
+		}
+		terminal.draw(None, CursorKind::Block).unwrap();
+		terminal.backend.take_drawn()
+	}
+
+	#[test]
+	fn flush_scrolls_the_backend() {
+		let before = ["a", "b", "c", "d", "e", "1|1"];
+		let after = ["c", "d", "e", "f", "g", "3|1"];
+
+		for (backend, drawn) in [
+			(TestBackend::new(4, 6).with_scroll_region(), 3),
+			(TestBackend::new(4, 6), 6),
+		] {
+			let mut terminal = Terminal::new(backend).unwrap();
+			draw_frame(&mut terminal, &before);
+			assert_eq!(draw_frame(&mut terminal, &after), drawn);
+			assert_eq!(terminal.backend.buffer(), &screen(&after));
+		}
+	}
+
+	#[test]
+	fn no_scroll_for_edits() {
+		let previous = screen(&["a", "b", "c", "d", "e", "1|1"]);
+		let next = screen(&["a", "bx", "c", "d", "e", "1|2"]);
+
+		assert_eq!(find_scroll(&previous, &next), None);
+	}
+}
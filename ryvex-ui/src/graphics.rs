@@ -36,6 +36,14 @@ impl Rect {
 		self.y.saturating_add(self.height)
 	}
 
+	#[inline]
+	pub fn contains(self, x: u16, y: u16) -> bool {
+		x >= self.left() &&
+			x < self.right() &&
+			y >= self.top() &&
+			y < self.bottom()
+	}
+
 	pub fn clip_left(self, width: u16) -> Rect {
 		let width = core::cmp::min(width, self.width);
 		Rect {
@@ -2,3 +2,4 @@
 pub extern crate alloc;
 
 pub mod graphics;
+pub mod style;
@@ -0,0 +1,282 @@
+use core::ops::{
+	BitAnd,
+	BitOr,
+	BitOrAssign,
+};
+
+/// A terminal color. The named ones are the 16 colors of the palette the
+/// user configured, so they fit any theme; [`Color::Indexed`] picks from the
+/// 256 color palette and [`Color::Rgb`] asks for true color.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Color {
+	/// The terminal's own foreground or background.
+	#[default]
+	Reset,
+	Black,
+	Red,
+	Green,
+	Yellow,
+	Blue,
+	Magenta,
+	Cyan,
+	Gray,
+	DarkGray,
+	LightRed,
+	LightGreen,
+	LightYellow,
+	LightBlue,
+	LightMagenta,
+	LightCyan,
+	White,
+	Indexed(u8),
+	Rgb(u8, u8, u8),
+}
+
+/// The 16 named colors in palette order, with the RGB values xterm gives
+/// them by default.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+	(Color::Black, (0, 0, 0)),
+	(Color::Red, (205, 0, 0)),
+	(Color::Green, (0, 205, 0)),
+	(Color::Yellow, (205, 205, 0)),
+	(Color::Blue, (0, 0, 238)),
+	(Color::Magenta, (205, 0, 205)),
+	(Color::Cyan, (0, 205, 205)),
+	(Color::Gray, (229, 229, 229)),
+	(Color::DarkGray, (127, 127, 127)),
+	(Color::LightRed, (255, 0, 0)),
+	(Color::LightGreen, (0, 255, 0)),
+	(Color::LightYellow, (255, 255, 0)),
+	(Color::LightBlue, (92, 92, 255)),
+	(Color::LightMagenta, (255, 0, 255)),
+	(Color::LightCyan, (0, 255, 255)),
+	(Color::White, (255, 255, 255)),
+];
+
+/// The levels of each channel in the 6x6x6 color cube of the 256 color
+/// palette, which starts at index 16.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors a terminal can show.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+	/// The 16 named colors.
+	Ansi16,
+	/// The 256 color palette.
+	Indexed256,
+	/// Any RGB color.
+	TrueColor,
+}
+
+impl Color {
+	/// The closest color a terminal with `depth` can show.
+	pub fn fit(self, depth: ColorDepth) -> Color {
+		match (self, depth) {
+			(_, ColorDepth::TrueColor) => self,
+			(Color::Rgb(r, g, b), ColorDepth::Indexed256) => {
+				Color::Indexed(to_indexed(r, g, b))
+			}
+			(Color::Indexed(index), ColorDepth::Ansi16) => {
+				let (r, g, b) = indexed_rgb(index);
+				nearest_ansi(r, g, b)
+			}
+			(Color::Rgb(r, g, b), ColorDepth::Ansi16) => nearest_ansi(r, g, b),
+			_ => self,
+		}
+	}
+}
+
+/// The index of the cube or grayscale ramp entry closest to `(r, g, b)`.
+fn to_indexed(r: u8, g: u8, b: u8) -> u8 {
+	let level = |c: u8| {
+		(0..CUBE_LEVELS.len())
+			.min_by_key(|&i| CUBE_LEVELS[i].abs_diff(c))
+			.unwrap_or(0) as u8
+	};
+	let (ri, gi, bi) = (level(r), level(g), level(b));
+	let cube = 16 + 36 * ri + 6 * gi + bi;
+	let cube_rgb = (
+		CUBE_LEVELS[ri as usize],
+		CUBE_LEVELS[gi as usize],
+		CUBE_LEVELS[bi as usize],
+	);
+
+	// the ramp runs from 8 to 238 in steps of 10
+	let average = (r as u16 + g as u16 + b as u16) / 3;
+	let step = (average.saturating_sub(3) / 10).min(23) as u8;
+	let gray = 8 + 10 * step;
+
+	if distance((r, g, b), (gray, gray, gray)) < distance((r, g, b), cube_rgb) {
+		232 + step
+	} else {
+		cube
+	}
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+	match index {
+		0..=15 => ANSI[index as usize].1,
+		16..=231 => {
+			let i = index - 16;
+			(
+				CUBE_LEVELS[(i / 36) as usize],
+				CUBE_LEVELS[(i / 6 % 6) as usize],
+				CUBE_LEVELS[(i % 6) as usize],
+			)
+		}
+		232..=255 => {
+			let gray = 8 + 10 * (index - 232);
+			(gray, gray, gray)
+		}
+	}
+}
+
+fn nearest_ansi(r: u8, g: u8, b: u8) -> Color {
+	ANSI.iter()
+		.min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+		.map_or(Color::Reset, |(color, _)| *color)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+	let d = |x: u8, y: u8| (x.abs_diff(y) as u32).pow(2);
+	d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Text attributes, combined with `|`.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Modifier(u16);
+
+impl Modifier {
+	pub const NONE: Modifier = Modifier(0);
+	pub const BOLD: Modifier = Modifier(1);
+	pub const DIM: Modifier = Modifier(1 << 1);
+	pub const ITALIC: Modifier = Modifier(1 << 2);
+	pub const UNDERLINED: Modifier = Modifier(1 << 3);
+	pub const BLINK: Modifier = Modifier(1 << 4);
+	pub const REVERSED: Modifier = Modifier(1 << 5);
+	pub const HIDDEN: Modifier = Modifier(1 << 6);
+	pub const CROSSED_OUT: Modifier = Modifier(1 << 7);
+
+	pub fn contains(self, other: Modifier) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	pub fn intersects(self, other: Modifier) -> bool {
+		self.0 & other.0 != 0
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	pub fn without(self, other: Modifier) -> Modifier {
+		Modifier(self.0 & !other.0)
+	}
+}
+
+impl BitOr for Modifier {
+	type Output = Modifier;
+
+	fn bitor(self, rhs: Modifier) -> Modifier {
+		Modifier(self.0 | rhs.0)
+	}
+}
+
+impl BitAnd for Modifier {
+	type Output = Modifier;
+
+	fn bitand(self, rhs: Modifier) -> Modifier {
+		Modifier(self.0 & rhs.0)
+	}
+}
+
+impl BitOrAssign for Modifier {
+	fn bitor_assign(&mut self, rhs: Modifier) {
+		self.0 |= rhs.0;
+	}
+}
+
+/// How text is drawn. A style only says what it changes: unset colors and
+/// modifiers it neither adds nor removes are left as they are when it is
+/// [patched](Style::patch) onto another one.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Style {
+	pub fg:           Option<Color>,
+	pub bg:           Option<Color>,
+	pub add_modifier: Modifier,
+	pub sub_modifier: Modifier,
+}
+
+impl Style {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn fg(mut self, color: Color) -> Self {
+		self.fg = Some(color);
+		self
+	}
+
+	pub fn bg(mut self, color: Color) -> Self {
+		self.bg = Some(color);
+		self
+	}
+
+	pub fn add_modifier(mut self, modifier: Modifier) -> Self {
+		self.sub_modifier = self.sub_modifier.without(modifier);
+		self.add_modifier |= modifier;
+		self
+	}
+
+	pub fn remove_modifier(mut self, modifier: Modifier) -> Self {
+		self.add_modifier = self.add_modifier.without(modifier);
+		self.sub_modifier |= modifier;
+		self
+	}
+
+	/// `other` drawn on top of `self`.
+	pub fn patch(mut self, other: Style) -> Self {
+		self.fg = other.fg.or(self.fg);
+		self.bg = other.bg.or(self.bg);
+		self.add_modifier =
+			self.add_modifier.without(other.sub_modifier) | other.add_modifier;
+		self.sub_modifier =
+			self.sub_modifier.without(other.add_modifier) | other.sub_modifier;
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn patch() {
+		let base = Style::new()
+			.fg(Color::Red)
+			.bg(Color::Black)
+			.add_modifier(Modifier::BOLD | Modifier::ITALIC);
+		let over = Style::new()
+			.fg(Color::Indexed(42))
+			.remove_modifier(Modifier::BOLD);
+		let patched = base.patch(over);
+
+		assert_eq!(patched.fg, Some(Color::Indexed(42)));
+		assert_eq!(patched.bg, Some(Color::Black));
+		assert_eq!(patched.add_modifier, Modifier::ITALIC);
+		assert_eq!(patched.sub_modifier, Modifier::BOLD);
+	}
+
+	#[test]
+	fn fit() {
+		let orange = Color::Rgb(255, 135, 0);
+		assert_eq!(orange.fit(ColorDepth::TrueColor), orange);
+		assert_eq!(orange.fit(ColorDepth::Indexed256), Color::Indexed(208));
+		assert_eq!(orange.fit(ColorDepth::Ansi16), Color::Yellow);
+
+		let gray = Color::Rgb(128, 128, 130);
+		assert_eq!(gray.fit(ColorDepth::Indexed256), Color::Indexed(244));
+		assert_eq!(Color::Indexed(9).fit(ColorDepth::Ansi16), Color::LightRed);
+		assert_eq!(Color::Blue.fit(ColorDepth::Ansi16), Color::Blue);
+	}
+}